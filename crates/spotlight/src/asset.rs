@@ -0,0 +1,134 @@
+use crate::{Error, Filter, Result, util};
+use image::ImageFormat;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Minimum size of a landscape wallpaper. Portrait wallpapers use it transposed.
+pub const WALLPAPER_MIN_SIZE: (u32, u32) = (1920, 1080);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Orientation {
+    Landscape,
+    Portrait,
+    Square,
+}
+
+impl Orientation {
+    pub fn new(width: u32, height: u32) -> Self {
+        match width.cmp(&height) {
+            std::cmp::Ordering::Greater => Orientation::Landscape,
+            std::cmp::Ordering::Less => Orientation::Portrait,
+            std::cmp::Ordering::Equal => Orientation::Square,
+        }
+    }
+}
+
+impl fmt::Display for Orientation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Orientation::Landscape => "landscape",
+            Orientation::Portrait => "portrait",
+            Orientation::Square => "square",
+        })
+    }
+}
+
+/// An image in the Spotlight assets directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpotlightAsset {
+    pub path: PathBuf,
+    pub format: ImageFormat,
+    pub width: u32,
+    pub height: u32,
+    pub orientation: Orientation,
+    /// File size in bytes.
+    pub size: u64,
+    /// Last modification time, which is about when Spotlight downloaded the image.
+    pub modified: Option<SystemTime>,
+}
+
+impl SpotlightAsset {
+    /// Reads the asset at `path`.
+    ///
+    /// Only the image header is read, the image itself is not decoded.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+
+        let metadata = fs::metadata(path).map_err(Error::io(path))?;
+        let reader = util::new_image_reader(path)?;
+        let format = reader
+            .format()
+            .ok_or_else(|| Error::UnknownFormat(path.to_owned()))?;
+        let (width, height) = reader.into_dimensions().map_err(Error::image(path))?;
+
+        Ok(SpotlightAsset {
+            path: path.to_owned(),
+            format,
+            width,
+            height,
+            orientation: Orientation::new(width, height),
+            size: metadata.len(),
+            modified: metadata.modified().ok(),
+        })
+    }
+
+    /// Returns `true` if the asset is large enough to be a wallpaper in its orientation.
+    pub fn is_wallpaper(&self) -> bool {
+        Filter::default().matches(self)
+    }
+
+    /// Returns the extension matching the image format, e.g. `jpg`.
+    pub fn extension(&self) -> &'static str {
+        self.format
+            .extensions_str()
+            .first()
+            .copied()
+            .unwrap_or("img")
+    }
+
+    /// Returns the file name with the extension matching the image format.
+    pub fn file_name(&self) -> PathBuf {
+        let name = self.path.file_name().unwrap_or(self.path.as_os_str());
+        Path::new(name).with_extension(self.extension())
+    }
+
+    /// Copies the asset into `dir`, with the extension matching the image format.
+    ///
+    /// Returns the path of the copy, or `None` if the file already exists.
+    pub fn copy_to(&self, dir: impl AsRef<Path>) -> Result<Option<PathBuf>> {
+        let dst = dir.as_ref().join(self.file_name());
+
+        if dst.exists() {
+            return Ok(None);
+        }
+
+        fs::copy(&self.path, &dst).map_err(Error::io(&dst))?;
+        Ok(Some(dst))
+    }
+
+    /// Saves the asset to `path`, re-encoding it if `format` differs from the asset format.
+    pub fn save_as(&self, path: impl AsRef<Path>, format: ImageFormat) -> Result<()> {
+        let path = path.as_ref();
+
+        if format == self.format {
+            fs::copy(&self.path, path).map_err(Error::io(path))?;
+            return Ok(());
+        }
+
+        let img = util::new_image_reader(&self.path)?
+            .decode()
+            .map_err(Error::image(&self.path))?;
+
+        // Not every encoder supports every color type, but all support 8-bit RGB(A).
+        let img = if img.color().has_alpha() {
+            image::DynamicImage::ImageRgba8(img.into_rgba8())
+        } else {
+            image::DynamicImage::ImageRgb8(img.into_rgb8())
+        };
+
+        img.save_with_format(path, format)
+            .map_err(Error::image(path))
+    }
+}
@@ -0,0 +1,315 @@
+use crate::{Error, Result, Sidecar, util};
+use rayon::prelude::*;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use xpic::duplicates::{DEFAULT_THRESHOLD, PerceptualHash};
+
+/// SHA-256 of the file content.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ContentHash([u8; 32]);
+
+impl ContentHash {
+    pub fn from_bytes(bytes: [u8; 32]) -> Self {
+        ContentHash(bytes)
+    }
+
+    pub fn of_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let data = fs::read(path).map_err(Error::io(path))?;
+
+        Ok(ContentHash(Sha256::digest(data).into()))
+    }
+}
+
+impl FromStr for ContentHash {
+    type Err = hex::FromHexError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut bytes = [0; 32];
+        hex::decode_to_slice(s, &mut bytes)?;
+        Ok(ContentHash(bytes))
+    }
+}
+
+impl fmt::Display for ContentHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&hex::encode(self.0))
+    }
+}
+
+/// Hashes of an image file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImageHash {
+    pub content: ContentHash,
+    pub perceptual: Option<PerceptualHash>,
+}
+
+/// A file with the same image as an earlier one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Duplicate {
+    pub path: PathBuf,
+    pub original: PathBuf,
+}
+
+/// Files found by [`Hashes::scan`].
+#[derive(Debug, Default)]
+pub struct Scan {
+    /// Files with the same image as an earlier one.
+    pub duplicates: Vec<Duplicate>,
+    /// Files that could not be read or decoded, which are left out.
+    pub skipped: Vec<Error>,
+}
+
+/// Known images, used to detect an image saved again under another name.
+///
+/// Spotlight asset names change over time, so the same image can't be recognized by name.
+#[derive(Debug, Default)]
+pub struct Hashes {
+    perceptual: bool,
+    content: HashMap<ContentHash, PathBuf>,
+    perceptual_hashes: Vec<(PerceptualHash, PathBuf)>,
+}
+
+impl Hashes {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Also compares perceptual hashes, so re-encoded or resized copies count as duplicates.
+    pub fn perceptual(mut self, perceptual: bool) -> Self {
+        self.perceptual = perceptual;
+        self
+    }
+
+    /// Hashes the image at `path`.
+    pub fn hash(&self, path: impl AsRef<Path>) -> Result<ImageHash> {
+        let path = path.as_ref();
+
+        Ok(ImageHash {
+            content: ContentHash::of_file(path)?,
+            perceptual: if self.perceptual {
+                Some(perceptual_hash(path)?)
+            } else {
+                None
+            },
+        })
+    }
+
+    /// Returns the known file with the same image, if any.
+    pub fn find(&self, hash: &ImageHash) -> Option<&Path> {
+        if let Some(path) = self.content.get(&hash.content) {
+            return Some(path);
+        }
+
+        let perceptual = hash.perceptual?;
+        self.perceptual_hashes
+            .iter()
+            .find(|(known, _)| known.distance(perceptual) <= DEFAULT_THRESHOLD)
+            .map(|(_, path)| path.as_path())
+    }
+
+    pub fn insert(&mut self, hash: ImageHash, path: impl Into<PathBuf>) {
+        let path = path.into();
+
+        if let Some(perceptual) = hash.perceptual {
+            self.perceptual_hashes.push((perceptual, path.clone()));
+        }
+        self.content.insert(hash.content, path);
+    }
+
+    /// Hashes the images in `dir`, oldest first, and returns the duplicates among them.
+    ///
+    /// Duplicates are not added, so each image is known by its oldest file. Files that fail to
+    /// be hashed are skipped, so one corrupt file does not stop the others from being found.
+    pub fn scan(&mut self, dir: impl AsRef<Path>) -> Result<Scan> {
+        let dir = dir.as_ref();
+        let mut scan = Scan::default();
+
+        let mut files = Vec::new();
+        for entry in fs::read_dir(dir).map_err(Error::io(dir))? {
+            let entry = entry.map_err(Error::io(dir))?;
+            let metadata = match entry.metadata() {
+                Ok(metadata) => metadata,
+                Err(err) => {
+                    scan.skipped.push(Error::io(entry.path())(err));
+                    continue;
+                }
+            };
+
+            if metadata.is_file() {
+                files.push((metadata.modified().ok(), entry.path()));
+            }
+        }
+        files.sort();
+
+        // Files that are not images, such as sidecars, are skipped.
+        let hashes = files
+            .into_par_iter()
+            .filter(|(_, path)| is_image(path))
+            .map(|(_, path)| self.hash(&path).map(|hash| (hash, path)))
+            .collect::<Vec<_>>();
+
+        for result in hashes {
+            let (hash, path) = match result {
+                Ok(result) => result,
+                Err(err) => {
+                    scan.skipped.push(err);
+                    continue;
+                }
+            };
+
+            match self.find(&hash) {
+                Some(original) => scan.duplicates.push(Duplicate {
+                    original: original.to_owned(),
+                    path,
+                }),
+                None => {
+                    // Re-encoded images keep the hash of the original asset in their sidecar.
+                    if let Some(original) = Sidecar::load(&path)
+                        .ok()
+                        .and_then(|Sidecar(image)| image.hash.parse().ok())
+                    {
+                        self.content.entry(original).or_insert_with(|| path.clone());
+                    }
+                    self.insert(hash, path)
+                }
+            }
+        }
+
+        Ok(scan)
+    }
+}
+
+/// Hashes the image at `path` like xpic hashes Bing thumbnails, which survives re-encoding and
+/// resizing.
+fn perceptual_hash(path: &Path) -> Result<PerceptualHash> {
+    let image = util::new_image_reader(path)?
+        .decode()
+        .map_err(Error::image(path))?;
+
+    Ok(PerceptualHash::of_image(&image))
+}
+
+fn is_image(path: &Path) -> bool {
+    util::new_image_reader(path).is_ok_and(|reader| reader.format().is_some())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ImageFormat, RgbImage};
+    use serde_json::json;
+
+    fn gradient(width: u32, height: u32, flip: bool) -> RgbImage {
+        RgbImage::from_fn(width, height, |x, y| {
+            let x = if flip { width - 1 - x } else { x };
+            image::Rgb([(x * 255 / width) as u8, (y * 255 / height) as u8, 128])
+        })
+    }
+
+    #[test]
+    fn test_scan() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = |name: &str| dir.path().join(name);
+
+        gradient(64, 48, false)
+            .save_with_format(path("a"), ImageFormat::Png)
+            .unwrap();
+        fs::copy(path("a"), path("b")).unwrap();
+        gradient(128, 96, false)
+            .save_with_format(path("c"), ImageFormat::Jpeg)
+            .unwrap();
+        gradient(64, 48, true)
+            .save_with_format(path("d"), ImageFormat::Png)
+            .unwrap();
+        fs::write(path("e.json"), b"{}").unwrap();
+
+        let duplicates = Hashes::new().scan(dir.path()).unwrap().duplicates;
+        assert_eq!(
+            duplicates,
+            [Duplicate {
+                path: path("b"),
+                original: path("a"),
+            }]
+        );
+
+        let mut hashes = Hashes::new().perceptual(true);
+        let mut duplicates = hashes.scan(dir.path()).unwrap().duplicates;
+        duplicates.sort_by(|a, b| a.path.cmp(&b.path));
+        assert_eq!(
+            duplicates,
+            [
+                Duplicate {
+                    path: path("b"),
+                    original: path("a"),
+                },
+                Duplicate {
+                    path: path("c"),
+                    original: path("a"),
+                },
+            ]
+        );
+
+        let hash = hashes.hash(path("d")).unwrap();
+        assert_eq!(hashes.find(&hash), Some(path("d").as_path()));
+    }
+
+    #[test]
+    fn test_scan_corrupt() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = |name: &str| dir.path().join(name);
+
+        gradient(64, 48, false)
+            .save_with_format(path("a"), ImageFormat::Png)
+            .unwrap();
+        fs::copy(path("a"), path("b")).unwrap();
+        // A PNG signature followed by garbage is detected as an image, but can't be decoded.
+        let mut corrupt = fs::read(path("a")).unwrap();
+        corrupt.truncate(64);
+        fs::write(path("c"), corrupt).unwrap();
+
+        let scan = Hashes::new().perceptual(true).scan(dir.path()).unwrap();
+        assert_eq!(scan.duplicates.len(), 1);
+        assert_eq!(scan.skipped.len(), 1);
+        assert!(matches!(scan.skipped[0], Error::Image { .. }));
+    }
+
+    #[test]
+    fn test_scan_sidecar() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.webp");
+        gradient(64, 48, false)
+            .save_with_format(&path, ImageFormat::WebP)
+            .unwrap();
+
+        let original = ContentHash::from_bytes([7; 32]);
+        let sidecar = json!({
+            "url": "https://example.com/a.jpg",
+            "start_date": "20260101",
+            "full_start_date": "202601010000",
+            "end_date": "20260102",
+            "id": "a.webp",
+            "copyright": "",
+            "copyright_link": "https://example.com/a.jpg",
+            "title": "",
+            "quiz_link": "https://example.com/a.jpg",
+            "wallpaper": true,
+            "hash": original.to_string(),
+        });
+        fs::write(Sidecar::path(&path), sidecar.to_string()).unwrap();
+
+        let mut hashes = Hashes::new();
+        assert!(hashes.scan(dir.path()).unwrap().duplicates.is_empty());
+
+        let hash = ImageHash {
+            content: original,
+            perceptual: None,
+        };
+        assert_eq!(hashes.find(&hash), Some(path.as_path()));
+        assert_eq!(original.to_string().parse::<ContentHash>(), Ok(original));
+    }
+}
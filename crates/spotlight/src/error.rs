@@ -0,0 +1,55 @@
+use std::io;
+use std::path::PathBuf;
+
+/// Errors returned by this crate.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// Neither `LocalAppData` nor `UserProfile` is set, so there is no default assets directory.
+    #[error("neither LocalAppData nor UserProfile is set, specify the assets directory instead")]
+    NoDefaultSource,
+
+    /// No assets directory was found in the given directory.
+    #[error("no assets directory found in {}", .0.display())]
+    AssetsNotFound(PathBuf),
+
+    #[error("failed to access {}: {source}", path.display())]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+
+    #[error("failed to read image {}: {source}", path.display())]
+    Image {
+        path: PathBuf,
+        #[source]
+        source: image::ImageError,
+    },
+
+    #[error("invalid aspect ratio: {0}, expected e.g. 16:9 or 1.78")]
+    InvalidAspect(String),
+
+    #[error("invalid name template {0}")]
+    InvalidTemplate(String),
+
+    #[error("failed to watch assets: {0}")]
+    Watch(#[from] notify_debouncer_mini::notify::Error),
+
+    /// The file is not in an image format we recognize.
+    #[error("unknown image format: {}", .0.display())]
+    UnknownFormat(PathBuf),
+}
+
+impl Error {
+    pub(crate) fn io(path: impl Into<PathBuf>) -> impl FnOnce(io::Error) -> Self {
+        let path = path.into();
+        move |source| Error::Io { path, source }
+    }
+
+    pub(crate) fn image(path: impl Into<PathBuf>) -> impl FnOnce(image::ImageError) -> Self {
+        let path = path.into();
+        move |source| Error::Image { path, source }
+    }
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
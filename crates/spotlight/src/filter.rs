@@ -0,0 +1,153 @@
+use crate::{Error, Orientation, SpotlightAsset, WALLPAPER_MIN_SIZE};
+use std::fmt;
+use std::str::FromStr;
+
+/// Selects assets by size, orientation and aspect ratio.
+///
+/// The default filter selects wallpapers: landscape assets at least [`WALLPAPER_MIN_SIZE`], and
+/// portrait assets at least that size transposed.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Filter {
+    /// Minimum width. Defaults to the wallpaper minimum for the orientation of each asset.
+    pub min_width: Option<u32>,
+    /// Minimum height. Defaults to the wallpaper minimum for the orientation of each asset.
+    pub min_height: Option<u32>,
+    /// Required orientation, any if `None`.
+    pub orientation: Option<Orientation>,
+    /// Required aspect ratio, any if `None`.
+    pub aspect: Option<Aspect>,
+}
+
+impl Filter {
+    /// Returns `true` if `asset` passes the filter.
+    pub fn matches(&self, asset: &SpotlightAsset) -> bool {
+        let (min_width, min_height) = match asset.orientation {
+            Orientation::Portrait => (WALLPAPER_MIN_SIZE.1, WALLPAPER_MIN_SIZE.0),
+            Orientation::Landscape | Orientation::Square => WALLPAPER_MIN_SIZE,
+        };
+
+        asset.width >= self.min_width.unwrap_or(min_width)
+            && asset.height >= self.min_height.unwrap_or(min_height)
+            && self
+                .orientation
+                .is_none_or(|orientation| orientation == asset.orientation)
+            && self
+                .aspect
+                .is_none_or(|aspect| aspect.matches(asset.width, asset.height))
+    }
+}
+
+/// Aspect ratio, i.e. width divided by height.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aspect(f64);
+
+impl Aspect {
+    /// Maximum relative difference for a size to match, so `16:9` matches 1366×768.
+    const TOLERANCE: f64 = 0.01;
+
+    pub fn new(width: u32, height: u32) -> Self {
+        Aspect(width as f64 / height as f64)
+    }
+
+    pub fn ratio(self) -> f64 {
+        self.0
+    }
+
+    /// Returns `true` if `width` and `height` have this aspect ratio, within a 1% tolerance.
+    pub fn matches(self, width: u32, height: u32) -> bool {
+        height != 0 && (Aspect::new(width, height).0 / self.0 - 1.0).abs() <= Self::TOLERANCE
+    }
+}
+
+impl FromStr for Aspect {
+    type Err = Error;
+
+    /// Parses `16:9`, `16/9` or `1.78`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || Error::InvalidAspect(s.to_owned());
+
+        let ratio = match s.trim().split_once([':', '/']) {
+            Some((width, height)) => {
+                let width = width.trim().parse::<f64>().map_err(|_| invalid())?;
+                let height = height.trim().parse::<f64>().map_err(|_| invalid())?;
+                width / height
+            }
+            None => s.trim().parse::<f64>().map_err(|_| invalid())?,
+        };
+
+        if ratio.is_finite() && ratio > 0.0 {
+            Ok(Aspect(ratio))
+        } else {
+            Err(invalid())
+        }
+    }
+}
+
+impl fmt::Display for Aspect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:.2}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::ImageFormat;
+
+    fn asset(width: u32, height: u32) -> SpotlightAsset {
+        SpotlightAsset {
+            path: Default::default(),
+            format: ImageFormat::Jpeg,
+            width,
+            height,
+            orientation: Orientation::new(width, height),
+            size: 0,
+            modified: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_aspect() {
+        for s in ["16:9", "16/9", " 16 : 9 ", "1.78"] {
+            assert!(s.parse::<Aspect>().unwrap().matches(1920, 1080), "{s}");
+        }
+
+        for s in ["", "16:", ":9", "16:0", "-1", "a:b"] {
+            assert!(s.parse::<Aspect>().is_err(), "{s}");
+        }
+    }
+
+    #[test]
+    fn test_matches() {
+        let filter = Filter::default();
+        assert!(filter.matches(&asset(1920, 1080)));
+        assert!(filter.matches(&asset(1080, 1920)));
+        assert!(filter.matches(&asset(3840, 2160)));
+        assert!(!filter.matches(&asset(1920, 1)));
+        assert!(!filter.matches(&asset(1080, 1080)));
+
+        let filter = Filter {
+            orientation: Some(Orientation::Portrait),
+            ..Default::default()
+        };
+        assert!(filter.matches(&asset(1080, 1920)));
+        assert!(!filter.matches(&asset(1920, 1080)));
+
+        let filter = Filter {
+            min_width: Some(2560),
+            ..Default::default()
+        };
+        assert!(filter.matches(&asset(3840, 2160)));
+        assert!(!filter.matches(&asset(1920, 1080)));
+        assert!(!filter.matches(&asset(1080, 1920)));
+
+        let filter = Filter {
+            min_width: Some(0),
+            min_height: Some(0),
+            aspect: Some("4:3".parse().unwrap()),
+            ..Default::default()
+        };
+        assert!(filter.matches(&asset(640, 480)));
+        assert!(!filter.matches(&asset(1920, 1080)));
+    }
+}
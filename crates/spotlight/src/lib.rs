@@ -0,0 +1,349 @@
+//! Windows Spotlight images.
+//!
+//! Spotlight stores its images without extensions in the `Assets` directory of the
+//! ContentDeliveryManager package, next to icons and other files. This crate finds that
+//! directory and reads the images in it. [`SpotlightSource`] lists them as an xpic wallpaper
+//! source.
+
+use rayon::prelude::*;
+use std::path::{Path, PathBuf};
+use std::{env, fs, vec};
+
+mod asset;
+mod dedupe;
+mod error;
+mod filter;
+mod metadata;
+mod save;
+mod source;
+mod template;
+mod util;
+mod watch;
+
+pub use asset::{Orientation, SpotlightAsset, WALLPAPER_MIN_SIZE};
+pub use dedupe::{ContentHash, Duplicate, Hashes, ImageHash, Scan};
+pub use error::{Error, Result};
+pub use filter::{Aspect, Filter};
+pub use metadata::{Metadata, MetadataIndex, Sidecar};
+pub use save::{Saved, Saver};
+pub use source::SpotlightSource;
+pub use template::NameTemplate;
+pub use watch::{AssetWatcher, Changes};
+
+/// Glob patterns for the assets directory, relative to the directories a user may point to.
+const ASSET_DIR_PATTERNS: &[&[&str]] = &[
+    // The assets directory itself.
+    &[],
+    // `LocalState` of the ContentDeliveryManager package.
+    &["Assets"],
+    // The ContentDeliveryManager package.
+    &["LocalState", "Assets"],
+    // `%LocalAppData%`.
+    &[
+        "Packages",
+        "*ContentDeliveryManager*",
+        "LocalState",
+        "Assets",
+    ],
+    // A user profile, e.g. a Windows drive mounted at `/mnt/c/Users/<name>`.
+    &[
+        "AppData",
+        "Local",
+        "Packages",
+        "*ContentDeliveryManager*",
+        "LocalState",
+        "Assets",
+    ],
+];
+
+/// Finds Spotlight assets directories in `root`.
+///
+/// `root` may be the assets directory itself, or any directory above it down from a user
+/// profile, so a profile copied from or mounted from another machine works too.
+pub fn find_asset_dirs(root: impl AsRef<Path>) -> Vec<PathBuf> {
+    let root = glob::Pattern::escape(&root.as_ref().to_string_lossy());
+
+    for components in ASSET_DIR_PATTERNS {
+        let pattern = components
+            .iter()
+            .fold(PathBuf::from(&root), |path, component| path.join(component));
+
+        let dirs = glob::glob(&pattern.to_string_lossy())
+            .into_iter()
+            .flatten()
+            .filter_map(Result::ok)
+            .filter(|path| path.is_dir() && is_asset_dir(path))
+            .collect::<Vec<_>>();
+
+        if !dirs.is_empty() {
+            return dirs;
+        }
+    }
+
+    Vec::new()
+}
+
+/// Returns `true` if `path` looks like an assets directory, i.e. it is named `Assets`.
+fn is_asset_dir(path: &Path) -> bool {
+    path.file_name()
+        .is_some_and(|name| name.eq_ignore_ascii_case("Assets"))
+}
+
+/// Returns the assets directories of the current Windows user.
+pub fn default_asset_dirs() -> Result<Vec<PathBuf>> {
+    // Both are searched the same way, see `ASSET_DIR_PATTERNS`.
+    let root = env::var_os("LocalAppData")
+        .or_else(|| env::var_os("UserProfile"))
+        .ok_or(Error::NoDefaultSource)?;
+
+    let dirs = find_asset_dirs(&root);
+    if dirs.is_empty() {
+        return Err(Error::AssetsNotFound(root.into()));
+    }
+
+    Ok(dirs)
+}
+
+/// Returns the assets directories in `source`, or of the current user if `source` is `None`.
+///
+/// A `source` that matches none of the known layouts is taken as the assets directory itself,
+/// such as assets copied to a folder with another name.
+pub fn asset_dirs(source: Option<&Path>) -> Result<Vec<PathBuf>> {
+    match source {
+        Some(source) => {
+            let dirs = find_asset_dirs(source);
+            if !dirs.is_empty() {
+                return Ok(dirs);
+            }
+            if !source.is_dir() {
+                return Err(Error::AssetsNotFound(source.to_owned()));
+            }
+            Ok(vec![source.to_owned()])
+        }
+        None => default_asset_dirs(),
+    }
+}
+
+/// Returns the paths of files in `source`, or in the current user's assets directory if
+/// `source` is `None`.
+pub fn asset_paths(source: Option<&Path>) -> Result<Vec<PathBuf>> {
+    let dirs = asset_dirs(source)?;
+
+    let mut files = Vec::new();
+
+    for dir in dirs {
+        for entry in fs::read_dir(&dir).map_err(Error::io(&dir))? {
+            let path = entry.map_err(Error::io(&dir))?.path();
+            if path.is_file() {
+                files.push(path);
+            }
+        }
+    }
+
+    files.sort();
+
+    Ok(files)
+}
+
+/// Iterator over assets, returned by [`assets`].
+///
+/// Files that are not images yield an error without ending the iteration.
+#[derive(Debug)]
+pub struct Assets {
+    assets: vec::IntoIter<Result<SpotlightAsset>>,
+}
+
+impl Iterator for Assets {
+    type Item = Result<SpotlightAsset>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.assets.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.assets.size_hint()
+    }
+}
+
+impl ExactSizeIterator for Assets {}
+
+/// Returns assets in `source`, or in the current user's assets directory if `source` is `None`.
+///
+/// Files are read in parallel before this returns, in the order of [`asset_paths`].
+pub fn assets(source: Option<&Path>) -> Result<Assets> {
+    let assets = asset_paths(source)?
+        .into_par_iter()
+        .map(SpotlightAsset::open)
+        .collect::<Vec<_>>();
+
+    Ok(Assets {
+        assets: assets.into_iter(),
+    })
+}
+
+/// Returns assets passing `filter`. [`Filter::default`] selects wallpapers.
+///
+/// Errors of individual assets are passed through.
+pub fn wallpapers(
+    source: Option<&Path>,
+    filter: &Filter,
+) -> Result<impl Iterator<Item = Result<SpotlightAsset>>> {
+    let filter = *filter;
+    Ok(assets(source)?
+        .filter(move |asset| asset.as_ref().map_or(true, |asset| filter.matches(asset))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_asset_dirs() {
+        let profile = tempfile::tempdir().unwrap();
+        let assets = profile.path().join(
+            "AppData/Local/Packages/Microsoft.Windows.ContentDeliveryManager_cw5n1h2txyewy/LocalState/Assets",
+        );
+        fs::create_dir_all(&assets).unwrap();
+        fs::write(assets.join("0a1b2c"), b"").unwrap();
+
+        let local_state = assets.parent().unwrap();
+        let package = local_state.parent().unwrap();
+        let local_app_data = package.parent().unwrap().parent().unwrap();
+
+        for root in [
+            profile.path(),
+            local_app_data,
+            package,
+            local_state,
+            assets.as_path(),
+        ] {
+            assert_eq!(
+                find_asset_dirs(root),
+                vec![assets.clone()],
+                "{}",
+                root.display()
+            );
+        }
+
+        assert!(find_asset_dirs(profile.path().join("AppData/Roaming")).is_empty());
+        assert_eq!(
+            asset_paths(Some(profile.path())).unwrap(),
+            [assets.join("0a1b2c")]
+        );
+        assert!(matches!(
+            asset_paths(Some(&profile.path().join("AppData/Roaming"))),
+            Err(Error::AssetsNotFound(_))
+        ));
+
+        // Assets copied out to a folder with another name.
+        let copied = profile.path().join("Spotlight backup");
+        fs::create_dir(&copied).unwrap();
+        fs::write(copied.join("3d4e5f"), b"").unwrap();
+        assert_eq!(asset_dirs(Some(&copied)).unwrap(), vec![copied.clone()]);
+        assert_eq!(asset_paths(Some(&copied)).unwrap(), [copied.join("3d4e5f")]);
+    }
+
+    #[test]
+    fn test_assets() {
+        let dir = tempfile::tempdir().unwrap();
+        let assets = dir.path().join("Assets");
+        fs::create_dir(&assets).unwrap();
+
+        let (width, height) = WALLPAPER_MIN_SIZE;
+        image::RgbImage::new(width, height)
+            .save_with_format(assets.join("a"), image::ImageFormat::Png)
+            .unwrap();
+        image::RgbImage::new(16, 16)
+            .save_with_format(assets.join("b"), image::ImageFormat::Png)
+            .unwrap();
+        fs::write(assets.join("c"), b"not an image").unwrap();
+
+        let results = self::assets(Some(dir.path())).unwrap().collect::<Vec<_>>();
+        assert_eq!(results.len(), 3);
+
+        let a = results[0].as_ref().unwrap();
+        assert_eq!(a.format, image::ImageFormat::Png);
+        assert_eq!((a.width, a.height), WALLPAPER_MIN_SIZE);
+        assert_eq!(a.orientation, Orientation::Landscape);
+        assert_eq!(a.size, fs::metadata(assets.join("a")).unwrap().len());
+        assert!(a.is_wallpaper());
+
+        let b = results[1].as_ref().unwrap();
+        assert_eq!(b.orientation, Orientation::Square);
+        assert!(!b.is_wallpaper());
+
+        assert!(matches!(results[2], Err(Error::UnknownFormat(_))));
+
+        let wallpapers = wallpapers(Some(dir.path()), &Filter::default())
+            .unwrap()
+            .collect::<Vec<_>>();
+        assert_eq!(wallpapers.len(), 2);
+        assert_eq!(wallpapers[0].as_ref().unwrap().path, assets.join("a"));
+        assert!(wallpapers[1].is_err());
+
+        let dst = tempfile::tempdir().unwrap();
+        assert_eq!(
+            a.copy_to(dst.path()).unwrap(),
+            Some(dst.path().join("a.png"))
+        );
+        assert_eq!(a.copy_to(dst.path()).unwrap(), None);
+    }
+
+    /// Compares listing against decoding every asset in full, as listing used to do.
+    ///
+    /// Run with `cargo test --release -- --ignored --nocapture bench_assets`.
+    #[test]
+    #[ignore = "generates a large fixture directory"]
+    fn bench_assets() {
+        use std::time::Instant;
+
+        const COUNT: usize = 64;
+
+        let dir = tempfile::tempdir().unwrap();
+        let assets_dir = dir.path().join("Assets");
+        fs::create_dir(&assets_dir).unwrap();
+
+        let (width, height) = WALLPAPER_MIN_SIZE;
+        (0..COUNT).into_par_iter().for_each(|i| {
+            let (width, height) = if i % 2 == 0 {
+                (width, height)
+            } else {
+                (height, width)
+            };
+            let img = image::RgbImage::from_fn(width, height, |x, y| {
+                image::Rgb([(x ^ y) as u8, (x + i as u32) as u8, (y * 3) as u8])
+            });
+            img.save_with_format(
+                assets_dir.join(format!("{i:04x}")),
+                image::ImageFormat::Jpeg,
+            )
+            .unwrap();
+        });
+
+        let start = Instant::now();
+        let decoded = asset_paths(Some(dir.path()))
+            .unwrap()
+            .into_iter()
+            .map(|path| {
+                let img = util::new_image_reader(&path).unwrap().decode().unwrap();
+                (img.width(), img.height())
+            })
+            .collect::<Vec<_>>();
+        let decode_elapsed = start.elapsed();
+
+        let start = Instant::now();
+        let listed = assets(Some(dir.path()))
+            .unwrap()
+            .map(|asset| {
+                let asset = asset.unwrap();
+                (asset.width, asset.height)
+            })
+            .collect::<Vec<_>>();
+        let list_elapsed = start.elapsed();
+
+        println!("{COUNT} assets: decoded in {decode_elapsed:?}, listed in {list_elapsed:?}");
+
+        assert_eq!(listed, decoded);
+        assert!(list_elapsed < decode_elapsed);
+    }
+}
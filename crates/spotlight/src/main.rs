@@ -1,57 +1,358 @@
-use clap::Parser;
+use clap::{Args, Parser, ValueEnum};
+use image::ImageFormat;
+use spotlight::{
+    Aspect, AssetWatcher, Filter, Hashes, MetadataIndex, NameTemplate, Orientation, Saved, Saver,
+    Sidecar, SpotlightAsset,
+};
+use std::fs;
+use std::io;
 use std::path::{Path, PathBuf};
 
-mod spotlight;
-mod util;
-
 /// Windows Spotlight wallpapers
 #[derive(Parser)]
+#[allow(clippy::upper_case_acronyms)]
 enum CLI {
     /// List Windows Spotlight wallpapers
     List {
         /// The number of wallpapers to list
         #[arg(short)]
         number: Option<usize>,
+
+        /// The assets directory, or a directory containing it such as a Windows user profile
+        #[arg(long, value_name = "DIR")]
+        source: Option<PathBuf>,
+
+        #[command(flatten)]
+        filter: FilterArgs,
     },
-    /// Save wallpapers to a directory
+    /// Save wallpapers to a directory, skipping images already saved under another name
     Save {
         /// The directory where wallpapers are saved
         dir: PathBuf,
+
+        /// The assets directory, or a directory containing it such as a Windows user profile
+        #[arg(long, value_name = "DIR")]
+        source: Option<PathBuf>,
+
+        #[command(flatten)]
+        args: SaveArgs,
+
+        #[command(flatten)]
+        filter: FilterArgs,
+    },
+    /// Save existing wallpapers, then keep saving new ones as Spotlight downloads them
+    Watch {
+        /// The directory where wallpapers are saved
+        dir: PathBuf,
+
+        /// The assets directory, or a directory containing it such as a Windows user profile
+        #[arg(long, value_name = "DIR")]
+        source: Option<PathBuf>,
+
+        #[command(flatten)]
+        args: SaveArgs,
+
+        #[command(flatten)]
+        filter: FilterArgs,
     },
 }
 
+#[derive(Args)]
+struct SaveArgs {
+    /// Also detect re-encoded or resized copies of the same image
+    #[arg(long)]
+    perceptual: bool,
+
+    /// Remove duplicate images already in the directory, keeping the oldest file
+    #[arg(long)]
+    dedupe: bool,
+
+    /// Don't write JSON sidecars with the title and credit of each image
+    #[arg(long)]
+    no_sidecar: bool,
+
+    /// File name template without extension, with placeholders {name}, {title}, {date},
+    /// {width}, {height}, {orientation} and {hash}
+    #[arg(long, value_name = "TEMPLATE", default_value_t = NameTemplate::default())]
+    name: NameTemplate,
+
+    /// Re-encode images to this format
+    #[arg(long, value_enum)]
+    format: Option<FormatArg>,
+
+    /// Print what would be done without changing anything
+    #[arg(long)]
+    dry_run: bool,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum FormatArg {
+    Png,
+    Webp,
+    Avif,
+}
+
+impl From<FormatArg> for ImageFormat {
+    fn from(format: FormatArg) -> Self {
+        match format {
+            FormatArg::Png => ImageFormat::Png,
+            FormatArg::Webp => ImageFormat::WebP,
+            FormatArg::Avif => ImageFormat::Avif,
+        }
+    }
+}
+
+#[derive(Args)]
+struct FilterArgs {
+    /// Minimum width [default: 1920 for landscape, 1080 for portrait]
+    #[arg(long, value_name = "PIXELS")]
+    min_width: Option<u32>,
+
+    /// Minimum height [default: 1080 for landscape, 1920 for portrait]
+    #[arg(long, value_name = "PIXELS")]
+    min_height: Option<u32>,
+
+    /// Orientation
+    #[arg(long, value_enum, default_value_t = OrientationArg::Any)]
+    orientation: OrientationArg,
+
+    /// Aspect ratio, e.g. 16:9 or 1.78
+    #[arg(long)]
+    aspect: Option<Aspect>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum OrientationArg {
+    Landscape,
+    Portrait,
+    Square,
+    Any,
+}
+
+impl From<FilterArgs> for Filter {
+    fn from(args: FilterArgs) -> Self {
+        Filter {
+            min_width: args.min_width,
+            min_height: args.min_height,
+            orientation: match args.orientation {
+                OrientationArg::Landscape => Some(Orientation::Landscape),
+                OrientationArg::Portrait => Some(Orientation::Portrait),
+                OrientationArg::Square => Some(Orientation::Square),
+                OrientationArg::Any => None,
+            },
+            aspect: args.aspect,
+        }
+    }
+}
+
 impl CLI {
     fn run(self) {
         match self {
-            CLI::List { number } => Self::list(number),
-            CLI::Save { dir } => Self::save(dir),
+            CLI::List {
+                number,
+                source,
+                filter,
+            } => Self::list(number, source.as_deref(), &filter.into()),
+            CLI::Save {
+                dir,
+                source,
+                args,
+                filter,
+            } => Self::save(dir, source.as_deref(), &filter.into(), args),
+            CLI::Watch {
+                dir,
+                source,
+                args,
+                filter,
+            } => Self::watch(dir, source.as_deref(), &filter.into(), args),
         }
     }
 
-    fn list(number: Option<usize>) {
-        match spotlight::list_images() {
-            Ok(images) => {
-                if let Some(number) = number {
-                    for path in images.into_iter().take(number) {
-                        println!("{}", path.display());
+    fn list(number: Option<usize>, source: Option<&Path>, filter: &Filter) {
+        let wallpapers = match spotlight::wallpapers(source, filter) {
+            Ok(wallpapers) => wallpapers,
+            Err(err) => {
+                eprintln!("failed to get Windows Spotlight wallpapers: {err}");
+                return;
+            }
+        };
+
+        let wallpapers = wallpapers.filter_map(|result| {
+            result
+                .inspect_err(|err| eprintln!("failed to open image: {err}"))
+                .ok()
+        });
+
+        if let Some(number) = number {
+            for asset in wallpapers.take(number) {
+                println!("{}", asset.path.display());
+            }
+        } else {
+            for asset in wallpapers {
+                println!("{}", asset.path.display());
+            }
+        }
+    }
+
+    fn save(dir: PathBuf, source: Option<&Path>, filter: &Filter, args: SaveArgs) {
+        if let Some(mut saver) = Self::saver(dir, source, &args) {
+            Self::save_wallpapers(&mut saver, source, filter, args.dry_run);
+        }
+    }
+
+    fn save_wallpapers(saver: &mut Saver, source: Option<&Path>, filter: &Filter, dry_run: bool) {
+        let wallpapers = match spotlight::wallpapers(source, filter) {
+            Ok(wallpapers) => wallpapers,
+            Err(err) => {
+                eprintln!(
+                    "failed to copy Windows Spotlight wallpapers to {}: {}",
+                    saver.dir().display(),
+                    err
+                );
+                return;
+            }
+        };
+
+        for result in wallpapers {
+            let result = result.and_then(|asset| Ok((saver.save(&asset)?, asset)));
+            Self::save_asset(saver, result, dry_run);
+        }
+    }
+
+    fn watch(dir: PathBuf, source: Option<&Path>, filter: &Filter, args: SaveArgs) {
+        let dirs = match spotlight::asset_dirs(source) {
+            Ok(dirs) => dirs,
+            Err(err) => {
+                eprintln!("failed to get Windows Spotlight assets: {err}");
+                return;
+            }
+        };
+
+        // Start watching before saving existing assets, so none are missed in between.
+        let watcher = match AssetWatcher::new(&dirs, AssetWatcher::DEFAULT_DEBOUNCE) {
+            Ok(watcher) => watcher,
+            Err(err) => {
+                eprintln!("{err}");
+                return;
+            }
+        };
+
+        let Some(mut saver) = Self::saver(dir, source, &args) else {
+            return;
+        };
+
+        Self::save_wallpapers(&mut saver, source, filter, args.dry_run);
+
+        for dir in &dirs {
+            println!("watching {}", dir.display());
+        }
+
+        while let Some(result) = watcher.recv() {
+            let changes = match result {
+                Ok(changes) => changes,
+                Err(err) => {
+                    eprintln!("{err}");
+                    continue;
+                }
+            };
+
+            for result in saver.save_changes(changes, source, filter) {
+                Self::save_asset(&saver, result, args.dry_run);
+            }
+        }
+    }
+
+    /// Returns a saver for `dir`, removing duplicates first if requested.
+    fn saver(dir: PathBuf, source: Option<&Path>, args: &SaveArgs) -> Option<Saver> {
+        // Metadata is best effort, images are saved without it.
+        let mut saver = Saver::new(dir, Hashes::new().perceptual(args.perceptual))
+            .metadata(MetadataIndex::load(source).unwrap_or_default())
+            .name(args.name.clone())
+            .format(args.format.map(ImageFormat::from))
+            .sidecar(!args.no_sidecar)
+            .dry_run(args.dry_run);
+
+        let scan = match saver.scan() {
+            Ok(scan) => scan,
+            Err(err) => {
+                eprintln!(
+                    "failed to hash images in {}: {}",
+                    saver.dir().display(),
+                    err
+                );
+                return None;
+            }
+        };
+
+        for err in scan.skipped {
+            eprintln!("skipped {err}");
+        }
+
+        if args.dedupe {
+            for duplicate in scan.duplicates {
+                // The sidecar describes the removed file, the original has its own.
+                let sidecar = Sidecar::path(&duplicate.path);
+
+                if args.dry_run {
+                    println!(
+                        "remove {} (duplicate of {})",
+                        duplicate.path.display(),
+                        duplicate.original.display()
+                    );
+                    if sidecar.exists() {
+                        println!("remove {}", sidecar.display());
                     }
-                } else {
-                    for path in images {
-                        println!("{}", path.display());
+                    continue;
+                }
+
+                match fs::remove_file(&duplicate.path) {
+                    Ok(()) => println!(
+                        "removed {} (duplicate of {})",
+                        duplicate.path.display(),
+                        duplicate.original.display()
+                    ),
+                    Err(err) => {
+                        eprintln!("failed to remove {}: {}", duplicate.path.display(), err);
+                        continue;
                     }
                 }
+
+                match fs::remove_file(&sidecar) {
+                    Ok(()) => println!("removed {}", sidecar.display()),
+                    Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+                    Err(err) => eprintln!("failed to remove {}: {}", sidecar.display(), err),
+                }
             }
-            Err(err) => eprintln!("failed to get Windows Spotlight wallpapers: {err}"),
         }
+
+        Some(saver)
     }
 
-    fn save(dir: impl AsRef<Path>) {
-        if let Err(err) = spotlight::copy_images_to(&dir) {
-            eprintln!(
-                "failed to copy Windows Spotlight wallpapers to {}:{}",
-                dir.as_ref().display(),
-                err
-            );
+    fn save_asset(
+        saver: &Saver,
+        result: spotlight::Result<(Saved, SpotlightAsset)>,
+        dry_run: bool,
+    ) {
+        match result {
+            Ok((Saved::Duplicate { original }, asset)) if dry_run => println!(
+                "skip {} (duplicate of {})",
+                asset.path.display(),
+                original.display()
+            ),
+            Ok((Saved::Duplicate { .. }, _)) => {}
+            Ok((Saved::Copied { path }, asset)) => println!(
+                "{} {} -> {}",
+                if dry_run { "copy" } else { "copied" },
+                asset.path.display(),
+                path.display()
+            ),
+            Ok((Saved::Converted { path, .. }, asset)) => println!(
+                "{} {} -> {}",
+                if dry_run { "convert" } else { "converted" },
+                asset.path.display(),
+                path.display()
+            ),
+            Err(err) => eprintln!("failed to copy image to {}: {}", saver.dir().display(), err),
         }
     }
 }
@@ -0,0 +1,431 @@
+use crate::{ContentHash, Error, Result, SpotlightAsset};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use chrono::{DateTime, Days, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use url::Url;
+use xpic::{Copyright, Image};
+
+/// Files larger than this in `LocalState` are not metadata.
+const MAX_FILE_SIZE: u64 = 4 * 1024 * 1024;
+
+// Keys of text fields, in the older `TargetedContentCache` format and the newer one.
+const TITLE_KEYS: &[&str] = &["title", "title_text"];
+const DESCRIPTION_KEYS: &[&str] = &["description", "hs1_title_str", "hs2_title_str"];
+const COPYRIGHT_KEYS: &[&str] = &["copyright", "copyright_text"];
+const LINK_KEYS: &[&str] = &["ctaUri", "actionUri", "click_through_url"];
+
+/// Metadata of a Spotlight image, recovered from the ContentDeliveryManager cache.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Metadata {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    /// The credit, e.g. `© Westend61/Getty Images`.
+    pub copyright: Option<String>,
+    /// Where Spotlight downloaded the image from.
+    pub url: Option<String>,
+    /// Where to learn more about the image.
+    pub link: Option<String>,
+}
+
+/// Metadata by the content hash of the image it describes.
+///
+/// Spotlight keeps JSON descriptions of its images in `LocalState`, mainly in
+/// `TargetedContentCache`. Each one lists the SHA-256 of the image files, which is how they are
+/// matched with assets.
+#[derive(Debug, Clone, Default)]
+pub struct MetadataIndex {
+    entries: HashMap<ContentHash, Metadata>,
+}
+
+impl MetadataIndex {
+    /// Loads metadata next to the assets directories in `source`, or of the current user if
+    /// `source` is `None`.
+    pub fn load(source: Option<&Path>) -> Result<Self> {
+        let mut index = MetadataIndex::default();
+
+        for dir in crate::asset_dirs(source)? {
+            if let Some(local_state) = dir.parent() {
+                index.load_dir(local_state);
+            }
+        }
+
+        Ok(index)
+    }
+
+    /// Loads metadata from files in `dir` and its subdirectories, except `Assets`.
+    ///
+    /// Files that can't be read or are not metadata are skipped.
+    pub fn load_dir(&mut self, dir: impl AsRef<Path>) {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return;
+        };
+
+        for entry in entries.filter_map(io::Result::ok) {
+            let path = entry.path();
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+
+            if metadata.is_dir() {
+                if !crate::is_asset_dir(&path) {
+                    self.load_dir(&path);
+                }
+            } else if metadata.len() <= MAX_FILE_SIZE
+                && let Ok(data) = fs::read(&path)
+                && let Ok(value) = serde_json::from_slice::<Value>(&data)
+            {
+                self.visit(&value);
+            }
+        }
+    }
+
+    pub fn get(&self, hash: &ContentHash) -> Option<&Metadata> {
+        self.entries.get(hash)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Collects metadata from `value`.
+    ///
+    /// An object with image descriptions among its values, i.e. objects with `u` and `sha256`,
+    /// has the text fields of those images. Some entries are JSON encoded as strings.
+    fn visit(&mut self, value: &Value) {
+        match value {
+            Value::String(s) if s.trim_start().starts_with('{') => {
+                if let Ok(value) = serde_json::from_str::<Value>(s) {
+                    self.visit(&value);
+                }
+            }
+            Value::Array(values) => values.iter().for_each(|value| self.visit(value)),
+            Value::Object(map) => {
+                for image in map.values().filter_map(Value::as_object) {
+                    let Some(hash) = image
+                        .get("sha256")
+                        .and_then(Value::as_str)
+                        .and_then(parse_hash)
+                    else {
+                        continue;
+                    };
+
+                    let metadata = Metadata {
+                        title: text(map, TITLE_KEYS),
+                        description: text(map, DESCRIPTION_KEYS),
+                        copyright: text(map, COPYRIGHT_KEYS),
+                        url: image
+                            .get("u")
+                            .and_then(Value::as_str)
+                            .map(ToOwned::to_owned),
+                        link: text(map, LINK_KEYS).map(|link| {
+                            // Spotlight opens links in Edge regardless of the default browser.
+                            link.strip_prefix("microsoft-edge:")
+                                .map(ToOwned::to_owned)
+                                .unwrap_or(link)
+                        }),
+                    };
+
+                    self.entries.insert(hash, metadata);
+                }
+
+                map.values().for_each(|value| self.visit(value));
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Returns the first non-empty text among `keys`, either a string or an object with the text
+/// in `v` or `tx`.
+fn text(map: &Map<String, Value>, keys: &[&str]) -> Option<String> {
+    keys.iter()
+        .filter_map(|key| {
+            let value = map.get(*key)?;
+            value
+                .as_str()
+                .or_else(|| value.get("v")?.as_str())
+                .or_else(|| value.get("tx")?.as_str())
+        })
+        .map(str::trim)
+        .find(|text| !text.is_empty())
+        .map(ToOwned::to_owned)
+}
+
+/// Parses a SHA-256 in base64, as Spotlight writes it, or in hex.
+fn parse_hash(s: &str) -> Option<ContentHash> {
+    let bytes = if s.len() == 64 {
+        hex::decode(s).ok()?
+    } else {
+        BASE64.decode(s).ok()?
+    };
+
+    Some(ContentHash::from_bytes(bytes.try_into().ok()?))
+}
+
+/// Sidecar JSON saved next to an image, the [`Image`] describing it as xpic does.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Sidecar(pub Image);
+
+impl Sidecar {
+    /// Describes `path`, a copy of `asset`, like [`SpotlightSource`](crate::SpotlightSource)
+    /// describes the asset itself.
+    pub fn new(
+        asset: &SpotlightAsset,
+        path: impl AsRef<Path>,
+        hash: &ContentHash,
+        metadata: Option<&Metadata>,
+    ) -> Result<Self> {
+        let path = path.as_ref();
+        let id = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        Ok(Sidecar(to_image(asset, path, id, hash, metadata)?))
+    }
+
+    /// Returns the sidecar path of the image at `path`.
+    pub fn path(path: impl AsRef<Path>) -> PathBuf {
+        path.as_ref().with_extension("json")
+    }
+
+    /// Loads the sidecar of the image at `path`.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = Self::path(path);
+        let data = fs::read(&path).map_err(Error::io(&path))?;
+
+        serde_json::from_slice(&data)
+            .map_err(io::Error::from)
+            .map_err(Error::io(&path))
+    }
+
+    /// Saves the sidecar of the image at `path`.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = Self::path(path);
+        let data = serde_json::to_vec_pretty(self)
+            .map_err(io::Error::from)
+            .map_err(Error::io(&path))?;
+
+        fs::write(&path, data).map_err(Error::io(&path))
+    }
+}
+
+/// Describes `asset`, or its copy at `path`, as an [`Image`] with `id`.
+///
+/// Spotlight has no dates, so the image is dated by when the asset was downloaded. The link
+/// goes to where Spotlight links to, or else to where it downloaded the image from.
+pub(crate) fn to_image(
+    asset: &SpotlightAsset,
+    path: &Path,
+    id: String,
+    hash: &ContentHash,
+    metadata: Option<&Metadata>,
+) -> Result<Image> {
+    let metadata = metadata.cloned().unwrap_or_default();
+
+    let url = file_url(path)?;
+    let link = metadata
+        .link
+        .or(metadata.url)
+        .and_then(|link| Url::parse(&link).ok())
+        .unwrap_or_else(|| url.clone());
+
+    // Like Bing's `Description (© Credit)`.
+    let copyright = match (metadata.description, metadata.copyright) {
+        (Some(description), Some(copyright)) => format!("{description} ({copyright})"),
+        (description, copyright) => description.or(copyright).unwrap_or_default(),
+    };
+
+    let date = asset
+        .modified
+        .map(DateTime::<Utc>::from)
+        .unwrap_or_default();
+
+    Ok(Image {
+        url,
+        start_date: date.date_naive(),
+        full_start_date: date,
+        end_date: date
+            .date_naive()
+            .checked_add_days(Days::new(1))
+            .unwrap_or(date.date_naive()),
+        id,
+        id_parsed: None,
+        copyright_parsed: Copyright::parse(&copyright),
+        copyright,
+        copyright_link: link.clone(),
+        title: metadata.title.unwrap_or_default(),
+        quiz_link: link,
+        wallpaper: asset.is_wallpaper(),
+        hash: hash.to_string(),
+        video: None,
+        safe_area: None,
+        palette: None,
+        blurhash: None,
+        perceptual_hash: None,
+        rerun_of: None,
+    })
+}
+
+/// Returns the `file:` URL of `path`, which must exist.
+fn file_url(path: &Path) -> Result<Url> {
+    let path = path.canonicalize().map_err(Error::io(path))?;
+
+    Url::from_file_path(&path).map_err(|_| {
+        Error::io(&path)(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "not an absolute path",
+        ))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::time::{Duration, SystemTime};
+
+    #[test]
+    fn test_load_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let local_state = dir.path().join("LocalState");
+        let cache = local_state.join("TargetedContentCache/v3/338387");
+        fs::create_dir_all(&cache).unwrap();
+        fs::create_dir_all(local_state.join("Assets")).unwrap();
+
+        let hash = [7u8; 32];
+        let other_hash = [9u8; 32];
+
+        // The older format, with text fields next to the image descriptions.
+        let v3 = json!({
+            "items": [{
+                "properties": {
+                    "title": {"t": "txt", "v": "Lake Bled, Slovenia"},
+                    "description": {"t": "txt", "v": "An island church in the Julian Alps"},
+                    "copyright": {"t": "txt", "v": "© Jan Wlodarczyk/Alamy"},
+                    "ctaUri": {"t": "url", "u": "", "v": "microsoft-edge:https://www.bing.com/search?q=Lake+Bled"},
+                    "landscapeImage": {
+                        "t": "img",
+                        "w": 1920,
+                        "h": 1080,
+                        "u": "https://img-prod-cms-rt-microsoft-com.akamaized.net/cms/api/am/imageFileData/RE1",
+                        "sha256": BASE64.encode(hash),
+                    },
+                }
+            }]
+        });
+        fs::write(cache.join("1"), serde_json::to_vec(&v3).unwrap()).unwrap();
+
+        // The newer format, with the entry encoded as a string.
+        let ad = json!({
+            "ad": {
+                "title_text": {"tx": "Moonrise"},
+                "copyright_text": {"tx": "© NASA"},
+                "image_fullscreen_001_portrait": {
+                    "u": "https://img-s-msn-com.akamaized.net/tenant/amp/entityid/AA2",
+                    "sha256": hex::encode(other_hash),
+                },
+            }
+        });
+        let batch = json!({"batchrsp": {"items": [{"item": ad.to_string()}]}});
+        fs::write(local_state.join("batch.json"), batch.to_string()).unwrap();
+        fs::write(local_state.join("settings.dat"), b"\x00\x01").unwrap();
+
+        let mut index = MetadataIndex::default();
+        index.load_dir(&local_state);
+        assert_eq!(index.len(), 2);
+
+        assert_eq!(
+            index.get(&ContentHash::from_bytes(hash)),
+            Some(&Metadata {
+                title: Some("Lake Bled, Slovenia".to_string()),
+                description: Some("An island church in the Julian Alps".to_string()),
+                copyright: Some("© Jan Wlodarczyk/Alamy".to_string()),
+                url: Some(
+                    "https://img-prod-cms-rt-microsoft-com.akamaized.net/cms/api/am/imageFileData/RE1"
+                        .to_string()
+                ),
+                link: Some("https://www.bing.com/search?q=Lake+Bled".to_string()),
+            })
+        );
+
+        let other = index.get(&ContentHash::from_bytes(other_hash)).unwrap();
+        assert_eq!(other.title.as_deref(), Some("Moonrise"));
+        assert_eq!(other.copyright.as_deref(), Some("© NASA"));
+        assert_eq!(other.link, None);
+    }
+
+    #[test]
+    fn test_sidecar() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.jpg");
+        fs::write(&path, b"").unwrap();
+
+        let asset = SpotlightAsset {
+            path: path.clone(),
+            format: image::ImageFormat::Jpeg,
+            width: 1920,
+            height: 1080,
+            orientation: crate::Orientation::Landscape,
+            size: 0,
+            modified: Some(SystemTime::UNIX_EPOCH + Duration::from_secs(1_767_225_600)),
+        };
+        let hash = ContentHash::from_bytes([7; 32]);
+        let metadata = Metadata {
+            title: Some("Lake Bled".to_string()),
+            description: Some("Lake Bled, Slovenia".to_string()),
+            copyright: Some("© Jan Wlodarczyk/Alamy".to_string()),
+            url: Some("https://example.com/a.jpg".to_string()),
+            link: None,
+        };
+
+        let Sidecar(image) = Sidecar::new(&asset, &path, &hash, Some(&metadata)).unwrap();
+        assert_eq!(
+            image.url,
+            Url::from_file_path(path.canonicalize().unwrap()).unwrap()
+        );
+        assert_eq!(
+            image.full_start_date.to_rfc3339(),
+            "2026-01-01T00:00:00+00:00"
+        );
+        assert_eq!(image.end_date.to_string(), "2026-01-02");
+        assert_eq!(image.id, "a.jpg");
+        assert_eq!(
+            image.copyright,
+            "Lake Bled, Slovenia (© Jan Wlodarczyk/Alamy)"
+        );
+        assert_eq!(image.copyright_link.as_str(), "https://example.com/a.jpg");
+        assert_eq!(image.hash, hash.to_string());
+
+        // The sidecar is what xpic reads.
+        let sidecar = Sidecar(image);
+        sidecar.save(&path).unwrap();
+        let data = fs::read(dir.path().join("a.json")).unwrap();
+        let image = serde_json::from_slice::<Image>(&data).unwrap();
+        assert_eq!(image.title, "Lake Bled");
+        assert_eq!(Sidecar::load(&path).unwrap().0.hash, sidecar.0.hash);
+
+        // Without metadata, the link goes to the copy rather than to the asset.
+        let copy = dir.path().join("b.jpg");
+        fs::write(&copy, b"").unwrap();
+        let Sidecar(image) = Sidecar::new(&asset, &copy, &hash, None).unwrap();
+        assert_eq!(
+            image.url,
+            Url::from_file_path(copy.canonicalize().unwrap()).unwrap()
+        );
+        assert_eq!(image.copyright_link, image.url);
+        assert_eq!(image.id, "b.jpg");
+        assert_eq!(image.title, "");
+    }
+}
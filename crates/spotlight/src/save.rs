@@ -0,0 +1,279 @@
+use crate::{
+    Changes, Error, Filter, Hashes, MetadataIndex, NameTemplate, Result, Scan, Sidecar,
+    SpotlightAsset,
+};
+use image::ImageFormat;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// What [`Saver::save`] did with an asset, or would do in a dry run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Saved {
+    /// The image is already in the directory.
+    Duplicate {
+        original: PathBuf,
+    },
+    Copied {
+        path: PathBuf,
+    },
+    Converted {
+        path: PathBuf,
+        format: ImageFormat,
+    },
+}
+
+/// Saves assets into a directory, skipping images it already has.
+#[derive(Debug)]
+pub struct Saver {
+    dir: PathBuf,
+    hashes: Hashes,
+    metadata: MetadataIndex,
+    name: NameTemplate,
+    format: Option<ImageFormat>,
+    sidecar: bool,
+    dry_run: bool,
+    /// Paths planned in a dry run, which don't exist yet.
+    planned: HashSet<PathBuf>,
+}
+
+impl Saver {
+    pub fn new(dir: impl Into<PathBuf>, hashes: Hashes) -> Self {
+        Saver {
+            dir: dir.into(),
+            hashes,
+            metadata: MetadataIndex::default(),
+            name: NameTemplate::default(),
+            format: None,
+            sidecar: true,
+            dry_run: false,
+            planned: HashSet::new(),
+        }
+    }
+
+    /// Metadata used for names and sidecars.
+    pub fn metadata(mut self, metadata: MetadataIndex) -> Self {
+        self.metadata = metadata;
+        self
+    }
+
+    pub fn name(mut self, name: NameTemplate) -> Self {
+        self.name = name;
+        self
+    }
+
+    /// Re-encodes images to `format`, or keeps their format if `None`.
+    pub fn format(mut self, format: Option<ImageFormat>) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Writes a [`Sidecar`] next to each image. Enabled by default.
+    pub fn sidecar(mut self, sidecar: bool) -> Self {
+        self.sidecar = sidecar;
+        self
+    }
+
+    /// Plans without writing anything.
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn set_metadata(&mut self, metadata: MetadataIndex) {
+        self.metadata = metadata;
+    }
+
+    /// Hashes the images already in the directory, creating it unless in a dry run.
+    ///
+    /// Returns the duplicates among them, see [`Hashes::scan`].
+    pub fn scan(&mut self) -> Result<Scan> {
+        if !self.dry_run {
+            std::fs::create_dir_all(&self.dir).map_err(Error::io(&self.dir))?;
+        } else if !self.dir.exists() {
+            return Ok(Scan::default());
+        }
+
+        self.hashes.scan(&self.dir)
+    }
+
+    /// Saves `asset` unless the directory already has the image.
+    pub fn save(&mut self, asset: &SpotlightAsset) -> Result<Saved> {
+        let hash = self.hashes.hash(&asset.path)?;
+        if let Some(original) = self.hashes.find(&hash) {
+            return Ok(Saved::Duplicate {
+                original: original.to_owned(),
+            });
+        }
+
+        let metadata = self.metadata.get(&hash.content);
+        let format = self.format.unwrap_or(asset.format);
+        let extension = format.extensions_str().first().unwrap_or(&"img");
+        let name = self.name.render(asset, &hash.content, metadata);
+        let path = unique_path(&self.dir, &name, extension, |path| {
+            path.exists() || self.planned.contains(path)
+        });
+
+        if self.dry_run {
+            self.planned.insert(path.clone());
+        } else {
+            asset.save_as(&path, format)?;
+            if self.sidecar {
+                Sidecar::new(asset, &path, &hash.content, metadata)?.save(&path)?;
+            }
+        }
+
+        self.hashes.insert(hash, path.clone());
+
+        Ok(if format == asset.format {
+            Saved::Copied { path }
+        } else {
+            Saved::Converted { path, format }
+        })
+    }
+
+    /// Saves the assets in `changes` that match `filter`, as reported by an
+    /// [`AssetWatcher`](crate::AssetWatcher).
+    ///
+    /// Metadata is reloaded from `source` only if it changed.
+    pub fn save_changes(
+        &mut self,
+        changes: Changes,
+        source: Option<&Path>,
+        filter: &Filter,
+    ) -> Vec<Result<(Saved, SpotlightAsset)>> {
+        if changes.metadata {
+            // Metadata is best effort, images are saved without it.
+            self.set_metadata(MetadataIndex::load(source).unwrap_or_default());
+        }
+
+        changes
+            .assets
+            .into_iter()
+            .map(SpotlightAsset::open)
+            // Assets that fail to open are reported rather than filtered out.
+            .filter(|result| !result.as_ref().is_ok_and(|asset| !filter.matches(asset)))
+            .map(|result| result.and_then(|asset| Ok((self.save(&asset)?, asset))))
+            .collect()
+    }
+}
+
+/// Returns `dir/name.extension`, or `dir/name-2.extension` and so on if that is taken.
+fn unique_path(
+    dir: &Path,
+    name: &str,
+    extension: &str,
+    is_taken: impl Fn(&Path) -> bool,
+) -> PathBuf {
+    let mut path = dir.join(format!("{name}.{extension}"));
+
+    for i in 2.. {
+        if !is_taken(&path) {
+            break;
+        }
+        path = dir.join(format!("{name}-{i}.{extension}"));
+    }
+
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AssetWatcher, WALLPAPER_MIN_SIZE};
+    use image::RgbImage;
+    use std::fs;
+    use std::time::Duration;
+
+    #[test]
+    fn test_save() {
+        let src = tempfile::tempdir().unwrap();
+        let dst = tempfile::tempdir().unwrap();
+
+        let img = RgbImage::from_fn(32, 32, |x, y| image::Rgb([x as u8 * 8, y as u8 * 8, 0]));
+        for name in ["a", "b"] {
+            img.save_with_format(src.path().join(name), ImageFormat::Png)
+                .unwrap();
+        }
+        let a = SpotlightAsset::open(src.path().join("a")).unwrap();
+        let b = SpotlightAsset::open(src.path().join("b")).unwrap();
+
+        let template = "{width}x{height}".parse::<NameTemplate>().unwrap();
+
+        let mut saver = Saver::new(dst.path(), Hashes::new())
+            .name(template.clone())
+            .dry_run(true);
+        assert!(saver.scan().unwrap().duplicates.is_empty());
+        assert_eq!(
+            saver.save(&a).unwrap(),
+            Saved::Copied {
+                path: dst.path().join("32x32.png")
+            }
+        );
+        assert_eq!(fs::read_dir(dst.path()).unwrap().count(), 0);
+
+        let mut saver = Saver::new(dst.path(), Hashes::new())
+            .name(template)
+            .format(Some(ImageFormat::WebP));
+        assert!(saver.scan().unwrap().duplicates.is_empty());
+        assert_eq!(
+            saver.save(&a).unwrap(),
+            Saved::Converted {
+                path: dst.path().join("32x32.webp"),
+                format: ImageFormat::WebP
+            }
+        );
+        assert_eq!(
+            saver.save(&b).unwrap(),
+            Saved::Duplicate {
+                original: dst.path().join("32x32.webp")
+            }
+        );
+        assert!(dst.path().join("32x32.json").exists());
+
+        // The sidecar identifies the converted image as a copy of the asset.
+        let mut saver = Saver::new(dst.path(), Hashes::new());
+        saver.scan().unwrap();
+        assert!(matches!(saver.save(&b).unwrap(), Saved::Duplicate { .. }));
+    }
+
+    #[test]
+    fn test_save_changes() {
+        let src = tempfile::tempdir().unwrap();
+        let dst = tempfile::tempdir().unwrap();
+        let assets = src.path().join("LocalState").join("Assets");
+        fs::create_dir_all(&assets).unwrap();
+
+        let watcher = AssetWatcher::new(&[&assets], Duration::from_millis(100)).unwrap();
+        let mut saver = Saver::new(dst.path(), Hashes::new());
+        saver.scan().unwrap();
+
+        let (width, height) = WALLPAPER_MIN_SIZE;
+        RgbImage::from_fn(width, height, |x, y| image::Rgb([x as u8, y as u8, 0]))
+            .save_with_format(assets.join("0a1b2c"), ImageFormat::Png)
+            .unwrap();
+
+        let mut saved = Vec::new();
+        while saved.is_empty() {
+            let changes = watcher
+                .recv_timeout(Duration::from_secs(10))
+                .expect("asset should be reported")
+                .unwrap();
+            saved.extend(saver.save_changes(changes, Some(src.path()), &Filter::default()));
+        }
+
+        assert_eq!(saved.len(), 1);
+        let (saved, asset) = saved.pop().unwrap().unwrap();
+        assert_eq!(asset.path, assets.join("0a1b2c"));
+        let Saved::Copied { path } = saved else {
+            panic!("expected a copy, got {saved:?}");
+        };
+        assert_eq!(path.parent(), Some(dst.path()));
+        assert_eq!(fs::read(&path).unwrap(), fs::read(&asset.path).unwrap());
+        assert!(Sidecar::path(&path).exists());
+        Sidecar::load(&path).unwrap();
+    }
+}
@@ -0,0 +1,113 @@
+use crate::metadata::to_image;
+use crate::{ContentHash, Filter, MetadataIndex};
+use anyhow::anyhow;
+use futures::future::BoxFuture;
+use std::cmp::Reverse;
+use std::path::PathBuf;
+use xpic::Image;
+use xpic::source::WallpaperSource;
+
+/// Windows Spotlight images in the ContentDeliveryManager cache.
+///
+/// Titles and credits are recovered from the cache where available. Spotlight has no dates, so
+/// images are dated by when they were downloaded.
+#[derive(Debug, Clone, Default)]
+pub struct SpotlightSource {
+    source: Option<PathBuf>,
+    filter: Filter,
+}
+
+impl SpotlightSource {
+    /// Lists wallpapers of the current Windows user.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Lists wallpapers in `source` instead, see [`asset_dirs`](crate::asset_dirs).
+    pub fn source(mut self, source: impl Into<PathBuf>) -> Self {
+        self.source = Some(source.into());
+
+        self
+    }
+
+    pub fn filter(mut self, filter: Filter) -> Self {
+        self.filter = filter;
+
+        self
+    }
+
+    fn list_blocking(&self) -> anyhow::Result<Vec<Image>> {
+        let metadata = MetadataIndex::load(self.source.as_deref()).unwrap_or_default();
+
+        let mut images = crate::wallpapers(self.source.as_deref(), &self.filter)?
+            .filter_map(Result::ok)
+            .filter_map(|asset| {
+                let hash = ContentHash::of_file(&asset.path).ok()?;
+                let id = asset.file_name().to_string_lossy().into_owned();
+                to_image(&asset, &asset.path, id, &hash, metadata.get(&hash)).ok()
+            })
+            .collect::<Vec<_>>();
+
+        images.sort_by_key(|image| Reverse(image.full_start_date));
+
+        Ok(images)
+    }
+}
+
+impl WallpaperSource for SpotlightSource {
+    fn name(&self) -> &'static str {
+        "spotlight"
+    }
+
+    fn list(&self) -> BoxFuture<'_, anyhow::Result<Vec<Image>>> {
+        let this = self.clone();
+
+        Box::pin(async move { tokio::task::spawn_blocking(move || this.list_blocking()).await? })
+    }
+
+    fn fetch<'a>(&'a self, image: &'a Image) -> BoxFuture<'a, anyhow::Result<Vec<u8>>> {
+        Box::pin(async move {
+            let path = image
+                .url
+                .to_file_path()
+                .map_err(|_| anyhow!("not a Spotlight image: {}", image.url))?;
+
+            Ok(tokio::fs::read(path).await?)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[tokio::test]
+    async fn test_spotlight_source() {
+        let dir = tempfile::tempdir().unwrap();
+        let assets = dir.path().join("LocalState/Assets");
+        fs::create_dir_all(&assets).unwrap();
+
+        image::RgbImage::new(1920, 1080)
+            .save_with_format(assets.join("0a1b2c"), image::ImageFormat::Png)
+            .unwrap();
+        image::RgbImage::new(64, 64)
+            .save_with_format(assets.join("icon"), image::ImageFormat::Png)
+            .unwrap();
+
+        let source = SpotlightSource::new().source(dir.path());
+        let images = source.list().await.unwrap();
+
+        assert_eq!(images.len(), 1);
+        assert_eq!(images[0].id, "0a1b2c.png");
+        assert_eq!(images[0].url.scheme(), "file");
+        assert!(images[0].wallpaper);
+
+        assert_eq!(
+            source.fetch(&images[0]).await.unwrap(),
+            fs::read(assets.join("0a1b2c")).unwrap()
+        );
+        assert!(source.metadata("0a1b2c.png").await.unwrap().is_some());
+        assert!(source.metadata("icon.png").await.unwrap().is_none());
+    }
+}
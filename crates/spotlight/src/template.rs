@@ -0,0 +1,220 @@
+use crate::{ContentHash, Error, Metadata, SpotlightAsset};
+use chrono::{DateTime, Utc};
+use std::fmt;
+use std::str::FromStr;
+
+/// Maximum length of a rendered name in characters, leaving room for a suffix and extension.
+const MAX_NAME_LEN: usize = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Placeholder {
+    Name,
+    Title,
+    Date,
+    Width,
+    Height,
+    Orientation,
+    Hash,
+}
+
+impl Placeholder {
+    fn parse(s: &str) -> Option<Self> {
+        Some(match s {
+            "name" => Placeholder::Name,
+            "title" => Placeholder::Title,
+            "date" => Placeholder::Date,
+            "width" => Placeholder::Width,
+            "height" => Placeholder::Height,
+            "orientation" => Placeholder::Orientation,
+            "hash" => Placeholder::Hash,
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Text(String),
+    Placeholder(Placeholder),
+}
+
+/// Template for the names of saved images, without the extension.
+///
+/// Placeholders are:
+///
+/// - `{name}`: the asset name
+/// - `{title}`: the recovered title, or the asset name if unknown
+/// - `{date}`: the date the asset was downloaded in UTC, as in its [`Sidecar`](crate::Sidecar),
+///   e.g. `2026-01-01`
+/// - `{width}`, `{height}` and `{orientation}`
+/// - `{hash}`: the first 8 hex digits of the content hash
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NameTemplate {
+    source: String,
+    parts: Vec<Part>,
+}
+
+impl NameTemplate {
+    /// Renders the name of `asset`. Characters not allowed in file names are replaced with `_`.
+    pub fn render(
+        &self,
+        asset: &SpotlightAsset,
+        hash: &ContentHash,
+        metadata: Option<&Metadata>,
+    ) -> String {
+        let asset_name = asset
+            .path
+            .file_stem()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        let mut name = String::new();
+
+        for part in &self.parts {
+            match part {
+                Part::Text(text) => name.push_str(text),
+                Part::Placeholder(placeholder) => name.push_str(&match placeholder {
+                    Placeholder::Name => asset_name.clone(),
+                    Placeholder::Title => metadata
+                        .and_then(|metadata| metadata.title.clone())
+                        .unwrap_or_else(|| asset_name.clone()),
+                    Placeholder::Date => asset
+                        .modified
+                        .map(|modified| DateTime::<Utc>::from(modified).format("%Y-%m-%d"))
+                        .map(|date| date.to_string())
+                        .unwrap_or_else(|| "unknown".to_owned()),
+                    Placeholder::Width => asset.width.to_string(),
+                    Placeholder::Height => asset.height.to_string(),
+                    Placeholder::Orientation => asset.orientation.to_string(),
+                    Placeholder::Hash => hash.to_string()[..8].to_owned(),
+                }),
+            }
+        }
+
+        let name = sanitize(&name);
+        if name.is_empty() { asset_name } else { name }
+    }
+}
+
+impl Default for NameTemplate {
+    /// Keeps asset names.
+    fn default() -> Self {
+        "{name}".parse().unwrap()
+    }
+}
+
+impl FromStr for NameTemplate {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = |reason: &str| Error::InvalidTemplate(format!("{s}: {reason}"));
+
+        let mut parts = Vec::new();
+        let mut rest = s;
+
+        while let Some(start) = rest.find(['{', '}']) {
+            if rest[start..].starts_with('}') {
+                return Err(invalid("unmatched '}'"));
+            }
+            if start > 0 {
+                parts.push(Part::Text(rest[..start].to_owned()));
+            }
+
+            let end = rest[start..]
+                .find('}')
+                .ok_or_else(|| invalid("unmatched '{'"))?
+                + start;
+            let name = &rest[start + 1..end];
+            let placeholder = Placeholder::parse(name)
+                .ok_or_else(|| invalid(&format!("unknown placeholder {{{name}}}")))?;
+
+            parts.push(Part::Placeholder(placeholder));
+            rest = &rest[end + 1..];
+        }
+
+        if !rest.is_empty() {
+            parts.push(Part::Text(rest.to_owned()));
+        }
+
+        Ok(NameTemplate {
+            source: s.to_owned(),
+            parts,
+        })
+    }
+}
+
+impl fmt::Display for NameTemplate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+/// Replaces characters not allowed in file names on Windows, where most Spotlight images are
+/// saved, and trims the name.
+fn sanitize(name: &str) -> String {
+    let name = name
+        .chars()
+        .map(|c| match c {
+            '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .take(MAX_NAME_LEN)
+        .collect::<String>();
+
+    name.trim_matches(|c: char| c.is_whitespace() || c == '.')
+        .to_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Orientation;
+    use std::path::PathBuf;
+    use std::time::{Duration, SystemTime};
+
+    #[test]
+    fn test_render() {
+        let asset = SpotlightAsset {
+            path: PathBuf::from("Assets/0a1b2c3d"),
+            format: image::ImageFormat::Jpeg,
+            width: 1920,
+            height: 1080,
+            orientation: Orientation::Landscape,
+            size: 0,
+            // A minute before midnight in UTC, which is another day in many time zones.
+            modified: Some(SystemTime::UNIX_EPOCH + Duration::from_secs(1_767_225_540)),
+        };
+        let hash = ContentHash::from_bytes([0xab; 32]);
+        let metadata = Metadata {
+            title: Some("Lake Bled: Slovenia?".to_string()),
+            ..Default::default()
+        };
+
+        let render = |template: &str, metadata: Option<&Metadata>| {
+            template
+                .parse::<NameTemplate>()
+                .unwrap()
+                .render(&asset, &hash, metadata)
+        };
+
+        assert_eq!(render("{name}", None), "0a1b2c3d");
+        assert_eq!(
+            render("{date}_{title}_{width}x{height}", Some(&metadata)),
+            "2025-12-31_Lake Bled_ Slovenia__1920x1080"
+        );
+        assert_eq!(
+            render("{title} {orientation} {hash}", None),
+            "0a1b2c3d landscape abababab"
+        );
+        assert_eq!(render(" . ", None), "0a1b2c3d");
+        assert_eq!(
+            NameTemplate::default().render(&asset, &hash, None),
+            "0a1b2c3d"
+        );
+
+        for template in ["{nope}", "{name", "name}", "{{name}}"] {
+            assert!(template.parse::<NameTemplate>().is_err(), "{template}");
+        }
+    }
+}
@@ -0,0 +1,138 @@
+use crate::{Error, Result};
+use notify_debouncer_mini::notify::{RecommendedWatcher, RecursiveMode};
+use notify_debouncer_mini::{DebounceEventResult, Debouncer, new_debouncer};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::Duration;
+
+/// Files that changed in the watched directories, returned by [`AssetWatcher::recv`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Changes {
+    /// Assets created or changed, which still exist.
+    pub assets: Vec<PathBuf>,
+    /// Whether metadata next to the assets changed, see [`MetadataIndex`](crate::MetadataIndex).
+    pub metadata: bool,
+}
+
+/// Watches assets directories for new files, and the metadata next to them.
+///
+/// Events are debounced, so a file is reported once Spotlight has finished writing it.
+pub struct AssetWatcher {
+    _debouncer: Debouncer<RecommendedWatcher>,
+    rx: mpsc::Receiver<DebounceEventResult>,
+    dirs: Vec<PathBuf>,
+}
+
+impl AssetWatcher {
+    pub const DEFAULT_DEBOUNCE: Duration = Duration::from_secs(2);
+
+    pub fn new(dirs: &[impl AsRef<Path>], debounce: Duration) -> Result<Self> {
+        let (tx, rx) = mpsc::channel();
+        let mut debouncer = new_debouncer(debounce, tx)?;
+
+        for dir in dirs {
+            let dir = dir.as_ref();
+
+            // Spotlight keeps metadata in `LocalState`, which contains `Assets`.
+            match dir.parent().filter(|_| crate::is_asset_dir(dir)) {
+                Some(local_state) => debouncer
+                    .watcher()
+                    .watch(local_state, RecursiveMode::Recursive)?,
+                None => debouncer
+                    .watcher()
+                    .watch(dir, RecursiveMode::NonRecursive)?,
+            }
+        }
+
+        Ok(AssetWatcher {
+            _debouncer: debouncer,
+            rx,
+            dirs: dirs.iter().map(|dir| dir.as_ref().to_owned()).collect(),
+        })
+    }
+
+    /// Blocks until files are created or changed.
+    ///
+    /// Returns `None` if the watcher stopped.
+    pub fn recv(&self) -> Option<Result<Changes>> {
+        self.rx.recv().ok().map(|result| self.changes(result))
+    }
+
+    /// Like [`recv`](Self::recv), but returns `None` after `timeout` without changes.
+    pub fn recv_timeout(&self, timeout: Duration) -> Option<Result<Changes>> {
+        self.rx
+            .recv_timeout(timeout)
+            .ok()
+            .map(|result| self.changes(result))
+    }
+
+    fn changes(&self, result: DebounceEventResult) -> Result<Changes> {
+        let mut changes = Changes::default();
+
+        for event in result.map_err(Error::Watch)? {
+            let path = event.path;
+            let is_asset = path
+                .parent()
+                .is_some_and(|parent| self.dirs.iter().any(|dir| dir == parent));
+
+            if is_asset {
+                if path.is_file() {
+                    changes.assets.push(path);
+                }
+            } else if !self.dirs.contains(&path) {
+                changes.metadata = true;
+            }
+        }
+
+        changes.assets.sort();
+        changes.assets.dedup();
+
+        Ok(changes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_watch() {
+        let dir = tempfile::tempdir().unwrap();
+        let local_state = dir.path().join("LocalState");
+        let assets = local_state.join("Assets");
+        fs::create_dir_all(&assets).unwrap();
+
+        let watcher = AssetWatcher::new(&[&assets], Duration::from_millis(100)).unwrap();
+        let recv = || {
+            let mut changes = Changes::default();
+            while let Some(result) = watcher.recv_timeout(Duration::from_secs(10)) {
+                let result = result.unwrap();
+                changes.assets.extend(result.assets);
+                changes.metadata |= result.metadata;
+                if changes != Changes::default() {
+                    break;
+                }
+            }
+            changes
+        };
+
+        let path = assets.join("0a1b2c");
+        fs::write(&path, b"partial").unwrap();
+        fs::write(&path, b"complete").unwrap();
+
+        let changes = recv();
+        assert_eq!(changes.assets.len(), 1);
+        assert_eq!(changes.assets[0].file_name(), path.file_name());
+        assert!(!changes.metadata);
+
+        fs::write(local_state.join("metadata.json"), b"{}").unwrap();
+        assert_eq!(
+            recv(),
+            Changes {
+                assets: Vec::new(),
+                metadata: true
+            }
+        );
+    }
+}
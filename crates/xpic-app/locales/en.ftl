@@ -12,3 +12,5 @@ set-as-wallpaper = Set as Wallpaper
 set-as-lock-screen = Set as Lock Screen
 refresh = Refresh
 open-in-browser = Open in Browser
+source-bing = Bing
+source-spotlight = Windows Spotlight
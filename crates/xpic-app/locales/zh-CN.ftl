@@ -12,3 +12,5 @@ set-as-wallpaper = 设为壁纸
 set-as-lock-screen = 设为锁屏
 refresh = 刷新
 open-in-browser = 在浏览器中打开
+source-bing = 必应
+source-spotlight = Windows 聚焦
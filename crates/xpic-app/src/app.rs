@@ -1,31 +1,39 @@
 use crate::assets::Icon;
-use crate::config::Config;
+use crate::cache::ImageCache;
+use crate::config::{Config, Source};
 use crate::data;
 use crate::gallery::{Gallery, Refresh};
 use crate::locale;
 use crate::market_selector::{ChangeMarket, MarketSelector};
+use crate::menu;
 use crate::preview::{OpenPreview, Preview};
 use crate::search_bar::SearchBar;
-use crate::theme::Theme;
+use crate::source_selector::{ChangeSource, SourceSelector};
+use crate::theme::{self, Theme};
 use crate::theme_toggle::ThemeToggle;
 use crate::title_bar::TitleBar;
 use crate::RUNTIME;
 use ahash::AHashMap;
-use chrono::Duration;
+use chrono::{Duration, Utc};
 use gpui::prelude::*;
 use gpui::{
-    div, img, px, App, Context, DismissEvent, Entity, FocusHandle, Focusable, Render, Window,
+    div, img, px, rgb, App, Context, DismissEvent, Entity, FocusHandle, Focusable, Render, Task,
+    Window,
 };
 use gpui_component::input::{InputEvent, InputState};
 use gpui_component::scroll::ScrollableElement;
+use spotlight::SpotlightSource;
 use std::sync::Arc;
 use tracing::{debug, error, info};
 use xpic::bing::Market;
-use xpic::Image;
+use xpic::rotation::Rotation;
+use xpic::source::{LocalSource, WallpaperSource};
+use xpic::{search, Image};
 
 pub struct XpicApp {
     focus_handle: FocusHandle,
 
+    source: Source,
     market: Market,
     cache: AHashMap<Market, Vec<Arc<Image>>>,
     images: Vec<Arc<Image>>,
@@ -33,6 +41,7 @@ pub struct XpicApp {
 
     search_input: Entity<InputState>,
     search_query: String,
+    search_task: Option<Task<()>>,
 
     preview: Option<Entity<Preview>>,
 }
@@ -53,16 +62,20 @@ impl XpicApp {
                 let query = input.read(cx).value().trim().to_string();
                 if this.search_query != query {
                     this.search_query = query;
-                    this.filtered_images = this.search(&this.search_query);
-                    cx.notify();
+                    this.update_search(cx);
                 }
             }
         })
         .detach();
 
+        let source = cx.global::<Config>().source;
         let market = cx.global::<Config>().market;
-        let images = data::to_arc(data::embedded(market));
-        Self::load(market, cx);
+        let images = match source {
+            Source::Bing => data::to_arc(data::embedded(market)),
+            Source::Spotlight => Vec::new(),
+        };
+        Self::load(source, market, cx);
+        Self::rotate(cx);
 
         window.on_window_should_close(cx, |window, cx| {
             let config = cx.global_mut::<Config>();
@@ -70,6 +83,11 @@ impl XpicApp {
             config.window_bounds = Some(window.bounds());
             config.save();
 
+            // Saved before the window closes, as the process exits right after.
+            if let Err(err) = RUNTIME.block_on(ImageCache::get(cx).flush()) {
+                error!("failed to save cache index: {err}");
+            }
+
             true
         });
 
@@ -82,12 +100,14 @@ impl XpicApp {
 
         XpicApp {
             focus_handle,
+            source,
             market,
             cache: AHashMap::new(),
             filtered_images: images.clone(),
             images,
             search_input,
             search_query: String::new(),
+            search_task: None,
             preview: None,
         }
     }
@@ -111,31 +131,106 @@ impl XpicApp {
         locale::set_from_market(market);
         info!(market = market.code(), "market changed");
 
-        if let Some(cached) = self.cache.get(&market) {
+        self.show_market(cx);
+    }
+
+    fn on_change_source(
+        &mut self,
+        &ChangeSource(source): &ChangeSource,
+        _: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        if self.source == source {
+            return;
+        }
+
+        self.source = source;
+        cx.global_mut::<Config>().source = source;
+        info!(?source, "source changed");
+
+        match source {
+            Source::Bing => self.show_market(cx),
+            Source::Spotlight => {
+                self.images = Vec::new();
+                Self::load(source, self.market, cx);
+                self.update_search(cx);
+            }
+        }
+    }
+
+    /// Shows the images of the current market, loading them if they are missing or stale.
+    fn show_market(&mut self, cx: &mut Context<Self>) {
+        if let Some(cached) = self.cache.get(&self.market) {
             self.images = cached.clone();
 
             if data::is_stale(&self.images, Duration::hours(24)) {
-                Self::load(market, cx);
+                Self::load(Source::Bing, self.market, cx);
             }
         } else {
-            self.images = data::to_arc(data::embedded(market));
-            Self::load(market, cx);
+            self.images = data::to_arc(data::embedded(self.market));
+            Self::load(Source::Bing, self.market, cx);
         }
 
         self.filtered_images = self.images.clone();
         self.search_query = String::new();
+        self.search_task = None;
         cx.notify();
     }
 
     fn on_refresh(&mut self, _: &Refresh, _: &mut Window, cx: &mut Context<Self>) {
-        Self::load(self.market, cx);
+        Self::load(self.source, self.market, cx);
+    }
+
+    /// Loads the images of `source`.
+    fn load(source: Source, market: Market, cx: &mut Context<Self>) {
+        match source {
+            Source::Bing => Self::load_bing(market, cx),
+            Source::Spotlight => Self::load_spotlight(cx),
+        }
+    }
+
+    /// Loads the images in the Windows Spotlight cache, which are listed afresh every time.
+    fn load_spotlight(cx: &mut Context<Self>) {
+        debug!("loading spotlight images");
+
+        let handle = RUNTIME.handle().clone();
+
+        cx.spawn(async move |this, cx| {
+            let images = match handle
+                .spawn(async move { SpotlightSource::new().list().await })
+                .await?
+            {
+                Ok(images) => data::into_arc(images),
+                Err(err) => {
+                    error!("failed to list spotlight images: {err}");
+                    return Ok(());
+                }
+            };
+
+            this.update(cx, |this, cx| {
+                // The source was changed while listing.
+                if this.source != Source::Spotlight {
+                    return;
+                }
+
+                info!(count = images.len(), "spotlight images loaded");
+
+                this.images = images;
+                this.update_search(cx);
+            })?;
+
+            Ok::<_, anyhow::Error>(())
+        })
+        .detach();
     }
 
     /// Loads local data and fetch remote if stale.
-    fn load(market: Market, cx: &mut Context<Self>) {
+    fn load_bing(market: Market, cx: &mut Context<Self>) {
         debug!(market = market.code(), "loading data");
 
         let path = cx.global::<Config>().data_path(market);
+        let local_dirs = cx.global::<Config>().local_dirs.clone();
+        let cache = ImageCache::get(cx);
         let handle = RUNTIME.handle().clone();
 
         cx.spawn(async move |this, cx| {
@@ -150,7 +245,7 @@ impl XpicApp {
                     let mut merged = false;
 
                     if data::is_stale(&images, Duration::days(7))
-                        && let Ok(remote) = data::fetch_remote(market).await
+                        && let Ok(remote) = data::fetch_remote(&cache, market).await
                         && !remote.is_empty()
                     {
                         images = data::merge(&images, &remote);
@@ -158,9 +253,10 @@ impl XpicApp {
                     }
 
                     if data::is_stale(&images, Duration::hours(24))
-                        && let Ok(api) = data::fetch(market).await
+                        && let Ok(mut api) = data::fetch(market).await
                         && !api.is_empty()
                     {
+                        data::keep_thumbnail_data(&images, &mut api);
                         images = data::merge(&images, &api);
                         merged = true;
                     }
@@ -171,6 +267,16 @@ impl XpicApp {
                         }
                     }
 
+                    // Local wallpapers are listed afresh every time and never saved.
+                    for dir in local_dirs {
+                        match LocalSource::new(&dir).list().await {
+                            Ok(local) => images = data::merge(&images, &local),
+                            Err(err) => {
+                                error!(dir = %dir.display(), "failed to list local wallpapers: {err}")
+                            }
+                        }
+                    }
+
                     data::into_arc(images)
                 })
                 .await?;
@@ -180,6 +286,11 @@ impl XpicApp {
             }
 
             this.update(cx, |this, cx| {
+                // The source was changed while loading.
+                if this.source != Source::Bing {
+                    return;
+                }
+
                 info!(
                     count = images.len(),
                     market = market.code(),
@@ -188,8 +299,14 @@ impl XpicApp {
 
                 this.images = data::merge(&this.images, &images);
                 this.cache.insert(market, this.images.clone());
-                this.filtered_images = this.search(&this.search_query);
-                cx.notify();
+                this.update_search(cx);
+
+                // The newest image is the wallpaper, unless it is rotated.
+                if cx.global::<Config>().rotation.is_none()
+                    && let Some(image) = this.images.first().cloned()
+                {
+                    Self::tint(&image, cx);
+                }
             })?;
 
             Ok::<_, anyhow::Error>(())
@@ -197,22 +314,103 @@ impl XpicApp {
         .detach();
     }
 
-    fn search(&self, query: impl AsRef<str>) -> Vec<Arc<Image>> {
-        let query = query.as_ref();
-        if query.is_empty() {
-            return self.images.clone();
+    /// Changes the wallpaper to the next image in the gallery periodically, if enabled.
+    ///
+    /// The task stops when the app is closed.
+    fn rotate(cx: &mut Context<Self>) {
+        let Some(config) = cx.global::<Config>().rotation.clone() else {
+            return;
+        };
+        let mut rotation = Rotation::new(config.policy).favorites(config.favorites);
+        if let Some(date) = config.from {
+            rotation = rotation.since(date);
         }
+        if let Some(date) = config.to {
+            rotation = rotation.until(date);
+        }
+        let interval = std::time::Duration::from_secs(config.interval.max(1) * 60);
+        let cache = ImageCache::get(cx);
+        let effects = cx.global::<Config>().effects.clone();
+        let fit = cx.global::<Config>().fit;
+        let handle = RUNTIME.handle().clone();
 
-        let query = query.to_lowercase();
+        info!(policy = %config.policy, minutes = config.interval, "wallpaper rotation enabled");
 
-        self.images
-            .iter()
-            .filter(|img| {
-                img.title.to_lowercase().contains(&query)
-                    || img.copyright.to_lowercase().contains(&query)
+        cx.spawn(async move |this, cx| {
+            loop {
+                let Ok(image) = this.update(cx, |this, _| {
+                    rotation
+                        .next(&this.images, Utc::now().date_naive())
+                        .cloned()
+                }) else {
+                    return;
+                };
+
+                if let Some(image) = image {
+                    let current = image.clone();
+                    let cache = cache.clone();
+                    let effects = effects.clone();
+                    let result = handle
+                        .spawn(async move {
+                            menu::apply_wallpaper(&cache, &effects, fit, &image).await?;
+                            info!(id = %image.id, "wallpaper rotated");
+
+                            Ok::<_, anyhow::Error>(())
+                        })
+                        .await;
+
+                    match result
+                        .map_err(anyhow::Error::from)
+                        .and_then(|result| result)
+                    {
+                        Ok(()) => {
+                            let _ = this.update(cx, |_, cx| Self::tint(&current, cx));
+                        }
+                        Err(err) => error!("failed to rotate wallpaper: {err}"),
+                    }
+                }
+
+                cx.background_executor().timer(interval).await;
+            }
+        })
+        .detach();
+    }
+
+    /// Tints the accent color with the palette of `image`, the current wallpaper, if enabled.
+    fn tint(image: &Image, cx: &mut Context<Self>) {
+        if !cx.global::<Config>().accent_from_wallpaper {
+            return;
+        }
+
+        if let Some(palette) = &image.palette {
+            theme::set_accent(rgb(u32::from(palette.accent())).into(), cx);
+            cx.notify();
+        }
+    }
+
+    /// Filters images by the current search query on a background thread.
+    fn update_search(&mut self, cx: &mut Context<Self>) {
+        if self.search_query.is_empty() {
+            self.search_task = None;
+            self.filtered_images = self.images.clone();
+            cx.notify();
+            return;
+        }
+
+        let images = self.images.clone();
+        let query = self.search_query.clone();
+        let results = cx.background_spawn(async move { search::search(&images, query) });
+
+        // Replacing the task cancels any search still running for an older query.
+        self.search_task = Some(cx.spawn(async move |this, cx| {
+            let results = results.await;
+
+            this.update(cx, |this, cx| {
+                this.filtered_images = results;
+                cx.notify();
             })
-            .cloned()
-            .collect()
+            .ok();
+        }));
     }
 
     fn on_open_preview(
@@ -280,7 +478,10 @@ impl XpicApp {
                     .items_center()
                     .mr_1p5()
                     .h(theme.title_bar_height)
-                    .child(MarketSelector::new(self.market))
+                    .child(SourceSelector::new(self.source))
+                    .when(self.source == Source::Bing, |el| {
+                        el.child(MarketSelector::new(self.market))
+                    })
                     .child(ThemeToggle),
             )
     }
@@ -298,6 +499,7 @@ impl Render for XpicApp {
             .flex_col()
             .relative()
             .on_action(cx.listener(Self::on_change_market))
+            .on_action(cx.listener(Self::on_change_source))
             .on_action(cx.listener(Self::on_refresh))
             .on_action(cx.listener(Self::on_open_preview))
             .child(self.render_title_bar(cx))
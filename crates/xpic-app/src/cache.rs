@@ -0,0 +1,30 @@
+use crate::config::Config;
+use gpui::{App, Global};
+use std::sync::Arc;
+use tracing::error;
+use xpic::cache::Cache;
+
+/// The image cache shared by all views, stored as a global.
+#[derive(Clone)]
+pub struct ImageCache(Arc<Cache>);
+
+impl Global for ImageCache {}
+
+impl ImageCache {
+    pub fn new(config: &Config) -> Self {
+        let cache = Cache::open(&config.cache_dir)
+            .or_else(|err| {
+                error!("failed to open cache: {err}");
+                Cache::open(std::env::temp_dir().join("Xpic").join("cache"))
+            })
+            .expect("temporary cache directory should be writable")
+            .max_size(Some(config.cache_max_size));
+
+        Self(Arc::new(cache))
+    }
+
+    /// Returns the global cache.
+    pub fn get(cx: &App) -> Arc<Cache> {
+        cx.global::<Self>().0.clone()
+    }
+}
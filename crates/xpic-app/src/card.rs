@@ -1,9 +1,9 @@
-use crate::image::Image;
+use crate::image::{Image, Placeholder};
 use crate::spinner::Spinner;
 use crate::theme::Theme;
 use gpui::{
-    div, img, prelude::*, App, ClickEvent, ElementId, MouseButton, SharedString, StyleRefinement,
-    Window,
+    div, img, prelude::*, rgb, App, ClickEvent, ElementId, Hsla, MouseButton, SharedString,
+    StyleRefinement, Window,
 };
 use gpui_component::StyledExt;
 use std::cell::Cell;
@@ -16,6 +16,9 @@ pub struct Card {
     id: ElementId,
     title: Option<SharedString>,
     image: Image,
+    /// Shown until the thumbnail loads, instead of a spinner.
+    placeholder: Option<Hsla>,
+    blurhash: Option<SharedString>,
     /// Shared context menu index tracker.
     context_menu_index: Option<(usize, Rc<Cell<Option<usize>>>)>,
     style: StyleRefinement,
@@ -47,12 +50,26 @@ impl Card {
             id: id.clone().into(),
             title: None,
             image: Image::new(id),
+            placeholder: None,
+            blurhash: None,
             context_menu_index: None,
             on_click: None,
             style: StyleRefinement::default(),
         }
     }
 
+    pub fn from_image(image: &xpic::Image) -> Self {
+        Self {
+            image: Image::from_image(image),
+            placeholder: image
+                .palette
+                .as_ref()
+                .map(|palette| rgb(u32::from(palette.dominant)).into()),
+            blurhash: image.blurhash.clone().map(SharedString::from),
+            ..Self::new(&image.id)
+        }
+    }
+
     pub fn title(mut self, title: impl Into<SharedString>) -> Self {
         self.title = Some(title.into());
         self
@@ -78,6 +95,8 @@ impl RenderOnce for Card {
     fn render(self, _: &mut Window, cx: &mut App) -> impl IntoElement {
         let theme = cx.global::<Theme>();
 
+        let placeholder = self.placeholder;
+        let blurhash = self.blurhash.clone();
         let img_source = self.image.source();
         let lightened_img_source = self.image.clone().lighten_level(0.1).source();
 
@@ -113,7 +132,13 @@ impl RenderOnce for Card {
                         img(img_source)
                             .id("card-img")
                             .refine_style(&img_style)
-                            .with_loading(|| Loading.into_any_element()),
+                            .with_loading(move || {
+                                Loading {
+                                    placeholder,
+                                    blurhash: blurhash.clone(),
+                                }
+                                .into_any_element()
+                            }),
                     )
                     .child(
                         img(lightened_img_source)
@@ -146,7 +171,11 @@ impl RenderOnce for Card {
 }
 
 #[derive(IntoElement)]
-struct Loading;
+struct Loading {
+    /// The dominant color of the image, if known.
+    placeholder: Option<Hsla>,
+    blurhash: Option<SharedString>,
+}
 
 impl RenderOnce for Loading {
     fn render(self, _: &mut Window, _: &mut App) -> impl IntoElement {
@@ -155,10 +184,17 @@ impl RenderOnce for Loading {
             .flex()
             .items_center()
             .justify_center()
-            .child(
-                Spinner::new()
-                    .duration(Duration::from_secs_f64(0.5))
-                    .size_6(),
-            )
+            .when_some(self.placeholder, |this, color| this.rounded_t_sm().bg(color))
+            .map(|this| match self.blurhash {
+                Some(blurhash) => {
+                    this.child(img(Placeholder::source(blurhash)).size_full().rounded_t_sm())
+                }
+                None if self.placeholder.is_some() => this,
+                None => this.child(
+                    Spinner::new()
+                        .duration(Duration::from_secs_f64(0.5))
+                        .size_6(),
+                ),
+            })
     }
 }
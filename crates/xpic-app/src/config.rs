@@ -1,18 +1,45 @@
 use crate::locale;
 use crate::theme::Appearance;
+use chrono::NaiveDate;
 use gpui::{Bounds, Global, Pixels};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use sha2::{Digest, Sha256};
 use std::path::PathBuf;
 use tracing::error;
 use xpic::bing::Market;
+use xpic::cache::Cache;
+use xpic::effects::Pipeline;
+use xpic::rotation::Policy;
+use xpic::wallpaper::Fit;
 
 const APP_NAME: &str = "Xpic";
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
     pub cache_dir: PathBuf,
+    /// Maximum size of the image cache in bytes.
+    #[serde(default = "default_cache_max_size")]
+    pub cache_max_size: u64,
     pub data_dir: PathBuf,
+    /// Where the gallery takes its wallpapers from.
+    #[serde(default)]
+    pub source: Source,
+    /// Folders of local wallpapers shown alongside Bing images.
+    #[serde(default)]
+    pub local_dirs: Vec<PathBuf>,
+    /// Changes the desktop wallpaper periodically while the app is running.
+    #[serde(default)]
+    pub rotation: Option<RotationConfig>,
+    /// Effects applied to wallpapers before they are set or saved, e.g. `"dim=0.3,caption"`.
+    #[serde(default)]
+    pub effects: Pipeline,
+    /// How wallpapers are framed for each monitor, `"per-monitor"` or `"span"`, if the desktop
+    /// supports it. Without it, every monitor shows the same image.
+    #[serde(default = "default_fit")]
+    pub fit: Option<Fit>,
+    /// Tints the accent color with the colors of the current wallpaper.
+    #[serde(default)]
+    pub accent_from_wallpaper: bool,
 
     pub market: Market,
     pub appearance: Appearance,
@@ -27,7 +54,14 @@ impl Default for Config {
 
         Self {
             cache_dir: base.join("cache"),
+            cache_max_size: default_cache_max_size(),
             data_dir: base.join("data"),
+            source: Source::default(),
+            local_dirs: Vec::new(),
+            rotation: None,
+            effects: Pipeline::default(),
+            fit: default_fit(),
+            accent_from_wallpaper: false,
             market: locale::default_market(),
             appearance: Appearance::Dark,
             window_bounds: None,
@@ -35,6 +69,45 @@ impl Default for Config {
     }
 }
 
+fn default_cache_max_size() -> u64 {
+    Cache::DEFAULT_MAX_SIZE
+}
+
+fn default_fit() -> Option<Fit> {
+    Some(Fit::PerMonitor)
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum Source {
+    /// Bing wallpapers of the selected market, alongside images in the local folders.
+    #[default]
+    Bing,
+    /// Images in the Windows Spotlight cache.
+    Spotlight,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RotationConfig {
+    pub policy: Policy,
+    /// Minutes between wallpaper changes.
+    #[serde(default = "default_rotation_interval")]
+    pub interval: u64,
+    /// IDs or names of the images preferred by the `favorites` policy.
+    #[serde(default)]
+    pub favorites: Vec<String>,
+    /// First date of the images to rotate through.
+    #[serde(default)]
+    pub from: Option<NaiveDate>,
+    /// Last date of the images to rotate through.
+    #[serde(default)]
+    pub to: Option<NaiveDate>,
+}
+
+fn default_rotation_interval() -> u64 {
+    60
+}
+
 impl Global for Config {}
 
 impl Config {
@@ -48,11 +121,6 @@ impl Config {
         }
     }
 
-    pub fn image_cache(&self, url: impl AsRef<str>) -> PathBuf {
-        let hash = Sha256::digest(url.as_ref().as_bytes());
-        self.cache_dir.join(hex::encode(hash))
-    }
-
     pub fn data_path(&self, market: Market) -> PathBuf {
         self.data_dir.join(format!("{}.json", market.code()))
     }
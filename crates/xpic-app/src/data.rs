@@ -1,4 +1,3 @@
-use ahash::AHashSet;
 use chrono::{Duration, Utc};
 use serde::Serialize;
 use std::borrow::Borrow;
@@ -6,8 +5,11 @@ use std::path::Path;
 use std::sync::{Arc, LazyLock};
 use tracing::{debug, info};
 use xpic::bing::{Market, QueryParams};
+use xpic::cache::Cache;
 use xpic::{Image, ID};
 
+pub use xpic::merge;
+
 macro_rules! data {
     ($($market:ident => $filename:literal),* $(,)?) => {
         $(
@@ -55,25 +57,6 @@ pub async fn load(path: impl AsRef<Path>, market: Market) -> anyhow::Result<Vec<
     Ok(images)
 }
 
-/// Merges two image lists, deduplicating by `id`. Items from `new` take priority over `existing`.
-/// The result is sorted by `start_date` descending.
-pub fn merge<T>(existing: &[T], new: &[T]) -> Vec<T>
-where
-    T: Borrow<Image> + Clone,
-{
-    let mut seen = AHashSet::new();
-    let mut result = Vec::with_capacity(existing.len() + new.len());
-
-    for img in new.iter().chain(existing.iter()) {
-        if seen.insert(img.borrow().id.clone()) {
-            result.push(img.clone());
-        }
-    }
-
-    result.sort_by(|a, b| b.borrow().start_date.cmp(&a.borrow().start_date));
-    result
-}
-
 pub fn to_arc(images: &[Image]) -> Vec<Arc<Image>> {
     images.iter().cloned().map(Arc::new).collect()
 }
@@ -126,7 +109,31 @@ pub async fn fetch(market: Market) -> anyhow::Result<Vec<Image>> {
     Ok(images)
 }
 
-pub async fn fetch_remote(market: Market) -> anyhow::Result<Vec<Image>> {
+/// Copies the thumbnail data and rerun marks of `existing` images to the same images in `new`,
+/// such as those listed by the API, which has none of them.
+pub fn keep_thumbnail_data(existing: &[Image], new: &mut [Image]) {
+    for image in new.iter_mut() {
+        let Some(existing) = existing.iter().find(|existing| existing.id == image.id) else {
+            continue;
+        };
+
+        if image.palette.is_none() {
+            image.palette = existing.palette.clone();
+        }
+        if image.blurhash.is_none() {
+            image.blurhash = existing.blurhash.clone();
+        }
+        if image.perceptual_hash.is_none() {
+            image.perceptual_hash = existing.perceptual_hash;
+        }
+        if image.rerun_of.is_none() {
+            image.rerun_of = existing.rerun_of.clone();
+        }
+    }
+}
+
+/// Fetches metadata from the GitHub repository, revalidating the cached copy if there is one.
+pub async fn fetch_remote(cache: &Cache, market: Market) -> anyhow::Result<Vec<Image>> {
     let url = format!(
         "https://raw.githubusercontent.com/fhluo/xpic/main/data/{}.json",
         market.code()
@@ -134,7 +141,7 @@ pub async fn fetch_remote(market: Market) -> anyhow::Result<Vec<Image>> {
     debug!(%url, "fetching remote data");
 
     let images = filter_by_market(
-        reqwest::get(&url).await?.error_for_status()?.json().await?,
+        serde_json::from_slice(&cache.revalidate(&url).await?)?,
         market,
     );
     info!(count = images.len(), "fetched from remote");
@@ -51,7 +51,7 @@ impl RenderOnce for Gallery {
 
         for (i, image) in self.images.iter().enumerate() {
             gallery = gallery.child(
-                Card::new(&image.id)
+                Card::from_image(image)
                     .title(Self::display_title(image))
                     .context_menu_index(i, context_menu_index.clone())
                     .on_click(move |_, window, cx| {
@@ -85,17 +85,24 @@ impl RenderOnce for Gallery {
                 Some(i) if i < images.len() => {
                     let image = &images[i];
 
-                    menu.item(menu::open_in_browser(&image.id))
-                        .separator()
-                        .item(menu::save(&image.id))
-                        .submenu(t!("save-as"), window, cx, menu::save_submenu(&image.id))
-                        .separator()
-                        .item(menu::copy(t!("copy-title"), &image.title))
-                        .item(menu::copy_image(&image.id))
-                        .submenu(t!("copy"), window, cx, menu::copy_submenu(image))
-                        .separator()
-                        .item(menu::set_wallpaper(&image.id))
-                        .item(menu::set_lock_screen(&image.id))
+                    // Local images have no Bing page, and only images Bing serves by ID can be
+                    // resized.
+                    let local = menu::is_local(image);
+
+                    menu.when(!local, |menu| {
+                        menu.item(menu::open_in_browser(image)).separator()
+                    })
+                    .item(menu::save(image))
+                    .when(image.has_thumbnails(), |menu| {
+                        menu.submenu(t!("save-as"), window, cx, menu::save_submenu(image))
+                    })
+                    .separator()
+                    .item(menu::copy(t!("copy-title"), &image.title))
+                    .item(menu::copy_image(image))
+                    .submenu(t!("copy"), window, cx, menu::copy_submenu(image))
+                    .separator()
+                    .item(menu::set_wallpaper(image))
+                    .item(menu::set_lock_screen(image))
                 }
                 _ => menu.item(
                     PopupMenuItem::new(t!("refresh"))
@@ -1,20 +1,21 @@
-use crate::config::Config;
+use crate::cache::ImageCache;
 use crate::RUNTIME;
 use gpui::{
     img, App, Asset, ImageCacheError, ImageSource, Img, IntoElement, RenderImage, SharedString,
 };
-use image::RgbaImage;
+use image::{DynamicImage, RgbaImage};
 use photon_rs::colour_spaces::lighten_hsl;
 use photon_rs::PhotonImage;
 use std::hash::{Hash, Hasher};
-use std::path::Path;
 use std::sync::Arc;
-use tracing::debug;
 use xpic::bing::{ThumbnailParams, ThumbnailQuery, UrlBuilder};
 
 #[derive(Debug, Clone)]
 pub struct Image {
     url: UrlBuilder,
+    /// URL of an image Bing doesn't serve by ID, such as a local file, which is shown as is
+    /// instead of a Bing thumbnail.
+    original: Option<SharedString>,
     lighten_level: Option<f32>,
 }
 
@@ -22,10 +23,22 @@ impl Image {
     pub fn new(id: impl Into<String>) -> Self {
         Self {
             url: UrlBuilder::new(id),
+            original: None,
             lighten_level: None,
         }
     }
 
+    /// Shows `image`, whichever source it was listed by.
+    pub fn from_image(image: &xpic::Image) -> Self {
+        let mut this = Self::new(&image.id);
+
+        if !image.has_thumbnails() {
+            this.original = Some(image.url.to_string().into());
+        }
+
+        this
+    }
+
     pub fn lighten_level(mut self, level: f32) -> Self {
         self.lighten_level = Some(level);
 
@@ -81,7 +94,10 @@ impl Image {
 
     pub fn source(&self) -> ImageSource {
         let source = ImageAssetSource {
-            url: self.url.build().expect("URL should be valid").into(),
+            url: self
+                .original
+                .clone()
+                .unwrap_or_else(|| self.url.build().expect("URL should be valid").into()),
             lighten_level: self.lighten_level,
         };
 
@@ -122,30 +138,6 @@ impl IntoElement for Image {
     }
 }
 
-/// Fetches data from a URL, caching to the given path.
-pub async fn fetch_cached(
-    url: impl AsRef<str>,
-    cache_path: impl AsRef<Path>,
-) -> anyhow::Result<Vec<u8>> {
-    let url = url.as_ref();
-    let cache_path = cache_path.as_ref();
-
-    if cache_path.exists() {
-        debug!(%url, "cache hit");
-        return Ok(tokio::fs::read(cache_path).await?);
-    }
-
-    debug!(%url, "cache miss, fetching");
-    let data = reqwest::get(url).await?.error_for_status()?.bytes().await?;
-
-    if let Some(dir) = cache_path.parent() {
-        let _ = tokio::fs::create_dir_all(dir).await;
-    }
-    let _ = tokio::fs::write(cache_path, &data).await;
-
-    Ok(data.to_vec())
-}
-
 impl Asset for Image {
     type Source = ImageAssetSource;
     type Output = Result<Arc<RenderImage>, ImageCacheError>;
@@ -154,12 +146,12 @@ impl Asset for Image {
         ImageAssetSource { url, lighten_level }: Self::Source,
         cx: &mut App,
     ) -> impl Future<Output = Self::Output> + Send + 'static {
-        let path = cx.global::<Config>().image_cache(&url);
+        let cache = ImageCache::get(cx);
         let handle = RUNTIME.handle().clone();
 
         async move {
             let bytes = handle
-                .spawn(async move { fetch_cached(&url, &path).await })
+                .spawn(async move { cache.fetch(&url).await })
                 .await
                 .map_err(|err| ImageCacheError::Other(Arc::new(err.into())))?
                 .map_err(|err| ImageCacheError::Other(Arc::new(err)))?;
@@ -168,3 +160,39 @@ impl Asset for Image {
         }
     }
 }
+
+/// A BlurHash decoded into a tiny image, shown stretched while the real image loads.
+pub struct Placeholder;
+
+impl Placeholder {
+    const WIDTH: u32 = 32;
+    const HEIGHT: u32 = 18;
+
+    pub fn source(blurhash: impl Into<SharedString>) -> ImageSource {
+        let blurhash = blurhash.into();
+
+        ImageSource::Custom(Arc::new(move |window, cx| {
+            window.use_asset::<Placeholder>(&blurhash, cx)
+        }))
+    }
+}
+
+impl Asset for Placeholder {
+    type Source = SharedString;
+    type Output = Result<Arc<RenderImage>, ImageCacheError>;
+
+    fn load(
+        blurhash: Self::Source,
+        _: &mut App,
+    ) -> impl Future<Output = Self::Output> + Send + 'static {
+        async move {
+            let pixels = xpic::blurhash::decode(&blurhash, Self::WIDTH, Self::HEIGHT, 1.0)
+                .map_err(|err| ImageCacheError::Other(Arc::new(err)))?;
+            let img = DynamicImage::ImageRgb8(pixels).into_rgba8();
+
+            Ok(Arc::new(RenderImage::new([image::Frame::new(
+                Image::rgba_to_bgra(img),
+            )])))
+        }
+    }
+}
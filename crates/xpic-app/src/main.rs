@@ -2,6 +2,7 @@
 
 use crate::app::XpicApp;
 use crate::assets::Assets;
+use crate::cache::ImageCache;
 use crate::config::Config;
 use crate::single_instance::ensure_single_instance;
 use crate::theme::{Theme, apply_mica_theme, enable_mica_backdrop};
@@ -21,6 +22,7 @@ macro_rules! t {
 
 mod app;
 mod assets;
+mod cache;
 mod card;
 mod config;
 mod data;
@@ -34,11 +36,11 @@ mod menu;
 mod preview;
 mod search_bar;
 mod single_instance;
+mod source_selector;
 mod spinner;
 mod theme;
 mod theme_toggle;
 mod title_bar;
-mod wallpaper;
 
 pub static RUNTIME: LazyLock<tokio::runtime::Runtime> = LazyLock::new(|| {
     tokio::runtime::Builder::new_multi_thread()
@@ -68,6 +70,7 @@ fn main() -> anyhow::Result<()> {
         info!(market = config.market.code(), "config loaded");
 
         cx.set_global(Theme::from(config.appearance));
+        cx.set_global(ImageCache::new(&config));
         cx.set_global(config);
 
         open_main_window(cx);
@@ -1,14 +1,17 @@
+use crate::cache::ImageCache;
 use crate::config::Config;
-use crate::image::fetch_cached;
-use crate::wallpaper;
 use crate::RUNTIME;
 use anyhow::anyhow;
 use gpui::{prelude::*, App, ClipboardItem, Context, ImageFormat, SharedString, Window};
 use gpui_component::menu::{PopupMenu, PopupMenuItem};
 use gpui_component::IconNamed;
+use std::path::Path;
 use tracing::{debug, error, info};
-use xpic::bing::{ThumbnailParams, UrlBuilder};
+use xpic::bing::ThumbnailParams;
+use xpic::cache::Cache;
+use xpic::effects::Pipeline;
 use xpic::Copyright;
+use xpic::wallpaper::{self, Fit};
 
 #[derive(Clone, Copy)]
 pub enum MenuIcon {
@@ -42,6 +45,40 @@ impl IconNamed for MenuIcon {
     }
 }
 
+/// Returns whether `image` is a file on disk, such as a local or Spotlight wallpaper, rather than
+/// a Bing image.
+pub fn is_local(image: &xpic::Image) -> bool {
+    image.url.scheme() == "file"
+}
+
+/// Sets `image` as the desktop wallpaper with `effects` applied, framed for each monitor
+/// according to `fit` if the desktop supports it.
+pub async fn apply_wallpaper(
+    cache: &Cache,
+    effects: &Pipeline,
+    fit: Option<Fit>,
+    image: &xpic::Image,
+) -> Result<(), anyhow::Error> {
+    let backend = wallpaper::detect()?;
+
+    match fit {
+        Some(fit) if backend.supports_monitors() => {
+            let monitors = backend.monitors()?;
+            let mut wallpapers = wallpaper::render(cache, image, &monitors, fit).await?;
+            for (_, path) in &mut wallpapers {
+                *path = effects.process(cache, image, path).await?;
+            }
+            backend.set_monitor_wallpapers(&wallpapers)?;
+        }
+        _ => {
+            let path = cache.fetch_path(&image.download_url()?).await?;
+            backend.set_wallpaper(&effects.process(cache, image, &path).await?)?;
+        }
+    }
+
+    Ok(())
+}
+
 pub fn copy(label: impl Into<SharedString>, text: impl Into<String>) -> PopupMenuItem {
     copy_with_icon(MenuIcon::Copy, label, text)
 }
@@ -59,24 +96,32 @@ pub fn copy_with_icon(
         })
 }
 
-pub fn copy_image(id: impl Into<String>) -> PopupMenuItem {
-    let id = id.into();
+pub fn copy_image(image: &xpic::Image) -> PopupMenuItem {
+    let image = image.clone();
 
     PopupMenuItem::new(t!("copy-image"))
         .icon(MenuIcon::ClipboardCopy)
         .on_click(move |_, _, cx| {
-            let url = UrlBuilder::new(&id).build().expect("URL should be valid");
-            let cache_path = cx.global::<Config>().image_cache(&url);
+            let image = image.clone();
+            let cache = ImageCache::get(cx);
             let handle = RUNTIME.handle().clone();
             debug!("copying image to clipboard");
 
             cx.spawn(async move |cx| {
                 let bytes = handle
-                    .spawn(async move { fetch_cached(&url, &cache_path).await })
+                    .spawn(async move { cache.fetch(&image.download_url()?).await })
                     .await??;
 
+                // Bing images are JPEGs, local ones may be in any format.
+                let format = match image::guess_format(&bytes) {
+                    Ok(image::ImageFormat::Png) => ImageFormat::Png,
+                    Ok(image::ImageFormat::WebP) => ImageFormat::Webp,
+                    Ok(image::ImageFormat::Gif) => ImageFormat::Gif,
+                    _ => ImageFormat::Jpeg,
+                };
+
                 cx.update(|cx| {
-                    let image = gpui::Image::from_bytes(ImageFormat::Jpeg, bytes);
+                    let image = gpui::Image::from_bytes(format, bytes);
                     cx.write_to_clipboard(ClipboardItem::new_image(&image));
                 });
 
@@ -91,7 +136,11 @@ pub fn copy_submenu(
 ) -> impl Fn(PopupMenu, &mut Window, &mut Context<PopupMenu>) -> PopupMenu + 'static {
     let copyright_text = image.copyright.clone();
     let copyright = Copyright::parse(&image.copyright);
-    let image_link = UrlBuilder::new(&image.id).build().ok();
+    let image_link = if is_local(image) {
+        None
+    } else {
+        image.download_url().ok()
+    };
 
     move |menu, _, _| {
         menu.when_none(&copyright, |menu| {
@@ -119,12 +168,12 @@ pub fn copy_submenu(
     }
 }
 
-pub fn save(id: impl Into<String>) -> PopupMenuItem {
-    let id = id.into();
+pub fn save(image: &xpic::Image) -> PopupMenuItem {
+    let image = image.clone();
     PopupMenuItem::new(t!("save"))
         .icon(MenuIcon::Save)
         .on_click(move |_, _, cx| {
-            let _ = download(&id, None, cx);
+            let _ = download(&image, None, cx);
         })
 }
 
@@ -149,43 +198,73 @@ const RESOLUTIONS: &[ResolutionPreset] = &[
 ];
 
 pub fn save_submenu(
-    id: impl Into<String>,
+    image: &xpic::Image,
 ) -> impl Fn(PopupMenu, &mut Window, &mut Context<PopupMenu>) -> PopupMenu + 'static {
-    let id = id.into();
+    let image = image.clone();
     move |mut menu, _, _| {
         for &ResolutionPreset { label, resolution } in RESOLUTIONS {
-            let id = id.clone();
+            let image = image.clone();
             menu = menu.item(PopupMenuItem::new(label).on_click(move |_, _, cx| {
-                let _ = download(&id, Some(resolution), cx);
+                let _ = download(&image, Some(resolution), cx);
             }));
         }
         menu
     }
 }
 
+/// Saves the image to a file chosen by the user, with the effects from the config applied.
 pub fn download(
-    id: &str,
+    image: &xpic::Image,
     resolution: Option<(u32, u32)>,
     cx: &mut App,
 ) -> Result<(), anyhow::Error> {
-    let mut builder = UrlBuilder::new(id);
-    let mut id = xpic::ID::parse(id).ok_or_else(|| anyhow!("invalid ID"))?;
-    id.uhd = true;
+    let (url, filename) = if is_local(image) {
+        let path = image
+            .url
+            .to_file_path()
+            .map_err(|_| anyhow!("invalid file URL"))?;
+        let filename = path
+            .file_name()
+            .ok_or_else(|| anyhow!("invalid file URL"))?
+            .to_string_lossy()
+            .into_owned();
+
+        (image.url.to_string(), filename)
+    } else if !image.has_thumbnails() {
+        // Bing links to some images by file path, which come in one resolution only.
+        (image.download_url()?, image.id.clone())
+    } else {
+        let mut builder = image.url_builder();
+        let mut id = xpic::ID::parse(&image.id).ok_or_else(|| anyhow!("invalid ID"))?;
+        id.uhd = true;
+
+        if let Some((w, h)) = resolution {
+            builder = builder.width(w).height(h).no_padding();
+
+            id.uhd = false;
+            id.width = Some(w as usize);
+            id.height = Some(h as usize);
+        }
 
-    if let Some((w, h)) = resolution {
-        builder = builder.width(w).height(h).no_padding();
+        (builder.build()?, id.to_string())
+    };
 
-        id.uhd = false;
-        id.width = Some(w as usize);
-        id.height = Some(h as usize);
-    }
+    let cache = ImageCache::get(cx);
+    let effects = cx.global::<Config>().effects.clone();
+    let image = image.clone();
 
-    let url = builder.build()?;
-    let cache_path = cx.global::<Config>().image_cache(&url);
+    // Processed images are encoded as JPEG.
+    let filename = if effects.is_empty() {
+        filename
+    } else {
+        Path::new(&filename)
+            .with_extension("jpg")
+            .to_string_lossy()
+            .into_owned()
+    };
 
     let dir = dirs::picture_dir()
         .unwrap_or_else(|| dirs::download_dir().unwrap_or_else(std::env::temp_dir));
-    let filename = id.to_string();
     let receiver = cx.prompt_for_new_path(&dir, Some(&filename));
 
     RUNTIME.handle().spawn(async move {
@@ -193,7 +272,11 @@ pub fn download(
             .await??
             .ok_or_else(|| anyhow!("failed to get save path"))?;
 
-        let data = fetch_cached(&url, &cache_path).await?;
+        let mut data = cache.fetch(&url).await?;
+        if !effects.is_empty() {
+            data = tokio::task::spawn_blocking(move || effects.apply_bytes(&data, &image))
+                .await??;
+        }
         tokio::fs::write(&save_path, &data).await?;
         info!(path = %save_path.display(), "image saved");
 
@@ -203,34 +286,31 @@ pub fn download(
     Ok(())
 }
 
-pub fn open_in_browser(id: impl Into<String>) -> PopupMenuItem {
-    let id = id.into();
+pub fn open_in_browser(image: &xpic::Image) -> PopupMenuItem {
+    let url = image.download_url();
 
     PopupMenuItem::new(t!("open-in-browser"))
         .icon(MenuIcon::ExternalLink)
         .on_click(move |_, _, cx| {
-            if let Ok(url) = UrlBuilder::new(&id).build() {
-                cx.open_url(&url);
+            if let Ok(url) = &url {
+                cx.open_url(url);
             }
         })
 }
 
-pub fn set_wallpaper(id: impl Into<String>) -> PopupMenuItem {
-    let id = id.into();
+pub fn set_wallpaper(image: &xpic::Image) -> PopupMenuItem {
+    let image = image.clone();
 
     PopupMenuItem::new(t!("set-as-wallpaper"))
         .icon(MenuIcon::Wallpaper)
         .on_click(move |_, _, cx| {
-            let url = UrlBuilder::new(&id).build().expect("URL should be valid");
-            let cache_path = cx.global::<Config>().image_cache(&url);
+            let cache = ImageCache::get(cx);
+            let effects = cx.global::<Config>().effects.clone();
+            let fit = cx.global::<Config>().fit;
+            let image = image.clone();
 
             RUNTIME.handle().spawn(async move {
-                if let Err(err) = fetch_cached(&url, &cache_path).await {
-                    error!("failed to fetch image: {err}");
-                    return;
-                }
-
-                if let Err(err) = wallpaper::set_wallpaper(&cache_path) {
+                if let Err(err) = apply_wallpaper(&cache, &effects, fit, &image).await {
                     error!("failed to set wallpaper: {err}");
                 } else {
                     info!("wallpaper set");
@@ -239,22 +319,32 @@ pub fn set_wallpaper(id: impl Into<String>) -> PopupMenuItem {
         })
 }
 
-pub fn set_lock_screen(id: impl Into<String>) -> PopupMenuItem {
-    let id = id.into();
+pub fn set_lock_screen(image: &xpic::Image) -> PopupMenuItem {
+    let image = image.clone();
 
     PopupMenuItem::new(t!("set-as-lock-screen"))
         .icon(MenuIcon::Lock)
         .on_click(move |_, _, cx| {
-            let url = UrlBuilder::new(&id).build().expect("URL should be valid");
-            let cache_path = cx.global::<Config>().image_cache(&url);
+            let cache = ImageCache::get(cx);
+            let effects = cx.global::<Config>().effects.clone();
+            let image = image.clone();
 
             RUNTIME.handle().spawn(async move {
-                if let Err(err) = fetch_cached(&url, &cache_path).await {
-                    error!("failed to fetch image: {err}");
-                    return;
-                }
-
-                if let Err(err) = wallpaper::set_lock_screen(&cache_path).await {
+                let path = async {
+                    let path = cache.fetch_path(&image.download_url()?).await?;
+                    effects.process(&cache, &image, &path).await
+                };
+                let path = match path.await {
+                    Ok(path) => path,
+                    Err(err) => {
+                        error!("failed to fetch image: {err}");
+                        return;
+                    }
+                };
+
+                if let Err(err) =
+                    wallpaper::detect().and_then(|backend| backend.set_lock_screen(&path))
+                {
                     error!("failed to set lock screen: {err}");
                 } else {
                     info!("lock screen set");
@@ -1,4 +1,4 @@
-use crate::image::Image;
+use crate::image::{Image, Placeholder};
 use crate::spinner::Spinner;
 use crate::theme::Theme;
 use gpui::prelude::*;
@@ -57,13 +57,13 @@ impl Preview {
             (max_w, max_w / aspect)
         };
 
-        let thumbnail_source = Image::new(&self.image.id)
+        let thumbnail_source = Image::from_image(&self.image)
             .width(theme.thumbnail_width)
             .height(theme.thumbnail_height)
             .no_padding()
             .source();
 
-        let hd_source = Image::new(&self.image.id)
+        let hd_source = Image::from_image(&self.image)
             .width(1920)
             .height(1080)
             .no_padding()
@@ -76,6 +76,15 @@ impl Preview {
             .rounded_lg()
             .overflow_hidden()
             .occlude()
+            .when_some(self.image.blurhash.clone(), |this, blurhash| {
+                this.child(
+                    img(Placeholder::source(blurhash))
+                        .absolute()
+                        .inset_0()
+                        .size_full()
+                        .rounded_lg(),
+                )
+            })
             .child(img(thumbnail_source).size_full().rounded_lg())
             .child(
                 img(hd_source)
@@ -0,0 +1,49 @@
+use crate::config::Source;
+use crate::fluent_icon_button::FluentIconButton;
+use gpui::prelude::*;
+use gpui::{Action, Anchor, App, Window};
+use gpui_component::menu::{DropdownMenu, PopupMenuItem};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema, Action)]
+pub struct ChangeSource(pub Source);
+
+fn source_display_name(source: Source) -> String {
+    match source {
+        Source::Bing => t!("source-bing"),
+        Source::Spotlight => t!("source-spotlight"),
+    }
+}
+
+#[derive(IntoElement)]
+pub struct SourceSelector {
+    selected: Source,
+}
+
+impl SourceSelector {
+    pub fn new(selected: Source) -> Self {
+        Self { selected }
+    }
+}
+
+impl RenderOnce for SourceSelector {
+    fn render(self, _: &mut Window, _cx: &mut App) -> impl IntoElement {
+        FluentIconButton::new("source-selector", "\u{E91B}").dropdown_menu_with_anchor(
+            Anchor::TopRight,
+            move |menu, _, _| {
+                let mut menu = menu;
+
+                for source in [Source::Bing, Source::Spotlight] {
+                    menu = menu.item(
+                        PopupMenuItem::new(source_display_name(source))
+                            .checked(source == self.selected)
+                            .action(Box::new(ChangeSource(source))),
+                    );
+                }
+
+                menu
+            },
+        )
+    }
+}
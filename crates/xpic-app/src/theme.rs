@@ -230,6 +230,32 @@ fn sync_component_theme(mode: Appearance, cx: &mut App) {
     theme.tokens.muted = theme.colors.muted.into();
     theme.tokens.popover = theme.colors.popover.into();
     theme.tokens.border = theme.colors.border.into();
+
+    if let Some(&Accent(accent)) = cx.try_global::<Accent>() {
+        apply_accent(accent, cx);
+    }
+}
+
+/// Accent color taken from the current wallpaper, kept when the appearance changes.
+#[derive(Debug, Clone, Copy)]
+pub struct Accent(pub Hsla);
+
+impl Global for Accent {}
+
+/// Tints components with `accent`. Views are repainted when they are next notified.
+pub fn set_accent(accent: Hsla, cx: &mut App) {
+    cx.set_global(Accent(accent));
+    apply_accent(accent, cx);
+}
+
+fn apply_accent(accent: Hsla, cx: &mut App) {
+    let theme = cx.global_mut::<ComponentTheme>();
+
+    theme.colors.primary = accent;
+    theme.colors.ring = accent;
+
+    theme.tokens.primary = theme.colors.primary.into();
+    theme.tokens.ring = theme.colors.ring.into();
 }
 
 pub fn enable_mica_backdrop(window: &mut Window) {
@@ -10,7 +10,7 @@ pub use client::Client;
 pub use format::Format;
 pub use market::Market;
 pub use query::{Query, QueryParams};
-pub use response::{Image, Response, Tooltips};
+pub use response::{Image, Response, Tooltips, Video};
 pub use thumbnail_query::{CropMode, ThumbnailParams, ThumbnailQuery};
 pub use url::UrlBuilder;
 
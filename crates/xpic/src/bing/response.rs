@@ -53,6 +53,29 @@ pub struct Image {
 
     #[serde(rename = "hs")]
     pub hotspots: Option<Vec<Value>>,
+
+    /// Video or animated background shown instead of the still image.
+    #[serde(rename = "vid")]
+    pub video: Option<Video>,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Video {
+    /// Each source is `[mime, url]` or `[mime, url, codecs]`.
+    #[serde(default)]
+    pub sources: Vec<Vec<String>>,
+
+    #[serde(rename = "loop")]
+    pub looping: Option<bool>,
+
+    /// Poster image shown before the video starts.
+    pub image: Option<String>,
+
+    pub caption: Option<String>,
+
+    #[serde(rename = "captionlink")]
+    pub caption_link: Option<String>,
 }
 
 #[skip_serializing_none]
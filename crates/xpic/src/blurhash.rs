@@ -0,0 +1,230 @@
+//! [BlurHash](https://blurha.sh) placeholders.
+//!
+//! A BlurHash describes a blurred version of an image in a few dozen characters, small enough to
+//! store with the metadata so apps can show something before the image loads.
+
+use anyhow::{anyhow, bail};
+use image::{DynamicImage, Rgb, RgbImage};
+use std::f32::consts::PI;
+
+/// Horizontal and vertical components of hashes made for wallpapers, which are wider than tall.
+pub const COMPONENTS: (u32, u32) = (4, 3);
+
+const CHARACTERS: &[u8; 83] =
+    b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz#$%*+,-.:;=?@[]^_{|}~";
+
+/// Encodes `image` with `x` by `y` components, each from 1 to 9. More components keep more
+/// detail in a longer hash.
+pub fn encode(image: &DynamicImage, (x, y): (u32, u32)) -> anyhow::Result<String> {
+    if !(1..=9).contains(&x) || !(1..=9).contains(&y) {
+        bail!("invalid number of components: {x}x{y}, expected 1 to 9 each");
+    }
+    let pixels = image.to_rgb8();
+    let (width, height) = pixels.dimensions();
+    if width == 0 || height == 0 {
+        bail!("image is empty");
+    }
+
+    let linear = pixels
+        .pixels()
+        .map(|pixel| pixel.0.map(srgb_to_linear))
+        .collect::<Vec<_>>();
+
+    let mut factors = Vec::with_capacity((x * y) as usize);
+    for j in 0..y {
+        let rows = (0..height)
+            .map(|py| (PI * j as f32 * py as f32 / height as f32).cos())
+            .collect::<Vec<_>>();
+
+        for i in 0..x {
+            let columns = (0..width)
+                .map(|px| (PI * i as f32 * px as f32 / width as f32).cos())
+                .collect::<Vec<_>>();
+
+            let mut factor = [0.0; 3];
+            for (py, row) in rows.iter().enumerate() {
+                for (px, column) in columns.iter().enumerate() {
+                    let basis = row * column;
+                    let pixel = linear[py * width as usize + px];
+                    for c in 0..3 {
+                        factor[c] += basis * pixel[c];
+                    }
+                }
+            }
+
+            let normalization = if i == 0 && j == 0 { 1.0 } else { 2.0 };
+            factors.push(factor.map(|f| f * normalization / (width * height) as f32));
+        }
+    }
+
+    let (dc, ac) = factors
+        .split_first()
+        .expect("there is at least one component");
+
+    let mut hash = String::with_capacity(4 + 2 * factors.len());
+    encode83((x - 1) + (y - 1) * 9, 1, &mut hash);
+
+    let maximum = if ac.is_empty() {
+        encode83(0, 1, &mut hash);
+        1.0
+    } else {
+        let actual = ac.iter().flatten().fold(0.0f32, |max, f| max.max(f.abs()));
+        let quantized = ((actual * 166.0 - 0.5).floor() as i32).clamp(0, 82) as u32;
+        encode83(quantized, 1, &mut hash);
+        (quantized + 1) as f32 / 166.0
+    };
+
+    let [r, g, b] = dc.map(|c| linear_to_srgb(c) as u32);
+    encode83((r << 16) + (g << 8) + b, 4, &mut hash);
+
+    for factor in ac {
+        let [r, g, b] = factor
+            .map(|f| ((sign_pow(f / maximum, 0.5) * 9.0 + 9.5).floor() as i32).clamp(0, 18) as u32);
+        encode83(r * 19 * 19 + g * 19 + b, 2, &mut hash);
+    }
+
+    Ok(hash)
+}
+
+/// Decodes `hash` into a `width` by `height` image. A `punch` above 1 makes the colors more
+/// contrasted.
+pub fn decode(hash: &str, width: u32, height: u32, punch: f32) -> anyhow::Result<RgbImage> {
+    let invalid = || anyhow!("invalid BlurHash: {hash}");
+
+    if hash.len() < 6 || !hash.is_ascii() {
+        return Err(invalid());
+    }
+
+    let size = decode83(&hash[..1]).ok_or_else(invalid)?;
+    let (x, y) = (size % 9 + 1, size / 9 + 1);
+    if hash.len() != 4 + 2 * (x * y) as usize {
+        return Err(invalid());
+    }
+
+    let maximum = (decode83(&hash[1..2]).ok_or_else(invalid)? + 1) as f32 / 166.0 * punch;
+
+    let mut colors = Vec::with_capacity((x * y) as usize);
+    let dc = decode83(&hash[2..6]).ok_or_else(invalid)?;
+    colors.push([dc >> 16, (dc >> 8) & 255, dc & 255].map(|c| srgb_to_linear(c as u8)));
+
+    for i in 1..(x * y) as usize {
+        let ac = decode83(&hash[4 + i * 2..6 + i * 2]).ok_or_else(invalid)?;
+        colors.push(
+            [ac / (19 * 19), (ac / 19) % 19, ac % 19]
+                .map(|q| sign_pow((q as f32 - 9.0) / 9.0, 2.0) * maximum),
+        );
+    }
+
+    Ok(RgbImage::from_fn(width, height, |px, py| {
+        let mut pixel = [0.0; 3];
+        for j in 0..y {
+            let row = (PI * py as f32 * j as f32 / height as f32).cos();
+            for i in 0..x {
+                let basis = (PI * px as f32 * i as f32 / width as f32).cos() * row;
+                let color = colors[(i + j * x) as usize];
+                for c in 0..3 {
+                    pixel[c] += color[c] * basis;
+                }
+            }
+        }
+
+        Rgb(pixel.map(linear_to_srgb))
+    }))
+}
+
+fn encode83(value: u32, length: u32, hash: &mut String) {
+    for i in 1..=length {
+        let digit = (value / 83u32.pow(length - i)) % 83;
+        hash.push(CHARACTERS[digit as usize] as char);
+    }
+}
+
+fn decode83(s: &str) -> Option<u32> {
+    s.bytes().try_fold(0, |value, c| {
+        let digit = CHARACTERS.iter().position(|&d| d == c)?;
+        Some(value * 83 + digit as u32)
+    })
+}
+
+fn srgb_to_linear(c: u8) -> f32 {
+    let c = c as f32 / 255.0;
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(c: f32) -> u8 {
+    let c = c.clamp(0.0, 1.0);
+    let c = if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    };
+
+    (c * 255.0 + 0.5) as u8
+}
+
+fn sign_pow(value: f32, exponent: f32) -> f32 {
+    value.abs().powf(exponent).copysign(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode() {
+        let red = DynamicImage::ImageRgb8(RgbImage::from_pixel(32, 18, Rgb([255, 0, 0])));
+        let hash = encode(&red, COMPONENTS).unwrap();
+
+        assert_eq!(hash.len(), 4 + 2 * 4 * 3);
+        // The size flag and the DC component of pure red.
+        assert!(hash.starts_with('L'));
+        assert_eq!(&hash[2..6], "TI:j");
+
+        assert!(encode(&red, (0, 3)).is_err());
+        assert!(encode(&red, (10, 3)).is_err());
+        assert!(encode(&DynamicImage::new_rgb8(0, 0), COMPONENTS).is_err());
+    }
+
+    #[test]
+    fn test_decode() {
+        // The example image of the reference implementation.
+        let image = decode("LEHV6nWB2yk8pyo0adR*.7kCMdnj", 32, 18, 1.0).unwrap();
+        assert_eq!(image.dimensions(), (32, 18));
+
+        for hash in [
+            "",
+            "LEHV6",
+            "LEHV6nWB2yk8pyo0adR*.7kCMdn",
+            "LEHV6nWB2yk8pyo0adR*.7kCMdn\"",
+        ] {
+            assert!(decode(hash, 32, 18, 1.0).is_err(), "{hash}");
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        // Dark on the left, light on the right.
+        let image = DynamicImage::ImageRgb8(RgbImage::from_fn(64, 36, |x, _| {
+            let v = (x * 4) as u8;
+            Rgb([v, v / 2, 255 - v])
+        }));
+
+        let hash = encode(&image, COMPONENTS).unwrap();
+        let decoded = decode(&hash, 64, 36, 1.0).unwrap();
+
+        for x in [4, 32, 60] {
+            let original = image.to_rgb8().get_pixel(x, 18).0;
+            let pixel = decoded.get_pixel(x, 18).0;
+            for c in 0..3 {
+                assert!(
+                    original[c].abs_diff(pixel[c]) < 40,
+                    "{x}: {original:?} {pixel:?}"
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,1036 @@
+//! On-disk cache for downloaded images.
+//!
+//! Files are named by the SHA-256 hash of their URL and tracked in an `index.json` file that
+//! records their size and when they were fetched and last used. When the cache grows past its
+//! size limit, the least recently used files are evicted.
+//!
+//! The index also keeps the `ETag` and `Last-Modified` headers of each response, so that
+//! [`Cache::revalidate`] can refresh changing resources with a conditional request.
+//!
+//! The app, the CLI and the daemon share the same directory. Each process keeps its own copy of
+//! the index and merges its changes into `index.json` under a lock file, so that entries written
+//! by the others are not lost. Changes are written on a blocking thread a moment after they are
+//! made, so that a burst of downloads rewrites the index once.
+
+use crate::Image;
+use chrono::{DateTime, Duration, Utc};
+use reqwest::header::{ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use reqwest::{Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::fmt::Display;
+use std::fs::File;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+use tracing::{debug, warn};
+
+const INDEX_FILE: &str = "index.json";
+const LOCK_FILE: &str = "index.lock";
+const TEMP_EXTENSION: &str = "tmp";
+
+/// Files missing from the index are only considered stray once they are this old, since another
+/// process may have written them and not updated the index yet.
+const STRAY_GRACE_PERIOD: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+
+/// How long changes are collected before they are written to the index.
+const FLUSH_DELAY: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// Metadata for a cached file.
+#[skip_serializing_none]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Entry {
+    /// File name within the cache directory.
+    pub file: String,
+    pub size: u64,
+    /// When the data was last downloaded or confirmed unchanged by the server.
+    pub fetched_at: DateTime<Utc>,
+    pub accessed_at: DateTime<Utc>,
+
+    /// `ETag` header of the response.
+    pub etag: Option<String>,
+    /// `Last-Modified` header of the response.
+    pub last_modified: Option<String>,
+}
+
+impl Entry {
+    fn new(url: &str, size: u64) -> Self {
+        let now = Utc::now();
+
+        Self {
+            file: file_name(url),
+            size,
+            fetched_at: now,
+            accessed_at: now,
+            etag: None,
+            last_modified: None,
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Index {
+    entries: HashMap<String, Entry>,
+
+    /// URLs added or updated by this process since the index was last saved.
+    #[serde(skip)]
+    changed: HashSet<String>,
+    /// URLs removed by this process since the index was last saved.
+    #[serde(skip)]
+    removed: HashSet<String>,
+    /// Whether this process removed all entries since the index was last saved.
+    #[serde(skip)]
+    cleared: bool,
+}
+
+impl Index {
+    fn size(&self) -> u64 {
+        self.entries.values().map(|entry| entry.size).sum()
+    }
+
+    /// Whether this process has changes that are not saved yet.
+    fn is_dirty(&self) -> bool {
+        self.cleared || !self.changed.is_empty() || !self.removed.is_empty()
+    }
+
+    fn insert(&mut self, url: &str, entry: Entry) {
+        self.removed.remove(url);
+        self.changed.insert(url.to_owned());
+        self.entries.insert(url.to_owned(), entry);
+    }
+
+    fn remove(&mut self, url: &str) -> Option<Entry> {
+        self.changed.remove(url);
+        self.removed.insert(url.to_owned());
+        self.entries.remove(url)
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+        self.changed.clear();
+        self.removed.clear();
+        self.cleared = true;
+    }
+
+    /// Applies the changes made by this process on top of `saved`, the index on disk, which
+    /// may have been updated by other processes.
+    fn merge(&mut self, saved: Index, dir: &Path) {
+        let mut entries = if self.cleared {
+            HashMap::new()
+        } else {
+            saved.entries
+        };
+
+        for url in self.removed.drain() {
+            entries.remove(&url);
+        }
+
+        for url in self.changed.drain() {
+            let Some(entry) = self.entries.remove(&url) else {
+                continue;
+            };
+
+            match entries.get_mut(&url) {
+                // Another process fetched the file again since.
+                Some(saved) if saved.fetched_at > entry.fetched_at => {
+                    saved.accessed_at = saved.accessed_at.max(entry.accessed_at);
+                }
+                // Another process removed the file since.
+                None if !dir.join(&entry.file).exists() => {}
+                _ => {
+                    entries.insert(url, entry);
+                }
+            }
+        }
+
+        self.entries = entries;
+        self.cleared = false;
+    }
+
+    /// Removes least recently used entries until the total size fits in `max_size`.
+    ///
+    /// The entry for `keep` is never removed.
+    fn evict(&mut self, max_size: u64, keep: Option<&str>) -> Vec<Entry> {
+        let mut size = self.size();
+        if size <= max_size {
+            return Vec::new();
+        }
+
+        let mut candidates = self
+            .entries
+            .iter()
+            .filter(|(url, _)| Some(url.as_str()) != keep)
+            .map(|(url, entry)| (entry.accessed_at, url.clone()))
+            .collect::<Vec<_>>();
+        candidates.sort();
+
+        let mut evicted = Vec::new();
+
+        for (_, url) in candidates {
+            if size <= max_size {
+                break;
+            }
+
+            if let Some(entry) = self.remove(&url) {
+                size -= entry.size;
+                evicted.push(entry);
+            }
+        }
+
+        evicted
+    }
+}
+
+/// Summary of the cache contents.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Stats {
+    pub entries: usize,
+    pub size: u64,
+    pub max_size: Option<u64>,
+    pub oldest: Option<DateTime<Utc>>,
+    pub newest: Option<DateTime<Utc>>,
+}
+
+/// Files and bytes removed by [`Cache::clear`] or [`Cache::prune`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Removed {
+    pub files: usize,
+    pub bytes: u64,
+}
+
+impl Removed {
+    fn add(&mut self, bytes: u64) {
+        self.files += 1;
+        self.bytes += bytes;
+    }
+}
+
+pub struct Cache {
+    dir: PathBuf,
+    max_size: Option<u64>,
+    http: reqwest::Client,
+    index: Arc<Mutex<Index>>,
+    /// Whether a write of the index is already scheduled.
+    flush_scheduled: Arc<AtomicBool>,
+}
+
+impl Cache {
+    /// Default size limit of 512 MiB.
+    pub const DEFAULT_MAX_SIZE: u64 = 512 * 1024 * 1024;
+
+    /// Returns the cache directory shared by the CLI and the app.
+    pub fn default_dir() -> PathBuf {
+        dirs::data_local_dir()
+            .unwrap_or_else(std::env::temp_dir)
+            .join("Xpic")
+            .join("cache")
+    }
+
+    /// Opens a cache in `dir`, creating the directory if needed.
+    ///
+    /// A missing or unreadable index starts an empty one; files that are already in the
+    /// directory are adopted when their URL is next requested.
+    pub fn open(dir: impl Into<PathBuf>) -> io::Result<Self> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir)?;
+
+        let index = read_index(&dir);
+
+        Ok(Self {
+            dir,
+            max_size: Some(Self::DEFAULT_MAX_SIZE),
+            http: reqwest::Client::new(),
+            index: Arc::new(Mutex::new(index)),
+            flush_scheduled: Arc::new(AtomicBool::new(false)),
+        })
+    }
+
+    /// Sets the size limit in bytes, or `None` for no limit.
+    pub fn max_size(mut self, max_size: Option<u64>) -> Self {
+        self.max_size = max_size;
+
+        self
+    }
+
+    /// Sets the HTTP client used by [`fetch`](Self::fetch).
+    pub fn http(mut self, http: reqwest::Client) -> Self {
+        self.http = http;
+
+        self
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Returns the path where the file for `url` is stored, whether or not it is cached.
+    pub fn path(&self, url: impl AsRef<str>) -> PathBuf {
+        self.dir.join(file_name(url.as_ref()))
+    }
+
+    /// Returns the index entry for `url`, if cached.
+    pub fn entry(&self, url: impl AsRef<str>) -> Option<Entry> {
+        self.lock().entries.get(url.as_ref()).cloned()
+    }
+
+    /// Reads the cached data for `url`, marking it as recently used.
+    pub async fn get(&self, url: impl AsRef<str>) -> Option<Vec<u8>> {
+        let url = url.as_ref();
+        let path = self.path(url);
+
+        let data = match tokio::fs::read(&path).await {
+            Ok(data) => data,
+            Err(_) => {
+                // The file was removed behind our back.
+                self.lock().remove(url);
+                return None;
+            }
+        };
+
+        let mut index = self.lock();
+        let entry = match index.entries.get(url) {
+            Some(entry) => Entry {
+                accessed_at: Utc::now(),
+                ..entry.clone()
+            },
+            None => Entry::new(url, data.len() as u64),
+        };
+        index.insert(url, entry);
+
+        Some(data)
+    }
+
+    /// Stores `data` for `url`, evicting old files if the cache is over its size limit.
+    ///
+    /// The file is written to a temporary path first and then renamed, so readers never see a
+    /// partially written file. The index is saved shortly after.
+    pub async fn put(&self, url: impl AsRef<str>, data: &[u8]) -> io::Result<PathBuf> {
+        let url = url.as_ref();
+        self.insert(url, data, Entry::new(url, data.len() as u64))
+            .await
+    }
+
+    async fn insert(&self, url: &str, data: &[u8], entry: Entry) -> io::Result<PathBuf> {
+        let path = self.path(url);
+
+        write_atomic(&path, data).await?;
+
+        // Entries of other processes are known as of the last time the index was saved.
+        let evicted = {
+            let mut index = self.lock();
+            index.insert(url, entry);
+
+            self.max_size
+                .map(|max_size| index.evict(max_size, Some(url)))
+                .unwrap_or_default()
+        };
+
+        for entry in evicted {
+            debug!(file = entry.file, "evicting cached file");
+            let _ = tokio::fs::remove_file(self.dir.join(&entry.file)).await;
+        }
+
+        self.schedule_flush();
+
+        Ok(path)
+    }
+
+    /// Returns the data for `url`, downloading and caching it on a miss.
+    ///
+    /// `file:` URLs, such as those of local wallpapers, are read directly and never cached.
+    pub async fn fetch(&self, url: impl AsRef<str>) -> anyhow::Result<Vec<u8>> {
+        let url = url.as_ref();
+
+        if let Some(path) = file_path(url) {
+            return Ok(tokio::fs::read(path).await?);
+        }
+
+        if let Some(data) = self.get(url).await {
+            debug!(%url, "cache hit");
+            return Ok(data);
+        }
+
+        debug!(%url, "cache miss, fetching");
+        self.download(url).await
+    }
+
+    /// Returns the current data for `url`, checking with the server whether the cached copy
+    /// is still up to date.
+    ///
+    /// If the cached response had an `ETag` or `Last-Modified` header, the request is sent with
+    /// `If-None-Match` or `If-Modified-Since`, and a `304 Not Modified` response reuses the
+    /// cached data. If the server cannot be reached or answers with an error, the cached data is
+    /// returned as is.
+    pub async fn revalidate(&self, url: impl AsRef<str>) -> anyhow::Result<Vec<u8>> {
+        let url = url.as_ref();
+
+        let mut request = self.http.get(url);
+        if let Some(entry) = self.entry(url)
+            && self.path(url).exists()
+        {
+            if let Some(etag) = entry.etag {
+                request = request.header(IF_NONE_MATCH, etag);
+            }
+            if let Some(last_modified) = entry.last_modified {
+                request = request.header(IF_MODIFIED_SINCE, last_modified);
+            }
+        }
+
+        let response = match request.send().await {
+            Ok(response) => response,
+            Err(err) => return self.fall_back(url, err.into()).await,
+        };
+
+        if response.status() == StatusCode::NOT_MODIFIED {
+            if let Some(data) = self.get(url).await {
+                debug!(%url, "not modified");
+
+                {
+                    let mut index = self.lock();
+                    if let Some(entry) = index.entries.get(url) {
+                        let entry = Entry {
+                            fetched_at: Utc::now(),
+                            ..entry.clone()
+                        };
+                        index.insert(url, entry);
+                    }
+                }
+                self.schedule_flush();
+
+                return Ok(data);
+            }
+
+            // The cached file disappeared after the request was sent.
+            return self.download(url).await;
+        }
+
+        debug!(%url, "modified, updating cache");
+        match self.store(url, response).await {
+            Ok(data) => Ok(data),
+            Err(err) => self.fall_back(url, err).await,
+        }
+    }
+
+    /// Returns the cached data for `url` after a failed revalidation, or `err` if there is none.
+    async fn fall_back(&self, url: &str, err: anyhow::Error) -> anyhow::Result<Vec<u8>> {
+        match self.get(url).await {
+            Some(data) => {
+                warn!(%url, "revalidation failed, using cached data: {err}");
+                Ok(data)
+            }
+            None => Err(err),
+        }
+    }
+
+    async fn download(&self, url: &str) -> anyhow::Result<Vec<u8>> {
+        self.store(url, self.http.get(url).send().await?).await
+    }
+
+    /// Caches a successful response along with its validators.
+    async fn store(&self, url: &str, response: Response) -> anyhow::Result<Vec<u8>> {
+        let response = response.error_for_status()?;
+
+        let header = |name| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(ToOwned::to_owned)
+        };
+        let etag = header(ETAG);
+        let last_modified = header(LAST_MODIFIED);
+
+        let data = response.bytes().await?;
+
+        self.insert(
+            url,
+            &data,
+            Entry {
+                etag,
+                last_modified,
+                ..Entry::new(url, data.len() as u64)
+            },
+        )
+        .await?;
+
+        Ok(data.to_vec())
+    }
+
+    /// Like [`fetch`](Self::fetch), but returns the path of the cached file.
+    pub async fn fetch_path(&self, url: impl AsRef<str>) -> anyhow::Result<PathBuf> {
+        let url = url.as_ref();
+
+        if let Some(path) = file_path(url) {
+            return Ok(path);
+        }
+
+        self.fetch(url).await?;
+
+        Ok(self.path(url))
+    }
+
+    pub fn stats(&self) -> Stats {
+        let index = self.lock();
+
+        Stats {
+            entries: index.entries.len(),
+            size: index.size(),
+            max_size: self.max_size,
+            oldest: index.entries.values().map(|entry| entry.fetched_at).min(),
+            newest: index.entries.values().map(|entry| entry.fetched_at).max(),
+        }
+    }
+
+    /// Removes all cached files, except those still being written.
+    pub async fn clear(&self) -> io::Result<Removed> {
+        self.lock().clear();
+        self.flush().await?;
+
+        let mut removed = Removed::default();
+        for (path, size, _) in self.files().await? {
+            if !is_temp(&path) && remove_file(&path).await? {
+                removed.add(size);
+            }
+        }
+
+        Ok(removed)
+    }
+
+    /// Removes files fetched longer than `max_age` ago, evicts files over the size limit, and
+    /// deletes files that are not in the index, such as leftovers from interrupted writes.
+    ///
+    /// Temporary files and files modified in the last hour are kept even if they are not in the
+    /// index, since another process may still be writing them.
+    pub async fn prune(&self, max_age: Option<Duration>) -> io::Result<Removed> {
+        let max_size = self.max_size;
+        let (expired, known) = self
+            .sync(move |index| {
+                let mut expired = Vec::new();
+
+                if let Some(max_age) = max_age {
+                    let cutoff = Utc::now() - max_age;
+                    let urls = index
+                        .entries
+                        .iter()
+                        .filter(|(_, entry)| entry.fetched_at < cutoff)
+                        .map(|(url, _)| url.clone())
+                        .collect::<Vec<_>>();
+
+                    expired.extend(urls.iter().filter_map(|url| index.remove(url)));
+                }
+
+                if let Some(max_size) = max_size {
+                    expired.extend(index.evict(max_size, None));
+                }
+
+                let known = index
+                    .entries
+                    .values()
+                    .map(|entry| entry.file.clone())
+                    .collect::<HashSet<_>>();
+
+                (expired, known)
+            })
+            .await?;
+
+        let mut removed = Removed::default();
+
+        for entry in expired {
+            if remove_file(&self.dir.join(&entry.file)).await? {
+                removed.add(entry.size);
+            }
+        }
+
+        let cutoff = SystemTime::now() - STRAY_GRACE_PERIOD;
+        for (path, size, modified) in self.files().await? {
+            let name = path.file_name().and_then(|name| name.to_str());
+            let stray = name.is_none_or(|name| !known.contains(name));
+
+            if stray && !is_temp(&path) && modified < cutoff && remove_file(&path).await? {
+                removed.add(size);
+            }
+        }
+
+        // Drop entries whose files have disappeared.
+        {
+            let mut index = self.lock();
+            let missing = index
+                .entries
+                .iter()
+                .filter(|(_, entry)| !self.dir.join(&entry.file).exists())
+                .map(|(url, _)| url.clone())
+                .collect::<Vec<_>>();
+
+            for url in missing {
+                index.remove(&url);
+            }
+        }
+        self.flush().await?;
+
+        Ok(removed)
+    }
+
+    /// Writes the changes of this process, such as access times recorded by [`get`](Self::get),
+    /// to the index.
+    pub async fn flush(&self) -> io::Result<()> {
+        self.sync(|_| ()).await
+    }
+
+    /// Runs [`sync`] on a blocking thread, since it waits for the lock file.
+    async fn sync<R: Send + 'static>(
+        &self,
+        f: impl FnOnce(&mut Index) -> R + Send + 'static,
+    ) -> io::Result<R> {
+        let dir = self.dir.clone();
+        let index = self.index.clone();
+
+        tokio::task::spawn_blocking(move || sync(&dir, &index, f))
+            .await
+            .map_err(io::Error::other)?
+    }
+
+    /// Saves the index after [`FLUSH_DELAY`], unless a write is already scheduled.
+    fn schedule_flush(&self) {
+        if self.flush_scheduled.swap(true, Ordering::AcqRel) {
+            return;
+        }
+
+        let dir = self.dir.clone();
+        let index = self.index.clone();
+        let scheduled = self.flush_scheduled.clone();
+
+        tokio::spawn(async move {
+            tokio::time::sleep(FLUSH_DELAY).await;
+            // Changes made from now on need another write.
+            scheduled.store(false, Ordering::Release);
+
+            let result = tokio::task::spawn_blocking(move || sync(&dir, &index, |_| ())).await;
+            if let Ok(Err(err)) = result {
+                debug!("failed to save cache index: {err}");
+            }
+        });
+    }
+
+    /// Lists cached files with their sizes and modification times, excluding the index.
+    async fn files(&self) -> io::Result<Vec<(PathBuf, u64, SystemTime)>> {
+        let mut files = Vec::new();
+        let mut entries = tokio::fs::read_dir(&self.dir).await?;
+
+        while let Some(entry) = entries.next_entry().await? {
+            // Removed by another process in the meantime.
+            let Ok(metadata) = entry.metadata().await else {
+                continue;
+            };
+
+            let name = entry.file_name();
+            if metadata.is_file() && name != INDEX_FILE && name != LOCK_FILE {
+                let modified = metadata.modified().unwrap_or_else(|_| SystemTime::now());
+                files.push((entry.path(), metadata.len(), modified));
+            }
+        }
+
+        Ok(files)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Index> {
+        self.index
+            .lock()
+            .expect("cache index lock should not be poisoned")
+    }
+}
+
+/// Returns the key of a file made from `source`, the URL or path of `image`, such as the image
+/// with effects applied or framed for a monitor. `variant` tells the files made from one source
+/// apart.
+///
+/// The key includes the hash of the image, which changes with the contents of local files, so an
+/// image edited in place is made again.
+pub fn derived_key(source: impl Display, image: &Image, variant: impl Display) -> String {
+    format!("{source}#{}/{variant}", image.hash)
+}
+
+impl Drop for Cache {
+    fn drop(&mut self) {
+        if !self.lock().is_dirty() {
+            return;
+        }
+
+        let dir = self.dir.clone();
+        let index = self.index.clone();
+        let save = move || {
+            let _ = sync(&dir, &index, |_| ());
+        };
+
+        // Runtimes finish their blocking tasks before shutting down.
+        match tokio::runtime::Handle::try_current() {
+            Ok(handle) => drop(handle.spawn_blocking(save)),
+            Err(_) => save(),
+        }
+    }
+}
+
+/// Merges the index on disk into `index`, applies `f`, and saves the result while holding the
+/// lock file, so that concurrent processes do not overwrite each other's entries.
+fn sync<R>(dir: &Path, index: &Mutex<Index>, f: impl FnOnce(&mut Index) -> R) -> io::Result<R> {
+    let lock = File::create(dir.join(LOCK_FILE))?;
+    lock.lock()?;
+
+    let mut index = index
+        .lock()
+        .expect("cache index lock should not be poisoned");
+    index.merge(read_index(dir), dir);
+    let result = f(&mut index);
+    // Changes made by `f` are saved now.
+    index.changed.clear();
+    index.removed.clear();
+
+    let data = serde_json::to_vec(&*index)?;
+    drop(index);
+
+    let path = dir.join(INDEX_FILE);
+    let temp = temp_path(&path);
+    std::fs::write(&temp, data)?;
+    std::fs::rename(temp, path)?;
+
+    Ok(result)
+}
+
+/// Reads the index in `dir`, or returns an empty one if it is missing or unreadable.
+fn read_index(dir: &Path) -> Index {
+    std::fs::read(dir.join(INDEX_FILE))
+        .ok()
+        .and_then(|data| serde_json::from_slice(&data).ok())
+        .unwrap_or_default()
+}
+
+fn file_name(url: &str) -> String {
+    hex::encode(Sha256::digest(url.as_bytes()))
+}
+
+/// Returns the local path of a `file:` URL.
+fn file_path(url: &str) -> Option<PathBuf> {
+    url::Url::parse(url)
+        .ok()
+        .filter(|url| url.scheme() == "file")
+        .and_then(|url| url.to_file_path().ok())
+}
+
+/// Returns a unique temporary path next to `path`.
+fn temp_path(path: &Path) -> PathBuf {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{}.{n}.{TEMP_EXTENSION}", std::process::id()));
+
+    path.with_file_name(name)
+}
+
+fn is_temp(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == TEMP_EXTENSION)
+}
+
+/// Removes a file, returning `false` if it was already removed, e.g. by another process.
+async fn remove_file(path: &Path) -> io::Result<bool> {
+    match tokio::fs::remove_file(path).await {
+        Ok(()) => Ok(true),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(false),
+        Err(err) => Err(err),
+    }
+}
+
+async fn write_atomic(path: &Path, data: &[u8]) -> io::Result<()> {
+    let temp = temp_path(path);
+
+    if let Err(err) = tokio::fs::write(&temp, data).await {
+        let _ = tokio::fs::remove_file(&temp).await;
+        return Err(err);
+    }
+
+    tokio::fs::rename(&temp, path).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use tokio::task::JoinHandle;
+
+    fn open(dir: &Path, max_size: Option<u64>) -> Cache {
+        Cache::open(dir).unwrap().max_size(max_size)
+    }
+
+    #[tokio::test]
+    async fn test_put_get() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = open(dir.path(), None);
+
+        assert_eq!(cache.get("https://example.com/a").await, None);
+
+        let path = cache.put("https://example.com/a", b"hello").await.unwrap();
+        assert_eq!(path, cache.path("https://example.com/a"));
+        assert_eq!(
+            cache.get("https://example.com/a").await.as_deref(),
+            Some(&b"hello"[..])
+        );
+
+        let stats = cache.stats();
+        assert_eq!(stats.entries, 1);
+        assert_eq!(stats.size, 5);
+
+        // No temporary files are left behind.
+        let files = cache.files().await.unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!((&files[0].0, files[0].1), (&path, 5));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_file_url() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = open(&dir.path().join("cache"), None);
+
+        let path = dir.path().join("wallpaper.jpg");
+        std::fs::write(&path, b"local").unwrap();
+        let url = url::Url::from_file_path(&path).unwrap();
+
+        assert_eq!(cache.fetch(&url).await.unwrap(), b"local");
+        assert_eq!(cache.fetch_path(&url).await.unwrap(), path);
+        assert_eq!(cache.stats().entries, 0);
+    }
+
+    #[tokio::test]
+    async fn test_index_persists() {
+        let dir = tempfile::tempdir().unwrap();
+
+        {
+            let cache = open(dir.path(), None);
+            cache.put("https://example.com/a", b"hello").await.unwrap();
+            cache.flush().await.unwrap();
+        }
+
+        let cache = open(dir.path(), None);
+        let entry = cache.entry("https://example.com/a").unwrap();
+        assert_eq!(entry.size, 5);
+    }
+
+    #[tokio::test]
+    async fn test_flush_later() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = open(dir.path(), None);
+
+        cache.put("a", b"aaaa").await.unwrap();
+        cache.put("b", b"bb").await.unwrap();
+        assert!(!dir.path().join(INDEX_FILE).exists());
+
+        // Both entries are saved in one write.
+        tokio::time::sleep(FLUSH_DELAY * 2).await;
+        assert_eq!(read_index(dir.path()).entries.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_adopt_unindexed_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = open(dir.path(), None);
+
+        std::fs::write(cache.path("https://example.com/a"), b"legacy").unwrap();
+
+        assert_eq!(
+            cache.get("https://example.com/a").await.as_deref(),
+            Some(&b"legacy"[..])
+        );
+        assert_eq!(cache.entry("https://example.com/a").unwrap().size, 6);
+    }
+
+    #[tokio::test]
+    async fn test_evict_least_recently_used() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = open(dir.path(), Some(10));
+
+        cache.put("a", b"aaaa").await.unwrap();
+        cache.put("b", b"bbbb").await.unwrap();
+
+        // Touch `a` so that `b` becomes the least recently used.
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        cache.get("a").await.unwrap();
+
+        cache.put("c", b"cccc").await.unwrap();
+
+        assert!(cache.entry("a").is_some());
+        assert!(cache.entry("b").is_none());
+        assert!(cache.entry("c").is_some());
+        assert!(!cache.path("b").exists());
+        assert_eq!(cache.stats().size, 8);
+    }
+
+    #[tokio::test]
+    async fn test_prune() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = open(dir.path(), None);
+
+        cache.put("a", b"aaaa").await.unwrap();
+
+        let stale = SystemTime::now() - STRAY_GRACE_PERIOD * 2;
+        let write = |name: &str, data: &[u8], modified: Option<SystemTime>| {
+            let path = dir.path().join(name);
+            std::fs::write(&path, data).unwrap();
+            if let Some(modified) = modified {
+                File::options()
+                    .write(true)
+                    .open(&path)
+                    .unwrap()
+                    .set_modified(modified)
+                    .unwrap();
+            }
+        };
+        write("orphan", b"orphan", Some(stale));
+        // Possibly written by another process that has not updated the index yet.
+        write("recent", b"recent", None);
+        write("partial.1.0.tmp", b"x", Some(stale));
+
+        let removed = cache.prune(None).await.unwrap();
+        assert_eq!(removed, Removed { files: 1, bytes: 6 });
+        assert!(cache.entry("a").is_some());
+        assert!(dir.path().join("recent").exists());
+        assert!(dir.path().join("partial.1.0.tmp").exists());
+
+        let removed = cache.prune(Some(Duration::zero())).await.unwrap();
+        assert_eq!(removed, Removed { files: 1, bytes: 4 });
+        assert_eq!(cache.stats().entries, 0);
+    }
+
+    #[tokio::test]
+    async fn test_shared_directory() {
+        let dir = tempfile::tempdir().unwrap();
+
+        // Two processes sharing the cache, such as the app and the daemon.
+        let app = open(dir.path(), None);
+        let daemon = open(dir.path(), None);
+
+        app.put("a", b"aaaa").await.unwrap();
+        daemon.put("b", b"bb").await.unwrap();
+        app.put("c", b"c").await.unwrap();
+
+        // Pruning does not remove files the other process added.
+        assert_eq!(daemon.prune(None).await.unwrap(), Removed::default());
+        assert!(app.path("a").exists());
+
+        app.get("b").await.unwrap();
+        daemon.clear().await.unwrap();
+        app.flush().await.unwrap();
+        drop(app);
+        drop(daemon);
+
+        let cache = open(dir.path(), None);
+        assert_eq!(cache.stats().entries, 0);
+
+        let app = open(dir.path(), None);
+        let daemon = open(dir.path(), None);
+        app.put("a", b"aaaa").await.unwrap();
+        daemon.put("b", b"bb").await.unwrap();
+        app.flush().await.unwrap();
+        daemon.flush().await.unwrap();
+        drop(app);
+        drop(daemon);
+
+        let cache = open(dir.path(), None);
+        assert_eq!(cache.stats().entries, 2);
+        assert_eq!(cache.stats().size, 6);
+    }
+
+    type Resource = Arc<Mutex<(&'static str, &'static str)>>;
+
+    /// Serves a resource with an `ETag`, answering `304 Not Modified` to matching conditional
+    /// requests, or `503 Service Unavailable` while the `ETag` is empty. Returns the URL and the
+    /// status codes sent.
+    async fn serve(resource: Resource) -> (String, Arc<Mutex<Vec<u16>>>, JoinHandle<()>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/data.json", listener.local_addr().unwrap());
+        let statuses = Arc::new(Mutex::new(Vec::new()));
+
+        let handle = tokio::spawn({
+            let statuses = statuses.clone();
+
+            async move {
+                loop {
+                    let (mut stream, _) = listener.accept().await.unwrap();
+
+                    let mut buffer = vec![0; 4096];
+                    let n = stream.read(&mut buffer).await.unwrap();
+                    let request = String::from_utf8_lossy(&buffer[..n]).to_lowercase();
+
+                    let (etag, body) = *resource.lock().unwrap();
+                    let response = if etag.is_empty() {
+                        statuses.lock().unwrap().push(503);
+                        "HTTP/1.1 503 Service Unavailable\r\ncontent-length: 0\r\nconnection: close\r\n\r\n".to_owned()
+                    } else if request.contains(&format!("if-none-match: {etag}")) {
+                        statuses.lock().unwrap().push(304);
+                        "HTTP/1.1 304 Not Modified\r\nconnection: close\r\n\r\n".to_owned()
+                    } else {
+                        statuses.lock().unwrap().push(200);
+                        format!(
+                            "HTTP/1.1 200 OK\r\netag: {etag}\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                            body.len()
+                        )
+                    };
+
+                    stream.write_all(response.as_bytes()).await.unwrap();
+                }
+            }
+        });
+
+        (url, statuses, handle)
+    }
+
+    #[tokio::test]
+    async fn test_revalidate() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = open(dir.path(), None);
+
+        let resource: Resource = Arc::new(Mutex::new(("\"v1\"", "[1]")));
+        let (url, statuses, server) = serve(resource.clone()).await;
+
+        assert_eq!(cache.revalidate(&url).await.unwrap(), b"[1]");
+        assert_eq!(cache.entry(&url).unwrap().etag.as_deref(), Some("\"v1\""));
+
+        assert_eq!(cache.revalidate(&url).await.unwrap(), b"[1]");
+        assert_eq!(*statuses.lock().unwrap(), [200, 304]);
+
+        *resource.lock().unwrap() = ("\"v2\"", "[1,2]");
+        assert_eq!(cache.revalidate(&url).await.unwrap(), b"[1,2]");
+        assert_eq!(cache.entry(&url).unwrap().etag.as_deref(), Some("\"v2\""));
+        assert_eq!(*statuses.lock().unwrap(), [200, 304, 200]);
+
+        // Cached data is used when the server fails.
+        *resource.lock().unwrap() = ("", "");
+        assert_eq!(cache.revalidate(&url).await.unwrap(), b"[1,2]");
+        assert_eq!(*statuses.lock().unwrap(), [200, 304, 200, 503]);
+        // Without cached data, the error is returned.
+        let uncached = format!("{url}?v=2");
+        assert!(cache.revalidate(&uncached).await.is_err());
+
+        // Cached data is used when the server is unreachable.
+        server.abort();
+        let _ = server.await;
+        assert_eq!(cache.revalidate(&url).await.unwrap(), b"[1,2]");
+    }
+
+    #[tokio::test]
+    async fn test_clear() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = open(dir.path(), None);
+
+        cache.put("a", b"aaaa").await.unwrap();
+        cache.put("b", b"bb").await.unwrap();
+
+        let removed = cache.clear().await.unwrap();
+        assert_eq!(removed, Removed { files: 2, bytes: 6 });
+        assert_eq!(cache.stats(), Stats::default());
+        assert!(dir.path().join(INDEX_FILE).exists());
+    }
+}
@@ -0,0 +1,283 @@
+use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
+
+/// Parsed form of Bing's copyright line, e.g.
+/// `Winding road of Julier Pass, Switzerland (© Westend61/Getty Images)`.
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Copyright {
+    /// Everything before the credit, including the location.
+    pub description: String,
+    /// The credit, e.g. `© Westend61/Getty Images`. Empty if the line has no credit.
+    pub copyright: String,
+
+    /// Trailing part of the description after the first separator, e.g. `Switzerland`.
+    pub location: Option<String>,
+    /// First name in the credit, e.g. `Westend61`.
+    pub photographer: Option<String>,
+    /// Last name in the credit, e.g. `Getty Images`.
+    pub agency: Option<String>,
+}
+
+/// Credits consisting of a single name are treated as an agency if listed here.
+const KNOWN_AGENCIES: &[&str] = &[
+    "Adobe Stock",
+    "Alamy",
+    "Amazing Aerial Agency",
+    "Cavan Images",
+    "ESA",
+    "Getty Images",
+    "iStock",
+    "Minden Pictures",
+    "NASA",
+    "Nature Picture Library",
+    "Offset",
+    "Shutterstock",
+];
+
+/// Separators between the subject and the location, in Latin and CJK text.
+const LOCATION_SEPARATORS: &[&str] = &[", ", "，", "、"];
+
+impl Copyright {
+    /// Parses a copyright line.
+    ///
+    /// The credit is taken from the last `©` sign, whether or not it is wrapped in parentheses.
+    /// Without a `©` sign, a trailing parenthesized group is used instead. Lines without any
+    /// credit are kept as a bare description. Returns `None` only for blank input.
+    pub fn parse(s: impl AsRef<str>) -> Option<Self> {
+        let s = s.as_ref().trim();
+        if s.is_empty() {
+            return None;
+        }
+
+        let (description, credit) = split_credit(s);
+        let description = description
+            .trim_end_matches(|c: char| c.is_whitespace() || matches!(c, ',' | '-' | '|' | '，'))
+            .to_owned();
+
+        let credit = credit.map(clean_credit).unwrap_or_default();
+        let (photographer, agency) = parse_credit(&credit);
+
+        Some(Copyright {
+            location: parse_location(&description),
+            description,
+            copyright: credit,
+            photographer,
+            agency,
+        })
+    }
+}
+
+/// Splits `s` into the description and the raw credit text.
+fn split_credit(s: &str) -> (&str, Option<&str>) {
+    if let Some(sign) = s.rfind('©') {
+        // Include an opening parenthesis right before the sign in the credit.
+        let start = s[..sign]
+            .trim_end()
+            .strip_suffix('(')
+            .map_or(sign, str::len);
+
+        return (&s[..start], Some(&s[start..]));
+    }
+
+    if s.ends_with(')')
+        && let Some(open) = matching_open_paren(s)
+        && open > 0
+    {
+        return (&s[..open], Some(&s[open..]));
+    }
+
+    (s, None)
+}
+
+/// Returns the byte index of the `(` matching the trailing `)` of `s`.
+fn matching_open_paren(s: &str) -> Option<usize> {
+    let mut depth = 0usize;
+
+    for (i, c) in s.char_indices().rev() {
+        match c {
+            ')' => depth += 1,
+            '(' => {
+                depth = depth.checked_sub(1)?;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    None
+}
+
+/// Strips the enclosing parentheses and drops unbalanced ones, e.g.
+/// `(© Kevin Key/Slworking)/Getty Images)` becomes `© Kevin Key/Slworking/Getty Images`.
+fn clean_credit(credit: &str) -> String {
+    let credit = credit.trim();
+    let credit = credit.strip_prefix('(').unwrap_or(credit);
+    let credit = credit.strip_suffix(')').unwrap_or(credit);
+
+    let mut depth = 0usize;
+    let mut cleaned = String::with_capacity(credit.len());
+
+    for c in credit.chars() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 0 => continue,
+            ')' => depth -= 1,
+            _ => {}
+        }
+
+        cleaned.push(c);
+    }
+
+    cleaned.trim().to_owned()
+}
+
+/// Splits a credit like `© Photographer/Collection/Agency` into photographer and agency.
+fn parse_credit(credit: &str) -> (Option<String>, Option<String>) {
+    let names = credit
+        .trim_start_matches(|c: char| c == '©' || c.is_whitespace())
+        .split('/')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .collect::<Vec<_>>();
+
+    match names.as_slice() {
+        [] => (None, None),
+        [name] if is_known_agency(name) => (None, Some(name.to_string())),
+        [name] => (Some(name.to_string()), None),
+        [photographer, .., agency] => (Some(photographer.to_string()), Some(agency.to_string())),
+    }
+}
+
+fn is_known_agency(name: &str) -> bool {
+    KNOWN_AGENCIES
+        .iter()
+        .any(|agency| agency.eq_ignore_ascii_case(name))
+}
+
+fn parse_location(description: &str) -> Option<String> {
+    let (index, separator) = LOCATION_SEPARATORS
+        .iter()
+        .filter_map(|separator| Some((description.find(separator)?, separator)))
+        .min_by_key(|&(index, _)| index)?;
+
+    let location = description[index + separator.len()..].trim();
+
+    if location.is_empty() {
+        None
+    } else {
+        Some(location.to_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Image;
+    use std::fs;
+    use std::path::Path;
+
+    #[test]
+    fn test_parse() {
+        let test_cases = [
+            (
+                "Winding road of Julier Pass, Switzerland (© Westend61/Getty Images)",
+                Copyright {
+                    description: "Winding road of Julier Pass, Switzerland".to_string(),
+                    copyright: "© Westend61/Getty Images".to_string(),
+                    location: Some("Switzerland".to_string()),
+                    photographer: Some("Westend61".to_string()),
+                    agency: Some("Getty Images".to_string()),
+                },
+            ),
+            (
+                "Moon and Earth captured by the Artemis II crew (© NASA)",
+                Copyright {
+                    description: "Moon and Earth captured by the Artemis II crew".to_string(),
+                    copyright: "© NASA".to_string(),
+                    agency: Some("NASA".to_string()),
+                    ..Default::default()
+                },
+            ),
+            (
+                "Milky Way over Anza-Borrego Desert State Park, California (© Kevin Key/Slworking)/Getty Images)",
+                Copyright {
+                    description: "Milky Way over Anza-Borrego Desert State Park, California"
+                        .to_string(),
+                    copyright: "© Kevin Key/Slworking/Getty Images".to_string(),
+                    location: Some("California".to_string()),
+                    photographer: Some("Kevin Key".to_string()),
+                    agency: Some("Getty Images".to_string()),
+                },
+            ),
+            (
+                "蒙特雷湾的大翅鲸(座头鲸)，加利福尼亚州，美国 (© Kiliii Fish/Cavan Images)",
+                Copyright {
+                    description: "蒙特雷湾的大翅鲸(座头鲸)，加利福尼亚州，美国".to_string(),
+                    copyright: "© Kiliii Fish/Cavan Images".to_string(),
+                    location: Some("加利福尼亚州，美国".to_string()),
+                    photographer: Some("Kiliii Fish".to_string()),
+                    agency: Some("Cavan Images".to_string()),
+                },
+            ),
+            (
+                "Lake Bled, Slovenia © Jan Wlodarczyk/Alamy",
+                Copyright {
+                    description: "Lake Bled, Slovenia".to_string(),
+                    copyright: "© Jan Wlodarczyk/Alamy".to_string(),
+                    location: Some("Slovenia".to_string()),
+                    photographer: Some("Jan Wlodarczyk".to_string()),
+                    agency: Some("Alamy".to_string()),
+                },
+            ),
+            (
+                "Lake Bled (Jan Wlodarczyk/Alamy)",
+                Copyright {
+                    description: "Lake Bled".to_string(),
+                    copyright: "Jan Wlodarczyk/Alamy".to_string(),
+                    photographer: Some("Jan Wlodarczyk".to_string()),
+                    agency: Some("Alamy".to_string()),
+                    ..Default::default()
+                },
+            ),
+            (
+                "Lake Bled",
+                Copyright {
+                    description: "Lake Bled".to_string(),
+                    ..Default::default()
+                },
+            ),
+        ];
+
+        for (s, expected) in test_cases {
+            assert_eq!(Copyright::parse(s), Some(expected), "{s}");
+        }
+
+        assert_eq!(Copyright::parse("  "), None);
+    }
+
+    #[test]
+    fn test_parse_corpus() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../data");
+
+        for entry in fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            let images: Vec<Image> = serde_json::from_slice(&fs::read(&path).unwrap()).unwrap();
+
+            // The data is updated daily, so only what holds for any line is checked here.
+            // Specific credits are covered by `test_parse`.
+            for image in images {
+                if let Some(copyright) = Copyright::parse(&image.copyright) {
+                    assert!(
+                        !copyright.description.is_empty(),
+                        "{}: {}",
+                        path.display(),
+                        image.copyright
+                    );
+                }
+            }
+        }
+    }
+}
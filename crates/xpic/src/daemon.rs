@@ -0,0 +1,674 @@
+//! Keeping the desktop wallpaper up to date.
+//!
+//! A [`Daemon`] sets the newest wallpaper of a source, then sleeps until the next one is due.
+//! Bing publishes a new image for each market once a day, so the next update is scheduled a day
+//! after the newest image started, with a short grace period. If the new image is late, the
+//! daemon checks again every [`retry`](Daemon::retry) interval.
+//!
+//! With a [`Rotation`], the daemon instead shows a different image from the source and an archive
+//! of older images at a fixed interval.
+//!
+//! Time is read through a [`Clock`], so the schedule can be tested without waiting.
+
+use crate::cache::Cache;
+use crate::effects::Pipeline;
+use crate::rotation::Rotation;
+use crate::source::WallpaperSource;
+use crate::wallpaper::{self, Fit, Monitor, WallpaperBackend};
+use crate::{Image, merge};
+use anyhow::anyhow;
+use chrono::{DateTime, Duration, Utc};
+use futures::future::BoxFuture;
+use std::fmt::Write;
+use std::io;
+use std::path::{self, Path};
+use std::sync::Arc;
+use tracing::{debug, error, info};
+
+/// How long after the rollover to wait for the new image to be published.
+pub const GRACE: Duration = Duration::minutes(10);
+
+/// Default interval between checks while the new image is late, or after an error.
+pub const DEFAULT_RETRY: Duration = Duration::minutes(30);
+
+/// Source of the current time.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+
+    /// Completes at `deadline`.
+    fn sleep_until(&self, deadline: DateTime<Utc>) -> BoxFuture<'_, ()>;
+}
+
+/// The system clock.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl SystemClock {
+    /// Longest single sleep. Monotonic timers stop while the system is suspended, so the wall
+    /// clock is checked again at least this often.
+    const MAX_SLEEP: Duration = Duration::minutes(1);
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+
+    fn sleep_until(&self, deadline: DateTime<Utc>) -> BoxFuture<'_, ()> {
+        Box::pin(async move {
+            loop {
+                let remaining = deadline - Utc::now();
+                if remaining <= Duration::zero() {
+                    break;
+                }
+
+                let sleep = remaining.min(Self::MAX_SLEEP).to_std().unwrap_or_default();
+                tokio::time::sleep(sleep).await;
+            }
+        })
+    }
+}
+
+/// Returns when to look for the image following the one that started at `latest`.
+pub fn next_update(latest: DateTime<Utc>, now: DateTime<Utc>, retry: Duration) -> DateTime<Utc> {
+    let rollover = latest + Duration::days(1) + GRACE;
+
+    if rollover > now {
+        rollover
+    } else {
+        now + retry
+    }
+}
+
+/// Sets the newest wallpaper of a source whenever it changes.
+pub struct Daemon {
+    source: Arc<dyn WallpaperSource>,
+    backend: Arc<dyn WallpaperBackend>,
+    cache: Arc<Cache>,
+    clock: Arc<dyn Clock>,
+    resolution: Option<(u32, u32)>,
+    lock_screen: bool,
+    retry: Duration,
+    rotation: Option<(Rotation, Duration)>,
+    archive: Vec<Image>,
+    fit: Option<(Fit, Vec<Monitor>)>,
+    effects: Pipeline,
+    /// ID of the wallpaper set last.
+    current: Option<String>,
+}
+
+impl Daemon {
+    /// Sets wallpapers listed by `source` with `backend`, keeping the images in `cache`.
+    pub fn new(
+        source: Arc<dyn WallpaperSource>,
+        backend: Arc<dyn WallpaperBackend>,
+        cache: Arc<Cache>,
+    ) -> Self {
+        Self {
+            source,
+            backend,
+            cache,
+            clock: Arc::new(SystemClock),
+            resolution: None,
+            lock_screen: false,
+            retry: DEFAULT_RETRY,
+            rotation: None,
+            archive: Vec::new(),
+            fit: None,
+            effects: Pipeline::default(),
+            current: None,
+        }
+    }
+
+    pub fn clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+
+        self
+    }
+
+    /// Resizes Bing images to `width` by `height` instead of using the full image.
+    pub fn resolution(mut self, resolution: Option<(u32, u32)>) -> Self {
+        self.resolution = resolution;
+
+        self
+    }
+
+    /// Also sets the lock screen image.
+    pub fn lock_screen(mut self, lock_screen: bool) -> Self {
+        self.lock_screen = lock_screen;
+
+        self
+    }
+
+    /// Renders a wallpaper for each of `monitors`, or each monitor the backend lists if empty,
+    /// instead of showing the same image on all of them. The resolution is then ignored.
+    pub fn fit(mut self, fit: Fit, monitors: Vec<Monitor>) -> Self {
+        self.fit = Some((fit, monitors));
+
+        self
+    }
+
+    /// Applies `effects` to each wallpaper before setting it.
+    pub fn effects(mut self, effects: Pipeline) -> Self {
+        self.effects = effects;
+
+        self
+    }
+
+    pub fn retry(mut self, retry: Duration) -> Self {
+        self.retry = retry;
+
+        self
+    }
+
+    /// Shows the image chosen by `rotation` every `interval`, instead of the newest one.
+    pub fn rotate(mut self, rotation: Rotation, interval: Duration) -> Self {
+        self.rotation = Some((rotation, interval));
+
+        self
+    }
+
+    /// Rotates through `images` as well as those listed by the source.
+    pub fn archive(mut self, images: Vec<Image>) -> Self {
+        self.archive = images;
+
+        self
+    }
+
+    /// Updates the wallpaper forever, sleeping between updates.
+    pub async fn run(mut self) {
+        info!(
+            source = self.source.name(),
+            backend = self.backend.name(),
+            "daemon started"
+        );
+
+        loop {
+            let next = match self.update().await {
+                Ok(next) => next,
+                Err(err) => {
+                    error!("failed to update wallpaper: {err}");
+                    self.clock.now() + self.retry
+                }
+            };
+
+            info!(%next, "next update scheduled");
+            self.clock.sleep_until(next).await;
+        }
+    }
+
+    /// Sets the newest or the next rotated wallpaper if it is not set already, and returns when
+    /// to update next.
+    ///
+    /// The cache is pruned after every change. Pruning merges the index shared with other
+    /// processes, such as the app, under a lock and spares files they may still be writing.
+    pub async fn update(&mut self) -> anyhow::Result<DateTime<Utc>> {
+        let images = match self.source.list().await {
+            Ok(images) => images,
+            // The archive is enough to keep rotating while the source is unreachable.
+            Err(err) if self.rotation.is_some() && !self.archive.is_empty() => {
+                error!(
+                    "failed to list {}, rotating the archive: {err}",
+                    self.source.name()
+                );
+                Vec::new()
+            }
+            Err(err) => return Err(err),
+        };
+        let now = self.clock.now();
+
+        let latest = images.iter().max_by_key(|image| image.full_start_date);
+        let image = match &mut self.rotation {
+            Some((rotation, _)) => rotation
+                .next(&merge(&self.archive, &images), now.date_naive())
+                .cloned(),
+            None => latest.cloned(),
+        };
+        let image = image.ok_or_else(|| anyhow!("{} has no wallpapers", self.source.name()))?;
+
+        if self.current.as_deref() == Some(image.id.as_str()) {
+            debug!(id = %image.id, "wallpaper is up to date");
+        } else {
+            self.apply(&image).await?;
+
+            info!(id = %image.id, title = %image.title, "wallpaper set");
+            self.current = Some(image.id.clone());
+
+            let removed = self.cache.prune(None).await?;
+            debug!(files = removed.files, bytes = removed.bytes, "cache pruned");
+        }
+
+        let now = self.clock.now();
+        Ok(match (&self.rotation, latest) {
+            // Intervals past the end of time wait forever.
+            (Some((_, interval)), _) => now
+                .checked_add_signed(*interval)
+                .unwrap_or(DateTime::<Utc>::MAX_UTC),
+            (None, Some(latest)) => next_update(latest.full_start_date, now, self.retry),
+            (None, None) => now + self.retry,
+        })
+    }
+
+    fn url(&self, image: &Image) -> anyhow::Result<String> {
+        match self.resolution {
+            Some((width, height)) => image.resized_url(width, height),
+            None => image.download_url(),
+        }
+    }
+
+    async fn apply(&self, image: &Image) -> anyhow::Result<()> {
+        match &self.fit {
+            Some((fit, monitors)) => {
+                // Monitors come and go, so they are listed every time.
+                let monitors = if monitors.is_empty() {
+                    self.backend.monitors()?
+                } else {
+                    monitors.clone()
+                };
+
+                let mut wallpapers = wallpaper::render(&self.cache, image, &monitors, *fit).await?;
+                for (_, path) in &mut wallpapers {
+                    *path = self.effects.process(&self.cache, image, path).await?;
+                }
+                self.backend.set_monitor_wallpapers(&wallpapers)?;
+            }
+            None => {
+                let path = self.cache.fetch_path(self.url(image)?).await?;
+                let path = self.effects.process(&self.cache, image, &path).await?;
+                self.backend.set_wallpaper(&path)?;
+            }
+        }
+
+        if self.lock_screen {
+            let path = self.cache.fetch_path(self.url(image)?).await?;
+            let path = self.effects.process(&self.cache, image, &path).await?;
+            self.backend.set_lock_screen(&path)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns a systemd user unit that runs `program` with `args` in the graphical session.
+///
+/// The desktop backends need the session's environment, such as `XDG_CURRENT_DESKTOP`, which
+/// most desktops import into the systemd user manager before `graphical-session.target`.
+pub fn systemd_unit(program: &Path, args: &[String]) -> String {
+    let mut exec_start = quote_systemd(&program.to_string_lossy());
+    for arg in args {
+        write!(exec_start, " {}", quote_systemd(arg)).unwrap();
+    }
+
+    format!(
+        "[Unit]
+Description=Xpic wallpaper daemon
+PartOf=graphical-session.target
+After=graphical-session.target
+
+[Service]
+Type=simple
+ExecStart={exec_start}
+Restart=on-failure
+RestartSec=30
+
+[Install]
+WantedBy=graphical-session.target
+"
+    )
+}
+
+/// Makes the paths following any of `flags` in `args` absolute, as `--flag PATH` or
+/// `--flag=PATH`, since a unit does not run in the current directory.
+pub fn absolute_paths(args: &[String], flags: &[&str]) -> io::Result<Vec<String>> {
+    let absolute = |path: &str| -> io::Result<String> {
+        Ok(path::absolute(path)?.to_string_lossy().into_owned())
+    };
+
+    let mut result = Vec::with_capacity(args.len());
+    let mut is_path = false;
+
+    for arg in args {
+        let arg = match arg.split_once('=') {
+            _ if is_path => absolute(arg)?,
+            Some((flag, path)) if flags.contains(&flag) => format!("{flag}={}", absolute(path)?),
+            _ => arg.clone(),
+        };
+
+        is_path = flags.contains(&arg.as_str());
+        result.push(arg);
+    }
+
+    Ok(result)
+}
+
+/// Quotes `arg` for a systemd `ExecStart=` line.
+fn quote_systemd(arg: &str) -> String {
+    // `%` starts a specifier even inside quotes.
+    let arg = arg.replace('%', "%%");
+
+    if !arg.is_empty()
+        && !arg.contains(|c: char| c.is_whitespace() || matches!(c, '"' | '\'' | '\\' | ';' | '$'))
+    {
+        return arg;
+    }
+
+    let mut quoted = String::with_capacity(arg.len() + 2);
+    quoted.push('"');
+    for c in arg.chars() {
+        match c {
+            '"' | '\\' => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            // Keep `$` from expanding environment variables.
+            '$' => quoted.push_str("$$"),
+            _ => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::ImageBuilder;
+    use crate::rotation::Policy;
+    use anyhow::bail;
+    use std::path::PathBuf;
+    use std::sync::Mutex;
+    use url::Url;
+
+    /// A clock that jumps to each deadline and stops after `limit` sleeps.
+    struct FakeClock {
+        now: Mutex<DateTime<Utc>>,
+        sleeps: Mutex<Vec<DateTime<Utc>>>,
+        limit: usize,
+    }
+
+    impl FakeClock {
+        fn new(now: DateTime<Utc>, limit: usize) -> Self {
+            Self {
+                now: Mutex::new(now),
+                sleeps: Mutex::new(Vec::new()),
+                limit,
+            }
+        }
+    }
+
+    impl Clock for FakeClock {
+        fn now(&self) -> DateTime<Utc> {
+            *self.now.lock().unwrap()
+        }
+
+        fn sleep_until(&self, deadline: DateTime<Utc>) -> BoxFuture<'_, ()> {
+            let mut sleeps = self.sleeps.lock().unwrap();
+            if sleeps.len() == self.limit {
+                return Box::pin(futures::future::pending());
+            }
+
+            sleeps.push(deadline);
+            *self.now.lock().unwrap() = deadline;
+
+            Box::pin(async {})
+        }
+    }
+
+    /// Lists the images published by the time of `clock`.
+    struct FakeSource {
+        images: Vec<Image>,
+        clock: Arc<FakeClock>,
+    }
+
+    impl WallpaperSource for FakeSource {
+        fn name(&self) -> &'static str {
+            "fake"
+        }
+
+        fn list(&self) -> BoxFuture<'_, anyhow::Result<Vec<Image>>> {
+            let now = self.clock.now();
+            let images = self
+                .images
+                .iter()
+                .filter(|image| image.full_start_date <= now)
+                .cloned()
+                .collect();
+
+            Box::pin(async move { Ok(images) })
+        }
+
+        fn fetch<'a>(&'a self, image: &'a Image) -> BoxFuture<'a, anyhow::Result<Vec<u8>>> {
+            Box::pin(async move { bail!("{} is not fetched in tests", image.id) })
+        }
+    }
+
+    /// Fails to list, like a source without a network connection.
+    struct OfflineSource;
+
+    impl WallpaperSource for OfflineSource {
+        fn name(&self) -> &'static str {
+            "offline"
+        }
+
+        fn list(&self) -> BoxFuture<'_, anyhow::Result<Vec<Image>>> {
+            Box::pin(async { bail!("no network connection") })
+        }
+
+        fn fetch<'a>(&'a self, image: &'a Image) -> BoxFuture<'a, anyhow::Result<Vec<u8>>> {
+            Box::pin(async move { bail!("{} is not fetched in tests", image.id) })
+        }
+    }
+
+    #[derive(Default)]
+    struct FakeBackend {
+        wallpapers: Mutex<Vec<PathBuf>>,
+        lock_screens: Mutex<Vec<PathBuf>>,
+    }
+
+    impl WallpaperBackend for FakeBackend {
+        fn name(&self) -> &'static str {
+            "fake"
+        }
+
+        fn set_wallpaper(&self, path: &Path) -> anyhow::Result<()> {
+            self.wallpapers.lock().unwrap().push(path.to_path_buf());
+            Ok(())
+        }
+
+        fn supports_lock_screen(&self) -> bool {
+            true
+        }
+
+        fn set_lock_screen(&self, path: &Path) -> anyhow::Result<()> {
+            self.lock_screens.lock().unwrap().push(path.to_path_buf());
+            Ok(())
+        }
+    }
+
+    fn date(s: &str) -> DateTime<Utc> {
+        s.parse().unwrap()
+    }
+
+    fn image(dir: &Path, id: &str, start: DateTime<Utc>) -> Image {
+        let path = dir.join(id);
+        std::fs::write(&path, id).unwrap();
+
+        ImageBuilder::new(id)
+            .url(Url::from_file_path(&path).unwrap())
+            .start(start)
+            .build()
+    }
+
+    #[test]
+    fn test_next_update() {
+        let latest = date("2026-03-01T08:00:00Z");
+        let retry = Duration::minutes(30);
+
+        assert_eq!(
+            next_update(latest, date("2026-03-01T12:00:00Z"), retry),
+            date("2026-03-02T08:10:00Z")
+        );
+        // The next image is late.
+        assert_eq!(
+            next_update(latest, date("2026-03-02T09:00:00Z"), retry),
+            date("2026-03-02T09:30:00Z")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_run() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = Arc::new(Cache::open(dir.path().join("cache")).unwrap());
+        let clock = Arc::new(FakeClock::new(date("2026-03-01T12:00:00Z"), 3));
+        let source = Arc::new(FakeSource {
+            images: vec![
+                image(dir.path(), "a.jpg", date("2026-03-01T08:00:00Z")),
+                // Published late.
+                image(dir.path(), "b.jpg", date("2026-03-02T08:20:00Z")),
+            ],
+            clock: clock.clone(),
+        });
+        let backend = Arc::new(FakeBackend::default());
+
+        let daemon = Daemon::new(source, backend.clone(), cache)
+            .clock(clock.clone())
+            .lock_screen(true);
+        let _ = tokio::time::timeout(std::time::Duration::from_millis(200), daemon.run()).await;
+
+        assert_eq!(
+            *clock.sleeps.lock().unwrap(),
+            [
+                date("2026-03-02T08:10:00Z"),
+                date("2026-03-02T08:40:00Z"),
+                date("2026-03-03T08:30:00Z"),
+            ]
+        );
+        assert_eq!(
+            *backend.wallpapers.lock().unwrap(),
+            [dir.path().join("a.jpg"), dir.path().join("b.jpg")]
+        );
+        assert_eq!(
+            *backend.lock_screens.lock().unwrap(),
+            *backend.wallpapers.lock().unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_run_rotation() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = Arc::new(Cache::open(dir.path().join("cache")).unwrap());
+        let clock = Arc::new(FakeClock::new(date("2026-03-01T12:00:00Z"), 3));
+        let source = Arc::new(FakeSource {
+            images: vec![image(dir.path(), "b.jpg", date("2026-03-01T08:00:00Z"))],
+            clock: clock.clone(),
+        });
+        let archive = vec![image(dir.path(), "a.jpg", date("2025-03-01T08:00:00Z"))];
+        let backend = Arc::new(FakeBackend::default());
+
+        let daemon = Daemon::new(source, backend.clone(), cache)
+            .clock(clock.clone())
+            .rotate(Rotation::new(Policy::Sequential), Duration::hours(6))
+            .archive(archive);
+        let _ = tokio::time::timeout(std::time::Duration::from_millis(200), daemon.run()).await;
+
+        assert_eq!(
+            *clock.sleeps.lock().unwrap(),
+            [
+                date("2026-03-01T18:00:00Z"),
+                date("2026-03-02T00:00:00Z"),
+                date("2026-03-02T06:00:00Z"),
+            ]
+        );
+        assert_eq!(
+            *backend.wallpapers.lock().unwrap(),
+            ["b.jpg", "a.jpg", "b.jpg", "a.jpg"].map(|id| dir.path().join(id))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_run_rotation_offline() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = Arc::new(Cache::open(dir.path().join("cache")).unwrap());
+        let clock = Arc::new(FakeClock::new(date("2026-03-01T12:00:00Z"), 2));
+        let archive = vec![
+            image(dir.path(), "a.jpg", date("2025-03-01T08:00:00Z")),
+            image(dir.path(), "b.jpg", date("2025-03-02T08:00:00Z")),
+        ];
+        let backend = Arc::new(FakeBackend::default());
+
+        let daemon = Daemon::new(Arc::new(OfflineSource), backend.clone(), cache)
+            .clock(clock.clone())
+            .rotate(Rotation::new(Policy::Sequential), Duration::hours(6))
+            .archive(archive);
+        let _ = tokio::time::timeout(std::time::Duration::from_millis(200), daemon.run()).await;
+
+        assert_eq!(
+            *clock.sleeps.lock().unwrap(),
+            [date("2026-03-01T18:00:00Z"), date("2026-03-02T00:00:00Z")]
+        );
+        assert_eq!(
+            *backend.wallpapers.lock().unwrap(),
+            ["b.jpg", "a.jpg", "b.jpg"].map(|id| dir.path().join(id))
+        );
+    }
+
+    #[test]
+    fn test_systemd_unit() {
+        let unit = systemd_unit(
+            Path::new("/home/me/.cargo/bin/xpic"),
+            &[
+                "daemon".to_string(),
+                "--cache-dir".to_string(),
+                "/home/me/My Cache".to_string(),
+                "100%".to_string(),
+            ],
+        );
+
+        assert!(unit.contains(
+            "\nExecStart=/home/me/.cargo/bin/xpic daemon --cache-dir \"/home/me/My Cache\" 100%%\n"
+        ));
+        assert!(unit.contains("WantedBy=graphical-session.target"));
+    }
+
+    #[test]
+    fn test_absolute_paths() {
+        let cwd = std::env::current_dir().unwrap();
+        let args = absolute_paths(
+            &[
+                "daemon".to_string(),
+                "--archive".to_string(),
+                "data".to_string(),
+                "--favorites=favorites.txt".to_string(),
+                "--cache-dir".to_string(),
+                "/var/cache/xpic".to_string(),
+                "--rotate".to_string(),
+                "shuffle".to_string(),
+            ],
+            &["--archive", "--favorites", "--cache-dir"],
+        )
+        .unwrap();
+
+        assert_eq!(
+            args,
+            [
+                "daemon".to_string(),
+                "--archive".to_string(),
+                cwd.join("data").to_string_lossy().into_owned(),
+                format!("--favorites={}", cwd.join("favorites.txt").display()),
+                "--cache-dir".to_string(),
+                "/var/cache/xpic".to_string(),
+                "--rotate".to_string(),
+                "shuffle".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_quote_systemd() {
+        assert_eq!(quote_systemd("plain"), "plain");
+        assert_eq!(quote_systemd(""), "\"\"");
+        assert_eq!(quote_systemd(r#"a "b" \c"#), r#""a \"b\" \\c""#);
+        assert_eq!(quote_systemd("$HOME"), "\"$$HOME\"");
+    }
+}
@@ -0,0 +1,267 @@
+//! Finding the same photo published more than once.
+//!
+//! Bing sometimes reruns a photo under a new ID and hash, so reruns are recognized by a
+//! [`PerceptualHash`] of their thumbnails instead, which barely changes when an image is
+//! re-encoded or resized.
+
+use crate::Image;
+use image::DynamicImage;
+use image::imageops::FilterType;
+use serde_with::{DeserializeFromStr, SerializeDisplay};
+use std::borrow::Borrow;
+use std::cmp::Reverse;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+/// Maximum Hamming distance between perceptual hashes of the same photo.
+pub const DEFAULT_THRESHOLD: u32 = 6;
+
+/// Difference hash of an image, written as 16 hexadecimal digits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, SerializeDisplay, DeserializeFromStr)]
+pub struct PerceptualHash(u64);
+
+impl PerceptualHash {
+    pub fn of_image(image: &DynamicImage) -> Self {
+        // Compare each pixel with its right neighbour in a 9×8 grayscale thumbnail.
+        let thumbnail = image.resize_exact(9, 8, FilterType::Triangle).into_luma8();
+
+        let mut hash = 0u64;
+        for y in 0..8 {
+            for x in 0..8 {
+                let bit = thumbnail.get_pixel(x, y)[0] < thumbnail.get_pixel(x + 1, y)[0];
+                hash = (hash << 1) | bit as u64;
+            }
+        }
+
+        PerceptualHash(hash)
+    }
+
+    /// Decodes an image and hashes it.
+    pub fn from_bytes(data: &[u8]) -> anyhow::Result<Self> {
+        Ok(Self::of_image(&image::load_from_memory(data)?))
+    }
+
+    /// Returns the number of differing bits.
+    pub fn distance(self, other: Self) -> u32 {
+        (self.0 ^ other.0).count_ones()
+    }
+}
+
+impl Display for PerceptualHash {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+impl FromStr for PerceptualHash {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.len() != 16 || !s.bytes().all(|c| c.is_ascii_hexdigit()) {
+            return Err(format!(
+                "invalid perceptual hash: {s}, expected 16 hex digits"
+            ));
+        }
+
+        u64::from_str_radix(s, 16)
+            .map(PerceptualHash)
+            .map_err(|_| format!("invalid perceptual hash: {s}"))
+    }
+}
+
+/// Groups images whose perceptual hashes are all at most `threshold` bits apart from each other.
+///
+/// Each group is ordered from the oldest image to the newest, and groups from the most recent
+/// rerun. Images without a hash or without near-duplicates are left out.
+pub fn group<T: Borrow<Image>>(images: &[T], threshold: u32) -> Vec<Vec<&T>> {
+    group_indices(images, threshold)
+        .into_iter()
+        .map(|group| group.into_iter().map(|i| &images[i]).collect())
+        .collect()
+}
+
+/// Points each image published again after the oldest one of its [`group`] to that one with
+/// [`Image::rerun_of`], and clears the mark of all other images. Returns the number of reruns.
+pub fn mark_reruns(images: &mut [Image], threshold: u32) -> usize {
+    for image in images.iter_mut() {
+        image.rerun_of = None;
+    }
+
+    let mut reruns = 0;
+    for group in group_indices(images, threshold) {
+        let original = &images[group[0]];
+        let (id, start_date) = (original.id.clone(), original.start_date);
+
+        for &i in &group[1..] {
+            // The same photo on the same day is a copy, not a rerun.
+            if images[i].start_date > start_date {
+                images[i].rerun_of = Some(id.clone());
+                reruns += 1;
+            }
+        }
+    }
+
+    reruns
+}
+
+fn group_indices<T: Borrow<Image>>(images: &[T], threshold: u32) -> Vec<Vec<usize>> {
+    let date = |i: &usize| images[*i].borrow().full_start_date;
+
+    let mut hashes = images
+        .iter()
+        .enumerate()
+        .filter_map(|(i, image)| Some((i, image.borrow().perceptual_hash?)))
+        .collect::<Vec<_>>();
+    hashes.sort_by_key(|(i, _)| date(i));
+
+    // Complete linkage: an image joins the oldest group it is close to every image of, so a
+    // chain of slightly different photos doesn't merge photos that are far apart.
+    let mut groups: Vec<Vec<(usize, PerceptualHash)>> = Vec::new();
+    for (i, hash) in hashes {
+        let close = |group: &&mut Vec<(usize, PerceptualHash)>| {
+            group
+                .iter()
+                .all(|&(_, other)| hash.distance(other) <= threshold)
+        };
+
+        match groups.iter_mut().find(close) {
+            Some(group) => group.push((i, hash)),
+            None => groups.push(vec![(i, hash)]),
+        }
+    }
+
+    let mut groups = groups
+        .into_iter()
+        .filter(|group| group.len() > 1)
+        .map(|group| group.into_iter().map(|(i, _)| i).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    groups.sort_by_key(|group| Reverse(group.last().map(date)));
+
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::ImageBuilder;
+    use chrono::{TimeZone, Utc};
+    use image::{Rgb, RgbImage};
+
+    fn image(id: &str, day: u32, hash: Option<u64>) -> Image {
+        ImageBuilder::new(id)
+            .start(Utc.with_ymd_and_hms(2026, 3, day, 8, 0, 0).unwrap())
+            .perceptual_hash(hash.map(PerceptualHash))
+            .build()
+    }
+
+    #[test]
+    fn test_perceptual_hash() {
+        let gradient = DynamicImage::ImageRgb8(RgbImage::from_fn(320, 180, |x, y| {
+            Rgb([(x * 255 / 320) as u8, (y * 255 / 180) as u8, 128])
+        }));
+        let hash = PerceptualHash::of_image(&gradient);
+
+        // Resized and re-encoded.
+        let mut data = Vec::new();
+        gradient
+            .resize_exact(192, 108, FilterType::Triangle)
+            .write_to(
+                &mut std::io::Cursor::new(&mut data),
+                image::ImageFormat::Jpeg,
+            )
+            .unwrap();
+        let resized = PerceptualHash::from_bytes(&data).unwrap();
+        assert!(hash.distance(resized) <= DEFAULT_THRESHOLD);
+
+        let flipped = PerceptualHash::of_image(&gradient.fliph());
+        assert!(hash.distance(flipped) > DEFAULT_THRESHOLD);
+
+        assert_eq!(hash.to_string().parse::<PerceptualHash>().unwrap(), hash);
+        assert_eq!(PerceptualHash(0xff).to_string(), "00000000000000ff");
+        for s in [
+            "",
+            "ff",
+            "00000000000000fg",
+            "00000000000000ff0",
+            "+00000000000000f",
+        ] {
+            assert!(s.parse::<PerceptualHash>().is_err(), "{s}");
+        }
+    }
+
+    #[test]
+    fn test_group() {
+        let images = [
+            image("a", 1, Some(0b0000)),
+            image("b", 2, Some(u64::MAX)),
+            image("c", 5, Some(0b0111)),
+            image("d", 3, None),
+            // Close to c, but not to a, so it joins neither.
+            image("e", 4, Some(0b0111_1111)),
+            image("f", 6, Some(u64::MAX - 1)),
+        ];
+
+        let groups = group(&images, 4)
+            .into_iter()
+            .map(|group| {
+                group
+                    .iter()
+                    .map(|image| image.id.as_str())
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(groups, [vec!["b", "f"], vec!["a", "c"]]);
+
+        assert!(group(&images, 0).is_empty());
+    }
+
+    #[test]
+    fn test_group_chain() {
+        // a is close to b and b to c, but a and c are different photos.
+        let images = [
+            image("a", 1, Some(0)),
+            image("b", 2, Some(0b0111)),
+            image("c", 3, Some(0b0011_1111)),
+        ];
+        assert_eq!(
+            images[0]
+                .perceptual_hash
+                .unwrap()
+                .distance(images[2].perceptual_hash.unwrap()),
+            6
+        );
+
+        let groups = group(&images, 4);
+        assert_eq!(groups.len(), 1);
+        assert_eq!(
+            groups[0]
+                .iter()
+                .map(|image| image.id.as_str())
+                .collect::<Vec<_>>(),
+            ["a", "b"]
+        );
+
+        let mut images = images.to_vec();
+        assert_eq!(mark_reruns(&mut images, 4), 1);
+        assert_eq!(images[2].rerun_of, None);
+    }
+
+    #[test]
+    fn test_mark_reruns() {
+        let mut images = vec![
+            image("a", 1, Some(0)),
+            image("b", 2, Some(1)),
+            image("c", 3, Some(u64::MAX)),
+        ];
+        images[2].rerun_of = Some("stale".to_string());
+
+        assert_eq!(mark_reruns(&mut images, DEFAULT_THRESHOLD), 1);
+        assert_eq!(images[0].rerun_of, None);
+        assert_eq!(images[1].rerun_of.as_deref(), Some("a"));
+        assert_eq!(images[2].rerun_of, None);
+
+        // Published on the same day.
+        images[1].start_date = images[0].start_date;
+        assert_eq!(mark_reruns(&mut images, DEFAULT_THRESHOLD), 0);
+    }
+}
@@ -0,0 +1,516 @@
+//! Post-processing applied to wallpapers before they are set or saved.
+//!
+//! A [`Pipeline`] runs a list of [`Effect`]s in order. Effects are written as `name[=value]`,
+//! e.g. `dim=0.3`, both on the command line and in the app's config:
+//!
+//! | Effect        | Value                                  | Default |
+//! |---------------|----------------------------------------|---------|
+//! | `dim`         | how much darker, from 0 to 1           | 0.3     |
+//! | `blur`        | blur radius in pixels at 1080p         | 8       |
+//! | `grayscale`   |                                        |         |
+//! | `vignette`    | how much darker the corners, 0 to 1    | 0.5     |
+//! | `caption`     |                                        |         |
+//!
+//! The caption shows the title and copyright in the band Bing keeps clear for text, or at the
+//! bottom.
+
+mod font;
+
+use crate::Image;
+use crate::cache::{self, Cache};
+use image::codecs::jpeg::JpegEncoder;
+use image::{DynamicImage, Rgb, RgbImage};
+use serde::{Deserialize, Serialize};
+use serde_with::{DeserializeFromStr, SerializeDisplay};
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// JPEG quality of processed images.
+const QUALITY: u8 = 90;
+
+/// Opacity of the band behind captions.
+const CAPTION_SHADE: f32 = 0.5;
+
+/// A change made to the pixels of a wallpaper.
+#[derive(Debug, Clone, Copy, PartialEq, SerializeDisplay, DeserializeFromStr)]
+pub enum Effect {
+    /// Darkens the image by an amount from 0 to 1.
+    Dim(f32),
+    /// Blurs the image with a radius in pixels, scaled to the image as if it were 1080 pixels
+    /// high.
+    Blur(f32),
+    Grayscale,
+    /// Darkens the corners by an amount from 0 to 1.
+    Vignette(f32),
+    /// Draws the title and copyright.
+    Caption,
+}
+
+impl Effect {
+    fn apply(self, pixels: RgbImage, image: &Image) -> RgbImage {
+        match self {
+            Effect::Dim(amount) => shade(pixels, |_, _| 1.0 - amount),
+            Effect::Blur(radius) => {
+                let sigma = radius * pixels.height() as f32 / 1080.0;
+                DynamicImage::ImageRgb8(pixels).fast_blur(sigma).to_rgb8()
+            }
+            Effect::Grayscale => DynamicImage::ImageRgb8(pixels).grayscale().to_rgb8(),
+            Effect::Vignette(amount) => {
+                let (width, height) = pixels.dimensions();
+                let (cx, cy) = (width as f32 / 2.0, height as f32 / 2.0);
+
+                shade(pixels, |x, y| {
+                    // 0 at the center, 1 in the corners.
+                    let dx = (x as f32 - cx) / cx;
+                    let dy = (y as f32 - cy) / cy;
+                    let distance = ((dx * dx + dy * dy) / 2.0).sqrt();
+
+                    1.0 - amount * smoothstep(0.4, 1.0, distance)
+                })
+            }
+            Effect::Caption => caption(pixels, image),
+        }
+    }
+}
+
+impl Display for Effect {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Effect::Dim(amount) => write!(f, "dim={amount}"),
+            Effect::Blur(radius) => write!(f, "blur={radius}"),
+            Effect::Grayscale => write!(f, "grayscale"),
+            Effect::Vignette(amount) => write!(f, "vignette={amount}"),
+            Effect::Caption => write!(f, "caption"),
+        }
+    }
+}
+
+impl FromStr for Effect {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, value) = match s.split_once('=') {
+            Some((name, value)) => (name.trim(), Some(value.trim())),
+            None => (s.trim(), None),
+        };
+
+        let number = |default: f32, max: f32| match value {
+            None => Ok(default),
+            Some(value) => value
+                .parse::<f32>()
+                .ok()
+                .filter(|n| (0.0..=max).contains(n))
+                .ok_or_else(|| format!("invalid value for {name}: {value}, expected 0 to {max}")),
+        };
+        let none = |effect: Effect| match value {
+            None => Ok(effect),
+            Some(_) => Err(format!("{name} takes no value")),
+        };
+
+        match name.to_ascii_lowercase().as_str() {
+            "dim" => number(0.3, 1.0).map(Effect::Dim),
+            "blur" => number(8.0, 100.0).map(Effect::Blur),
+            "grayscale" | "greyscale" => none(Effect::Grayscale),
+            "vignette" => number(0.5, 1.0).map(Effect::Vignette),
+            "caption" => none(Effect::Caption),
+            _ => Err(format!(
+                "unknown effect: {name}, expected dim, blur, grayscale, vignette or caption"
+            )),
+        }
+    }
+}
+
+/// Effects applied one after another.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Pipeline {
+    effects: Vec<Effect>,
+}
+
+impl Pipeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn effect(mut self, effect: Effect) -> Self {
+        self.effects.push(effect);
+
+        self
+    }
+
+    pub fn effects(&self) -> &[Effect] {
+        &self.effects
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.effects.is_empty()
+    }
+
+    /// Applies the effects to `pixels`, the decoded `image`.
+    pub fn apply(&self, pixels: DynamicImage, image: &Image) -> DynamicImage {
+        if self.is_empty() {
+            return pixels;
+        }
+
+        let pixels = self
+            .effects
+            .iter()
+            .fold(pixels.to_rgb8(), |pixels, effect| {
+                effect.apply(pixels, image)
+            });
+
+        DynamicImage::ImageRgb8(pixels)
+    }
+
+    /// Applies the effects to the encoded `data` of `image` and returns a JPEG.
+    pub fn apply_bytes(&self, data: &[u8], image: &Image) -> anyhow::Result<Vec<u8>> {
+        let pixels = self.apply(image::load_from_memory(data)?, image);
+
+        let mut data = Vec::new();
+        JpegEncoder::new_with_quality(&mut data, QUALITY).encode_image(&pixels)?;
+
+        Ok(data)
+    }
+
+    /// Applies the effects to the file of `image` at `path` and returns the path of the result,
+    /// which is kept in `cache`. Without effects, `path` is returned as is.
+    pub async fn process(
+        &self,
+        cache: &Cache,
+        image: &Image,
+        path: &Path,
+    ) -> anyhow::Result<PathBuf> {
+        if self.is_empty() {
+            return Ok(path.to_path_buf());
+        }
+
+        let key = cache::derived_key(format!("effects:{}", path.display()), image, self);
+        let cached = cache.path(&key);
+        // The file may have been deleted behind the cache's back.
+        if cache.entry(&key).is_some() && cached.is_file() {
+            return Ok(cached);
+        }
+
+        let data = tokio::fs::read(path).await?;
+        let pipeline = self.clone();
+        let image = image.clone();
+        let data =
+            tokio::task::spawn_blocking(move || pipeline.apply_bytes(&data, &image)).await??;
+
+        Ok(cache.put(&key, &data).await?)
+    }
+}
+
+impl From<Vec<Effect>> for Pipeline {
+    fn from(effects: Vec<Effect>) -> Self {
+        Self { effects }
+    }
+}
+
+/// Formats as the effects separated by commas.
+impl Display for Pipeline {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (i, effect) in self.effects.iter().enumerate() {
+            if i > 0 {
+                write!(f, ",")?;
+            }
+            write!(f, "{effect}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Parses effects separated by commas.
+impl FromStr for Pipeline {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split(',')
+            .filter(|effect| !effect.trim().is_empty())
+            .map(str::parse)
+            .collect::<Result<Vec<_>, _>>()
+            .map(Self::from)
+    }
+}
+
+/// Multiplies each pixel by the factor `f` returns for its position.
+fn shade(mut pixels: RgbImage, f: impl Fn(u32, u32) -> f32) -> RgbImage {
+    for (x, y, pixel) in pixels.enumerate_pixels_mut() {
+        let factor = f(x, y).clamp(0.0, 1.0);
+        pixel.0 = pixel
+            .0
+            .map(|channel| (channel as f32 * factor).round() as u8);
+    }
+
+    pixels
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+/// Draws the title and copyright of `image` on a dark band, leaving out the lines the font can't
+/// draw.
+fn caption(mut pixels: RgbImage, image: &Image) -> RgbImage {
+    let (width, height) = pixels.dimensions();
+
+    // 3 at 1080p.
+    let scale = (height / 360).max(1);
+    let small = (scale * 2 / 3).max(1);
+    let margin = font::SIZE * scale;
+
+    let lines = [
+        (image.title.as_str(), scale),
+        (image.copyright.as_str(), small),
+    ];
+    // Lines in scripts the font lacks, such as Chinese, would only show question marks.
+    let lines = lines
+        .into_iter()
+        .filter(|(text, _)| !text.trim().is_empty() && font::can_render(text))
+        .collect::<Vec<_>>();
+    if lines.is_empty() {
+        return pixels;
+    }
+
+    let line_spacing = |scale: u32| font::SIZE * scale * 3 / 2;
+    let band = lines
+        .iter()
+        .map(|&(_, scale)| line_spacing(scale))
+        .sum::<u32>()
+        + margin;
+    if band + margin > height {
+        return pixels;
+    }
+
+    let top = match image.safe_area {
+        Some(area) if area.top && !area.bottom => margin,
+        _ => height - band - margin,
+    };
+
+    for y in top..top + band {
+        for x in 0..width {
+            let pixel = pixels.get_pixel_mut(x, y);
+            pixel.0 = pixel
+                .0
+                .map(|channel| (channel as f32 * (1.0 - CAPTION_SHADE)).round() as u8);
+        }
+    }
+
+    let mut y = top + margin / 2 + font::SIZE * scale / 4;
+    for (text, scale) in lines {
+        let max = ((width.saturating_sub(2 * margin)) / (font::SIZE * scale)) as usize;
+        draw_text(&mut pixels, &truncate(text.trim(), max), margin, y, scale);
+        y += line_spacing(scale);
+    }
+
+    pixels
+}
+
+/// Shortens `text` to at most `max` characters, ending it with `...` if cut.
+fn truncate(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        return text.to_string();
+    }
+
+    let mut text = text.chars().take(max.saturating_sub(3)).collect::<String>();
+    text.push_str("...");
+    text
+}
+
+fn draw_text(pixels: &mut RgbImage, text: &str, x: u32, y: u32, scale: u32) {
+    let white = Rgb([255, 255, 255]);
+
+    for (i, c) in text.chars().enumerate() {
+        let glyph = font::glyph(c);
+        let left = x + i as u32 * font::SIZE * scale;
+
+        for gy in 0..font::SIZE {
+            for gx in 0..font::SIZE {
+                if !font::is_set(glyph, gx, gy) {
+                    continue;
+                }
+
+                for dy in 0..scale {
+                    for dx in 0..scale {
+                        let (px, py) = (left + gx * scale + dx, y + gy * scale + dy);
+                        if px < pixels.width() && py < pixels.height() {
+                            pixels.put_pixel(px, py, white);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SafeArea;
+    use crate::image::ImageBuilder;
+
+    fn image(safe_area: Option<SafeArea>) -> Image {
+        ImageBuilder::new("OHR.Test_EN-US123_UHD.jpg")
+            .title("Stars over the lake")
+            .copyright("Lake Tekapo, New Zealand (© Someone)")
+            .safe_area(safe_area)
+            .build()
+    }
+
+    fn gray(width: u32, height: u32) -> DynamicImage {
+        DynamicImage::ImageRgb8(RgbImage::from_pixel(width, height, Rgb([200, 200, 200])))
+    }
+
+    fn luma(pixels: &DynamicImage, x: u32, y: u32) -> u8 {
+        pixels.to_rgb8().get_pixel(x, y).0[0]
+    }
+
+    #[test]
+    fn test_parse() {
+        let pipeline = "dim, blur=4,Grayscale,vignette=1,caption"
+            .parse::<Pipeline>()
+            .unwrap();
+
+        assert_eq!(
+            pipeline.effects(),
+            [
+                Effect::Dim(0.3),
+                Effect::Blur(4.0),
+                Effect::Grayscale,
+                Effect::Vignette(1.0),
+                Effect::Caption,
+            ]
+        );
+        assert_eq!(
+            pipeline.to_string(),
+            "dim=0.3,blur=4,grayscale,vignette=1,caption"
+        );
+        assert_eq!(pipeline.to_string().parse::<Pipeline>().unwrap(), pipeline);
+
+        for s in ["dim=2", "blur=-1", "caption=1", "sepia", "dim=x"] {
+            assert!(s.parse::<Effect>().is_err(), "{s}");
+        }
+
+        let json = serde_json::to_string(&pipeline).unwrap();
+        assert_eq!(
+            json,
+            r#"["dim=0.3","blur=4","grayscale","vignette=1","caption"]"#
+        );
+        assert_eq!(serde_json::from_str::<Pipeline>(&json).unwrap(), pipeline);
+    }
+
+    #[test]
+    fn test_dim_and_vignette() {
+        let image = image(None);
+
+        let dimmed = Pipeline::new()
+            .effect(Effect::Dim(0.5))
+            .apply(gray(40, 20), &image);
+        assert_eq!(luma(&dimmed, 20, 10), 100);
+
+        let vignette = Pipeline::new()
+            .effect(Effect::Vignette(1.0))
+            .apply(gray(40, 20), &image);
+        assert_eq!(luma(&vignette, 20, 10), 200);
+        assert!(luma(&vignette, 0, 0) < 20);
+    }
+
+    #[test]
+    fn test_blur_and_grayscale() {
+        let mut pixels = RgbImage::from_pixel(40, 40, Rgb([0, 0, 0]));
+        pixels.put_pixel(20, 20, Rgb([255, 0, 0]));
+
+        let processed = Pipeline::from(vec![Effect::Blur(200.0), Effect::Grayscale])
+            .apply(DynamicImage::ImageRgb8(pixels), &image(None))
+            .to_rgb8();
+
+        let [r, g, b] = processed.get_pixel(20, 20).0;
+        assert!(r < 76, "{r}");
+        assert_eq!((r, r), (g, b));
+    }
+
+    #[test]
+    fn test_caption() {
+        let band = |safe_area| {
+            let captioned = Pipeline::new()
+                .effect(Effect::Caption)
+                .apply(gray(720, 360), &image(safe_area));
+            (luma(&captioned, 710, 20), luma(&captioned, 710, 340))
+        };
+
+        // Shaded at the bottom by default, and at the top when only the top is clear.
+        assert_eq!(band(None), (200, 100));
+        let top = SafeArea {
+            top: true,
+            bottom: false,
+        };
+        assert_eq!(band(Some(top)), (100, 200));
+
+        // Too small for a caption.
+        let tiny = Pipeline::new()
+            .effect(Effect::Caption)
+            .apply(gray(40, 20), &image(None));
+        assert_eq!(luma(&tiny, 20, 19), 200);
+
+        // Nothing the font can draw.
+        let mut chinese = image(None);
+        chinese.title = "星空".to_string();
+        chinese.copyright = "特卡波湖，新西兰".to_string();
+        let untouched = Pipeline::new()
+            .effect(Effect::Caption)
+            .apply(gray(720, 360), &chinese);
+        assert_eq!(luma(&untouched, 710, 340), 200);
+    }
+
+    #[test]
+    fn test_font() {
+        assert_eq!(font::glyph('é'), font::glyph('e'));
+        assert_eq!(font::glyph('富'), font::glyph('?'));
+        assert_ne!(font::glyph('©'), font::glyph('?'));
+        assert!(font::can_render("Lac d’Annecy, Haute-Savoie (© Someone)"));
+        assert!(!font::can_render("星空"));
+        assert_eq!(truncate("Stars over the lake", 10), "Stars o...");
+        assert_eq!(truncate("Stars", 10), "Stars");
+    }
+
+    #[tokio::test]
+    async fn test_process() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = Cache::open(dir.path().join("cache")).unwrap();
+        let path = dir.path().join("a.png");
+        gray(40, 20).save(&path).unwrap();
+        let image = image(None);
+
+        assert_eq!(
+            Pipeline::new()
+                .process(&cache, &image, &path)
+                .await
+                .unwrap(),
+            path
+        );
+
+        let pipeline = Pipeline::new().effect(Effect::Dim(0.5));
+        let processed = pipeline.process(&cache, &image, &path).await.unwrap();
+        let pixels = image::load_from_memory(&std::fs::read(&processed).unwrap()).unwrap();
+        assert!(luma(&pixels, 20, 10).abs_diff(100) <= 2);
+
+        // Processed once.
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            pipeline.process(&cache, &image, &path).await.unwrap(),
+            processed
+        );
+
+        // Made again if the processed file is gone.
+        gray(40, 20).save(&path).unwrap();
+        std::fs::remove_file(&processed).unwrap();
+        assert_eq!(
+            pipeline.process(&cache, &image, &path).await.unwrap(),
+            processed
+        );
+        assert!(processed.is_file());
+    }
+}
@@ -0,0 +1,563 @@
+//! RSS, Atom and JSON Feed documents of wallpapers.
+//!
+//! Each image becomes an entry titled after the image, described by the parsed copyright line,
+//! with the full image attached. Entry IDs come from the image hash, so they stay the same when a
+//! feed is rendered again with newer images.
+
+use crate::bing::Market;
+use crate::{Copyright, Image};
+use serde::Serialize;
+use std::borrow::{Borrow, Cow};
+use std::fmt::Write;
+use strum::{Display, EnumString};
+use url::Url;
+
+/// The number of entries in a feed unless set otherwise.
+pub const DEFAULT_LIMIT: usize = 30;
+
+const GENERATOR: &str = "xpic";
+const JSON_FEED_VERSION: &str = "https://jsonfeed.org/version/1.1";
+
+/// Feed document format.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Display, EnumString)]
+#[strum(ascii_case_insensitive)]
+pub enum Format {
+    /// [RSS 2.0](https://www.rssboard.org/rss-specification).
+    #[default]
+    #[strum(serialize = "rss")]
+    Rss,
+    /// [Atom](https://www.rfc-editor.org/rfc/rfc4287).
+    #[strum(serialize = "atom")]
+    Atom,
+    /// [JSON Feed 1.1](https://www.jsonfeed.org/version/1.1/).
+    #[strum(serialize = "jsonfeed")]
+    JsonFeed,
+}
+
+impl Format {
+    /// The media type of documents in this format.
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            Format::Rss => "application/rss+xml",
+            Format::Atom => "application/atom+xml",
+            Format::JsonFeed => "application/feed+json",
+        }
+    }
+}
+
+/// Builder of a feed of the images of a market.
+#[derive(Debug, Clone)]
+pub struct Feed<'a, T> {
+    market: Market,
+    images: &'a [T],
+    title: Option<String>,
+    url: Option<Url>,
+    limit: usize,
+    resolution: Option<(u32, u32)>,
+}
+
+/// The parts of an image shared by all formats.
+struct Entry<'a> {
+    image: &'a Image,
+    id: String,
+    description: String,
+    credit: Option<String>,
+    enclosure: String,
+}
+
+impl<'a, T: Borrow<Image>> Feed<'a, T> {
+    /// Creates a feed of `images` published in `market`, newest first.
+    pub fn new(market: Market, images: &'a [T]) -> Self {
+        Self {
+            market,
+            images,
+            title: None,
+            url: None,
+            limit: DEFAULT_LIMIT,
+            resolution: None,
+        }
+    }
+
+    /// Sets the feed title instead of one naming the market.
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        self.title = Some(title.into());
+
+        self
+    }
+
+    /// Sets where the feed is published, which Atom and JSON Feed readers use to refresh it.
+    pub fn url(mut self, url: Url) -> Self {
+        self.url = Some(url);
+
+        self
+    }
+
+    /// Sets the maximum number of entries.
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+
+        self
+    }
+
+    /// Attaches images resized to `width` by `height` instead of the full images.
+    pub fn resolution(mut self, width: u32, height: u32) -> Self {
+        self.resolution = Some((width, height));
+
+        self
+    }
+
+    pub fn render(&self, format: Format) -> anyhow::Result<String> {
+        match format {
+            Format::Rss => self.render_rss(),
+            Format::Atom => self.render_atom(),
+            Format::JsonFeed => self.render_json_feed(),
+        }
+    }
+
+    fn render_rss(&self) -> anyhow::Result<String> {
+        let entries = self.entries()?;
+
+        let mut xml = String::new();
+        writeln!(xml, r#"<?xml version="1.0" encoding="utf-8"?>"#)?;
+        writeln!(
+            xml,
+            r#"<rss version="2.0" xmlns:atom="http://www.w3.org/2005/Atom" xmlns:dc="http://purl.org/dc/elements/1.1/">"#
+        )?;
+        writeln!(xml, "  <channel>")?;
+        writeln!(xml, "    <title>{}</title>", escape(&self.feed_title()))?;
+        writeln!(xml, "    <link>{}</link>", escape(&self.home_page()))?;
+        writeln!(
+            xml,
+            "    <description>{}</description>",
+            escape(&self.feed_description())
+        )?;
+        writeln!(xml, "    <language>{}</language>", self.market.code())?;
+        writeln!(xml, "    <generator>{GENERATOR}</generator>")?;
+        if let Some(url) = &self.url {
+            writeln!(
+                xml,
+                r#"    <atom:link href="{}" rel="self" type="{}"/>"#,
+                escape(url.as_str()),
+                Format::Rss.mime_type()
+            )?;
+        }
+        if let Some(entry) = entries.first() {
+            writeln!(
+                xml,
+                "    <lastBuildDate>{}</lastBuildDate>",
+                entry.image.full_start_date.to_rfc2822()
+            )?;
+        }
+
+        for entry in &entries {
+            let image = entry.image;
+
+            writeln!(xml, "    <item>")?;
+            writeln!(xml, "      <title>{}</title>", escape(&image.title))?;
+            writeln!(
+                xml,
+                "      <link>{}</link>",
+                escape(image.copyright_link.as_str())
+            )?;
+            writeln!(
+                xml,
+                "      <description>{}</description>",
+                escape(&entry.description)
+            )?;
+            if let Some(credit) = &entry.credit {
+                writeln!(xml, "      <dc:creator>{}</dc:creator>", escape(credit))?;
+            }
+            writeln!(
+                xml,
+                r#"      <guid isPermaLink="false">{}</guid>"#,
+                escape(&entry.id)
+            )?;
+            writeln!(
+                xml,
+                "      <pubDate>{}</pubDate>",
+                image.full_start_date.to_rfc2822()
+            )?;
+            // The size of the image is unknown without downloading it.
+            writeln!(
+                xml,
+                r#"      <enclosure url="{}" length="0" type="image/jpeg"/>"#,
+                escape(&entry.enclosure)
+            )?;
+            writeln!(xml, "    </item>")?;
+        }
+
+        writeln!(xml, "  </channel>")?;
+        writeln!(xml, "</rss>")?;
+
+        Ok(xml)
+    }
+
+    fn render_atom(&self) -> anyhow::Result<String> {
+        let entries = self.entries()?;
+
+        let mut xml = String::new();
+        writeln!(xml, r#"<?xml version="1.0" encoding="utf-8"?>"#)?;
+        writeln!(
+            xml,
+            r#"<feed xmlns="http://www.w3.org/2005/Atom" xml:lang="{}">"#,
+            self.market.code()
+        )?;
+        writeln!(xml, "  <id>{}</id>", escape(&self.feed_id()))?;
+        writeln!(xml, "  <title>{}</title>", escape(&self.feed_title()))?;
+        writeln!(
+            xml,
+            "  <subtitle>{}</subtitle>",
+            escape(&self.feed_description())
+        )?;
+        writeln!(xml, r#"  <link href="{}"/>"#, escape(&self.home_page()))?;
+        if let Some(url) = &self.url {
+            writeln!(
+                xml,
+                r#"  <link href="{}" rel="self" type="{}"/>"#,
+                escape(url.as_str()),
+                Format::Atom.mime_type()
+            )?;
+        }
+        // Atom requires a date even for an empty feed.
+        let updated = entries
+            .first()
+            .map(|entry| entry.image.full_start_date.to_rfc3339())
+            .unwrap_or_else(|| "1970-01-01T00:00:00+00:00".to_string());
+        writeln!(xml, "  <updated>{updated}</updated>")?;
+        // Atom requires an author for every entry, which entries without a credit inherit.
+        writeln!(xml, "  <author><name>Bing</name></author>")?;
+        writeln!(xml, "  <generator>{GENERATOR}</generator>")?;
+
+        for entry in &entries {
+            let image = entry.image;
+            let date = image.full_start_date.to_rfc3339();
+
+            writeln!(xml, "  <entry>")?;
+            writeln!(xml, "    <id>{}</id>", escape(&entry.id))?;
+            writeln!(xml, "    <title>{}</title>", escape(&image.title))?;
+            writeln!(
+                xml,
+                r#"    <link href="{}"/>"#,
+                escape(image.copyright_link.as_str())
+            )?;
+            writeln!(
+                xml,
+                r#"    <link href="{}" rel="enclosure" type="image/jpeg"/>"#,
+                escape(&entry.enclosure)
+            )?;
+            writeln!(xml, "    <published>{date}</published>")?;
+            writeln!(xml, "    <updated>{date}</updated>")?;
+            writeln!(xml, "    <summary>{}</summary>", escape(&entry.description))?;
+            if let Some(credit) = &entry.credit {
+                writeln!(xml, "    <author><name>{}</name></author>", escape(credit))?;
+            }
+            writeln!(xml, "  </entry>")?;
+        }
+
+        writeln!(xml, "</feed>")?;
+
+        Ok(xml)
+    }
+
+    fn render_json_feed(&self) -> anyhow::Result<String> {
+        #[derive(Serialize)]
+        struct JsonFeed<'a> {
+            version: &'static str,
+            title: String,
+            home_page_url: String,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            feed_url: Option<&'a str>,
+            description: String,
+            language: &'static str,
+            items: Vec<Item<'a>>,
+        }
+
+        #[derive(Serialize)]
+        struct Item<'a> {
+            id: &'a str,
+            url: &'a str,
+            title: &'a str,
+            content_text: &'a str,
+            image: &'a str,
+            date_published: String,
+            #[serde(skip_serializing_if = "Vec::is_empty")]
+            authors: Vec<Author<'a>>,
+            attachments: [Attachment<'a>; 1],
+        }
+
+        #[derive(Serialize)]
+        struct Author<'a> {
+            name: &'a str,
+        }
+
+        #[derive(Serialize)]
+        struct Attachment<'a> {
+            url: &'a str,
+            mime_type: &'static str,
+        }
+
+        let entries = self.entries()?;
+        let items = entries
+            .iter()
+            .map(|entry| Item {
+                id: &entry.id,
+                url: entry.image.copyright_link.as_str(),
+                title: &entry.image.title,
+                content_text: &entry.description,
+                image: &entry.enclosure,
+                date_published: entry.image.full_start_date.to_rfc3339(),
+                authors: entry.credit.iter().map(|name| Author { name }).collect(),
+                attachments: [Attachment {
+                    url: &entry.enclosure,
+                    mime_type: "image/jpeg",
+                }],
+            })
+            .collect();
+
+        let feed = JsonFeed {
+            version: JSON_FEED_VERSION,
+            title: self.feed_title(),
+            home_page_url: self.home_page(),
+            feed_url: self.url.as_ref().map(Url::as_str),
+            description: self.feed_description(),
+            language: self.market.code(),
+            items,
+        };
+
+        let mut json = serde_json::to_string_pretty(&feed)?;
+        json.push('\n');
+
+        Ok(json)
+    }
+
+    /// Returns the newest images of the market, up to the limit.
+    fn entries(&self) -> anyhow::Result<Vec<Entry<'a>>> {
+        let mut images = self
+            .images
+            .iter()
+            .map(Borrow::borrow)
+            .filter(|image: &&Image| {
+                image
+                    .id_parsed
+                    .as_ref()
+                    .and_then(|id| id.market)
+                    .is_none_or(|market| market == self.market)
+            })
+            .collect::<Vec<_>>();
+        images.sort_by_key(|image| std::cmp::Reverse(image.full_start_date));
+        images.truncate(self.limit);
+
+        images
+            .into_iter()
+            .map(|image| {
+                let copyright = image
+                    .copyright_parsed
+                    .clone()
+                    .or_else(|| Copyright::parse(&image.copyright));
+
+                let enclosure = match self.resolution {
+                    Some((width, height)) => image.resized_url(width, height)?,
+                    None => image.download_url()?,
+                };
+
+                Ok(Entry {
+                    image,
+                    id: guid(image),
+                    description: copyright
+                        .as_ref()
+                        .map(|copyright| copyright.description.clone())
+                        .unwrap_or_else(|| image.copyright.clone()),
+                    credit: copyright
+                        .map(|copyright| copyright.copyright)
+                        .filter(|credit| !credit.is_empty()),
+                    enclosure,
+                })
+            })
+            .collect()
+    }
+
+    fn feed_title(&self) -> String {
+        self.title
+            .clone()
+            .unwrap_or_else(|| format!("Bing Wallpapers ({})", self.market.code()))
+    }
+
+    fn feed_description(&self) -> String {
+        format!("Daily Bing wallpapers of the {} market", self.market.code())
+    }
+
+    fn feed_id(&self) -> String {
+        match &self.url {
+            Some(url) => url.to_string(),
+            None => format!("urn:xpic:feed:{}", self.market.code()),
+        }
+    }
+
+    fn home_page(&self) -> String {
+        format!("https://www.bing.com/?mkt={}", self.market.code())
+    }
+}
+
+/// Returns the ID of the feed entry of `image`, which only depends on the image hash.
+pub fn guid(image: &Image) -> String {
+    format!("urn:xpic:image:{}", image.hash)
+}
+
+/// Escapes text for XML content and attribute values.
+fn escape(s: &str) -> Cow<'_, str> {
+    if !s.contains(['&', '<', '>', '"', '\'']) {
+        return Cow::Borrowed(s);
+    }
+
+    let mut escaped = String::with_capacity(s.len() + 8);
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+
+    Cow::Owned(escaped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ID;
+    use crate::image::ImageBuilder;
+    use chrono::{TimeZone, Utc};
+
+    fn image(name: &str, day: u32, copyright: &str) -> Image {
+        ImageBuilder::new(format!("OHR.{name}_EN-US123_UHD.jpg"))
+            .start(Utc.with_ymd_and_hms(2026, 8, day, 7, 0, 0).unwrap())
+            .copyright(copyright)
+            .copyright_link(Url::parse("https://www.bing.com/search?q=a&b=c").unwrap())
+            .title(format!("{name} & more"))
+            .hash(format!("{name}hash"))
+            .build()
+    }
+
+    fn images() -> Vec<Image> {
+        vec![
+            image("Older", 20, "An orca (© John Hyde/Alamy)"),
+            image(
+                "JulierPass",
+                21,
+                "Winding road of Julier Pass, Switzerland (© Westend61/Getty Images)",
+            ),
+        ]
+    }
+
+    #[test]
+    fn test_format() {
+        assert_eq!("jsonfeed".parse::<Format>().unwrap(), Format::JsonFeed);
+        assert_eq!("RSS".parse::<Format>().unwrap(), Format::Rss);
+        assert_eq!(Format::Atom.to_string(), "atom");
+        assert!("json".parse::<Format>().is_err());
+    }
+
+    #[test]
+    fn test_rss() {
+        let images = images();
+        let rss = Feed::new(Market::EN_US, &images)
+            .render(Format::Rss)
+            .unwrap();
+
+        assert!(rss.contains("<language>en-US</language>"));
+        assert!(rss.contains("<title>JulierPass &amp; more</title>"));
+        assert!(
+            rss.contains("<description>Winding road of Julier Pass, Switzerland</description>")
+        );
+        assert!(rss.contains("<dc:creator>© Westend61/Getty Images</dc:creator>"));
+        assert!(rss.contains(r#"<guid isPermaLink="false">urn:xpic:image:JulierPasshash</guid>"#));
+        assert!(rss.contains(
+            r#"<enclosure url="https://www.bing.com/th?id=OHR.JulierPass_EN-US123_UHD.jpg" length="0" type="image/jpeg"/>"#
+        ));
+        assert!(rss.contains("<link>https://www.bing.com/search?q=a&amp;b=c</link>"));
+        assert!(rss.contains("<pubDate>Fri, 21 Aug 2026 07:00:00 +0000</pubDate>"));
+
+        // Newest first.
+        assert!(rss.find("JulierPass").unwrap() < rss.find("Older").unwrap());
+    }
+
+    #[test]
+    fn test_atom() {
+        let images = images();
+        let atom = Feed::new(Market::EN_US, &images)
+            .url(Url::parse("https://example.com/feed.xml").unwrap())
+            .resolution(1920, 1080)
+            .limit(1)
+            .render(Format::Atom)
+            .unwrap();
+
+        assert!(atom.contains("<id>https://example.com/feed.xml</id>"));
+        assert!(atom.contains("<updated>2026-08-21T07:00:00+00:00</updated>"));
+        assert!(atom.contains("<id>urn:xpic:image:JulierPasshash</id>"));
+        assert!(atom.contains(
+            r#"<link href="https://www.bing.com/th?id=OHR.JulierPass_EN-US123_UHD.jpg&amp;w=1920&amp;h=1080&amp;p=0" rel="enclosure" type="image/jpeg"/>"#
+        ));
+        assert!(!atom.contains("Older"));
+    }
+
+    #[test]
+    fn test_json_feed() {
+        let mut images = images();
+        // From another market.
+        images.push(image("Other", 22, "Elsewhere"));
+        images[2].id_parsed = ID::parse("OHR.Other_DE-DE123_UHD.jpg");
+
+        let json = Feed::new(Market::EN_US, &images)
+            .title("Wallpapers")
+            .render(Format::JsonFeed)
+            .unwrap();
+        let feed = serde_json::from_str::<serde_json::Value>(&json).unwrap();
+
+        assert_eq!(feed["version"], JSON_FEED_VERSION);
+        assert_eq!(feed["title"], "Wallpapers");
+        assert!(feed.get("feed_url").is_none());
+
+        let items = feed["items"].as_array().unwrap();
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0]["id"], "urn:xpic:image:JulierPasshash");
+        assert_eq!(
+            items[0]["content_text"],
+            "Winding road of Julier Pass, Switzerland"
+        );
+        assert_eq!(items[0]["authors"][0]["name"], "© Westend61/Getty Images");
+        assert_eq!(
+            items[0]["attachments"][0]["url"],
+            "https://www.bing.com/th?id=OHR.JulierPass_EN-US123_UHD.jpg"
+        );
+        assert_eq!(items[0]["date_published"], "2026-08-21T07:00:00+00:00");
+    }
+
+    #[test]
+    fn test_unparsed_copyright() {
+        let images = vec![image("Plain", 20, "A plain description")];
+        let rss = Feed::new(Market::EN_US, &images)
+            .render(Format::Rss)
+            .unwrap();
+
+        assert!(rss.contains("<description>A plain description</description>"));
+        assert!(!rss.contains("<dc:creator>"));
+
+        let atom = Feed::new(Market::EN_US, &images)
+            .render(Format::Atom)
+            .unwrap();
+        assert!(atom.contains("  <author><name>Bing</name></author>"));
+        assert_eq!(atom.matches("<author>").count(), 1);
+    }
+
+    #[test]
+    fn test_escape() {
+        assert!(matches!(escape("plain"), Cow::Borrowed("plain")));
+        assert_eq!(
+            escape(r#"<a href="x">'&'</a>"#),
+            "&lt;a href=&quot;x&quot;&gt;&apos;&amp;&apos;&lt;/a&gt;"
+        );
+    }
+}
@@ -1,15 +1,22 @@
+use crate::Copyright;
 use crate::bing;
-use crate::bing::Market;
+use crate::bing::{Market, ThumbnailParams};
 use crate::date;
+use crate::duplicates::PerceptualHash;
+use crate::palette::Palette;
 use anyhow::anyhow;
 use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 use serde_with::skip_serializing_none;
+use std::borrow::Borrow;
+use std::cmp::Reverse;
+use std::collections::HashSet;
 use std::fmt::{Display, Formatter};
 use std::sync::LazyLock;
 use url::Url;
 
+#[skip_serializing_none]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Image {
     pub url: Url,
@@ -34,6 +41,18 @@ pub struct Image {
     pub quiz_link: Url,
     pub wallpaper: bool,
     pub hash: String,
+
+    pub video: Option<Video>,
+    /// Where text can go over the image without covering its subject.
+    pub safe_area: Option<SafeArea>,
+    /// Main colors, extracted when metadata is exported.
+    pub palette: Option<Palette>,
+    /// A [BlurHash](crate::blurhash) shown while the image loads, made when metadata is exported.
+    pub blurhash: Option<String>,
+    /// Identifies the photo across reruns under another ID.
+    pub perceptual_hash: Option<PerceptualHash>,
+    /// ID of the first publication of the same photo, if this is a rerun.
+    pub rerun_of: Option<String>,
 }
 
 impl Image {
@@ -49,6 +68,9 @@ impl Image {
             quiz_link,
             wallpaper,
             hash,
+            video,
+            top,
+            bottom,
             ..
         } = image;
 
@@ -56,6 +78,7 @@ impl Image {
 
         let url = base.join(&url)?;
 
+        // Most entries use `/th?id=...`, but some link directly to a file path instead.
         let id = url
             .query_pairs()
             .find_map(|(key, id)| {
@@ -65,6 +88,12 @@ impl Image {
                     None
                 }
             })
+            .or_else(|| {
+                url.path_segments()?
+                    .next_back()
+                    .filter(|name| name.contains('.'))
+                    .map(ToOwned::to_owned)
+            })
             .ok_or_else(|| anyhow!("missing id"))?;
 
         Ok(Image {
@@ -82,6 +111,12 @@ impl Image {
             quiz_link: base.join(&quiz_link)?,
             wallpaper,
             hash,
+            video: video.and_then(|video| Video::parse(video, &base)),
+            safe_area: SafeArea::parse(top, bottom),
+            palette: None,
+            blurhash: None,
+            perceptual_hash: None,
+            rerun_of: None,
         })
     }
 
@@ -89,45 +124,264 @@ impl Image {
     pub fn url_builder(&self) -> bing::UrlBuilder {
         bing::UrlBuilder::new(&self.id)
     }
+
+    /// Whether Bing serves this image by its ID, so that it can be resized and cropped. Images
+    /// Bing links to by file path, and local images, are only available at [`url`](Self::url).
+    pub fn has_thumbnails(&self) -> bool {
+        self.url.query_pairs().any(|(key, _)| key == "id")
+    }
+
+    /// Returns the URL of the full image.
+    pub fn download_url(&self) -> anyhow::Result<String> {
+        if self.has_thumbnails() {
+            self.url_builder().build()
+        } else {
+            Ok(self.url.to_string())
+        }
+    }
+
+    /// Returns the URL of this image resized to `width` by `height`, or of the full image if Bing
+    /// can't resize it.
+    pub fn resized_url(&self, width: u32, height: u32) -> anyhow::Result<String> {
+        if self.has_thumbnails() {
+            self.url_builder()
+                .width(width)
+                .height(height)
+                .no_padding()
+                .build()
+        } else {
+            Ok(self.url.to_string())
+        }
+    }
+
+    /// Fetches a `width` by `height` thumbnail of this image. Images without thumbnails are
+    /// fetched whole.
+    pub async fn fetch_thumbnail(&self, width: u32, height: u32) -> anyhow::Result<Vec<u8>> {
+        if self.url.scheme() == "file" {
+            let path = self
+                .url
+                .to_file_path()
+                .map_err(|_| anyhow!("invalid file URL: {}", self.url))?;
+
+            return Ok(tokio::fs::read(path).await?);
+        }
+
+        if !self.has_thumbnails() {
+            return Ok(reqwest::get(self.url.clone())
+                .await?
+                .error_for_status()?
+                .bytes()
+                .await?
+                .to_vec());
+        }
+
+        Ok(crate::fetch_thumbnail(&self.id)
+            .width(width)
+            .height(height)
+            .send()
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await?
+            .to_vec())
+    }
 }
 
-#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
-pub struct Copyright {
-    pub description: String,
-    pub copyright: String,
+/// Builds [`Image`]s for tests, defaulting every field a test doesn't set.
+#[cfg(test)]
+pub(crate) struct ImageBuilder {
+    image: Image,
 }
 
-static COPYRIGHT_REGEX: LazyLock<Regex> = LazyLock::new(|| {
-    Regex::new(
-        r"(?x)
-(?P<description>.*?)
-\s*\(
-(?P<copyright>.*?)
-\)
-",
-    )
-    .unwrap()
-});
+#[cfg(test)]
+// Some methods are only used by tests of modules behind features.
+#[cfg_attr(not(feature = "cli"), allow(dead_code))]
+impl ImageBuilder {
+    /// An image titled and hashed after `id`, published on Bing at midnight on 2026-01-01.
+    pub fn new(id: impl Into<String>) -> Self {
+        let id = id.into();
+        let link = Url::parse(bing::BASE_URL).unwrap();
+        let start = NaiveDate::from_ymd_opt(2026, 1, 1).unwrap();
+
+        Self {
+            image: Image {
+                url: link.join(&format!("/th?id={id}")).unwrap(),
+                start_date: start,
+                full_start_date: start.and_time(chrono::NaiveTime::MIN).and_utc(),
+                end_date: start.checked_add_days(chrono::Days::new(1)).unwrap(),
+                id_parsed: ID::parse(&id),
+                copyright: String::new(),
+                copyright_parsed: None,
+                copyright_link: link.clone(),
+                title: id.clone(),
+                quiz_link: link,
+                wallpaper: true,
+                hash: id.clone(),
+                id,
+                video: None,
+                safe_area: None,
+                palette: None,
+                blurhash: None,
+                perceptual_hash: None,
+                rerun_of: None,
+            },
+        }
+    }
+
+    pub fn url(mut self, url: Url) -> Self {
+        self.image.url = url;
+
+        self
+    }
+
+    /// Publishes the image at `start`, until the next day.
+    pub fn start(mut self, start: DateTime<Utc>) -> Self {
+        self.image.full_start_date = start;
+        self.image.start_date = start.date_naive();
+        self.image.end_date = start
+            .date_naive()
+            .checked_add_days(chrono::Days::new(1))
+            .unwrap();
+
+        self
+    }
+
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        self.image.title = title.into();
+
+        self
+    }
+
+    /// Sets the copyright line, leaving it unparsed as in metadata read from disk.
+    pub fn copyright(mut self, copyright: impl Into<String>) -> Self {
+        self.image.copyright = copyright.into();
+
+        self
+    }
+
+    pub fn copyright_link(mut self, link: Url) -> Self {
+        self.image.copyright_link = link;
+
+        self
+    }
+
+    pub fn hash(mut self, hash: impl Into<String>) -> Self {
+        self.image.hash = hash.into();
+
+        self
+    }
+
+    pub fn safe_area(mut self, safe_area: Option<SafeArea>) -> Self {
+        self.image.safe_area = safe_area;
+
+        self
+    }
+
+    pub fn perceptual_hash(mut self, hash: Option<PerceptualHash>) -> Self {
+        self.image.perceptual_hash = hash;
+
+        self
+    }
+
+    pub fn build(self) -> Image {
+        self.image
+    }
+}
+
+/// Merges two image lists, deduplicating by `id`. Items from `new` take priority over `existing`.
+/// The result is sorted by `start_date` descending.
+pub fn merge<T>(existing: &[T], new: &[T]) -> Vec<T>
+where
+    T: Borrow<Image> + Clone,
+{
+    let mut seen = HashSet::new();
+    let mut result = Vec::with_capacity(existing.len() + new.len());
+
+    for img in new.iter().chain(existing.iter()) {
+        if seen.insert(img.borrow().id.clone()) {
+            result.push(img.clone());
+        }
+    }
+
+    result.sort_by_key(|image| Reverse(image.borrow().start_date));
+    result
+}
+
+/// Bands along the edges of an image that Bing keeps clear for overlaid text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SafeArea {
+    pub top: bool,
+    pub bottom: bool,
+}
+
+impl SafeArea {
+    /// Parses the `top` and `bot` flags of the API, which are `1` for clear bands.
+    fn parse(top: Option<isize>, bottom: Option<isize>) -> Option<Self> {
+        if top.is_none() && bottom.is_none() {
+            return None;
+        }
+
+        Some(Self {
+            top: top.is_some_and(|top| top > 0),
+            bottom: bottom.is_some_and(|bottom| bottom > 0),
+        })
+    }
+}
+
+/// Video or animated background attached to an image.
+#[skip_serializing_none]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Video {
+    pub sources: Vec<VideoSource>,
+    #[serde(default)]
+    pub looping: bool,
+    pub poster: Option<Url>,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VideoSource {
+    pub mime: String,
+    pub url: Url,
+    pub codecs: Option<String>,
+}
 
-impl Copyright {
-    pub fn parse(s: impl AsRef<str>) -> Option<Self> {
-        if let Some(captures) = COPYRIGHT_REGEX.captures(s.as_ref())
-            && let (Some(description), Some(copyright)) =
-                (captures.name("description"), captures.name("copyright"))
-        {
-            Some(Copyright {
-                description: description.as_str().to_owned(),
-                copyright: copyright.as_str().to_owned(),
+impl Video {
+    /// Converts the raw `vid` payload, resolving relative URLs against `base`.
+    ///
+    /// Returns `None` if no source has a usable URL.
+    pub fn parse(video: bing::Video, base: &Url) -> Option<Self> {
+        let sources = video
+            .sources
+            .into_iter()
+            .filter_map(|source| {
+                let mut source = source.into_iter();
+
+                Some(VideoSource {
+                    mime: source.next()?,
+                    url: base.join(&source.next()?).ok()?,
+                    codecs: source.next().filter(|codecs| !codecs.is_empty()),
+                })
             })
-        } else {
-            None
+            .collect::<Vec<_>>();
+
+        if sources.is_empty() {
+            return None;
         }
+
+        Some(Video {
+            sources,
+            looping: video.looping.unwrap_or_default(),
+            poster: video.image.and_then(|image| base.join(&image).ok()),
+        })
     }
 }
 
 #[skip_serializing_none]
 #[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct ID {
+    /// Image series prefix, e.g. `OHR`. Empty if the ID has no prefix.
+    pub prefix: String,
     pub name: String,
     pub market: Option<Market>,
     pub number: usize,
@@ -140,6 +394,7 @@ pub struct ID {
 impl Display for ID {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         let ID {
+            prefix,
             name,
             market,
             number,
@@ -155,15 +410,16 @@ impl Display for ID {
             "ROW".to_owned()
         };
 
+        if !prefix.is_empty() {
+            write!(f, "{prefix}.")?;
+        }
+
         if *uhd {
-            return write!(f, "OHR.{name}_{market}{number}_UHD.{extension}");
+            return write!(f, "{name}_{market}{number}_UHD.{extension}");
         }
 
         if let (Some(width), Some(height)) = (width, height) {
-            write!(
-                f,
-                "OHR.{name}_{market}{number}_{width}x{height}.{extension}"
-            )
+            write!(f, "{name}_{market}{number}_{width}x{height}.{extension}")
         } else {
             Err(std::fmt::Error)
         }
@@ -173,8 +429,8 @@ impl Display for ID {
 static ID_REGEX: LazyLock<Regex> = LazyLock::new(|| {
     Regex::new(
         r"(?x)
-^OHR
-\.
+^
+((?P<prefix>[A-Za-z]+)\.)?
 (?P<name>\w+)
 _
 (?P<market>ROW|\w{2}-\w{2})
@@ -198,6 +454,10 @@ impl ID {
         let uhd = captures.name("uhd").is_some();
 
         let id = Self {
+            prefix: captures
+                .name("prefix")
+                .map(|prefix| prefix.as_str().to_owned())
+                .unwrap_or_default(),
             name: captures.name("name")?.as_str().to_owned(),
             market: captures.name("market")?.as_str().parse::<Market>().ok(),
             number: captures.name("number")?.as_str().parse::<usize>().ok()?,
@@ -230,6 +490,7 @@ mod tests {
             (
                 "OHR.YosemiteFirefall_ROW8895162487_1920x1080.jpg",
                 ID {
+                    prefix: "OHR".to_string(),
                     name: "YosemiteFirefall".to_string(),
                     market: None,
                     number: 8895162487,
@@ -242,6 +503,7 @@ mod tests {
             (
                 "OHR.HalfDomeYosemite_EN-US4890007214_UHD.jpg",
                 ID {
+                    prefix: "OHR".to_string(),
                     name: "HalfDomeYosemite".to_string(),
                     market: Some(Market::EN_US),
                     number: 4890007214,
@@ -250,6 +512,31 @@ mod tests {
                     ..Default::default()
                 },
             ),
+            (
+                "BingAI.NorthernLights_ZH-CN9123456789_1920x1080.png",
+                ID {
+                    prefix: "BingAI".to_string(),
+                    name: "NorthernLights".to_string(),
+                    market: Some(Market::ZH_CN),
+                    number: 9123456789,
+                    width: Some(1920),
+                    height: Some(1080),
+                    extension: "png".to_string(),
+                    ..Default::default()
+                },
+            ),
+            (
+                "Matterhorn_EN-GB1234567890_1920x1200.jpg",
+                ID {
+                    name: "Matterhorn".to_string(),
+                    market: Some(Market::EN_GB),
+                    number: 1234567890,
+                    width: Some(1920),
+                    height: Some(1200),
+                    extension: "jpg".to_string(),
+                    ..Default::default()
+                },
+            ),
         ];
 
         for (id, expected) in test_cases {
@@ -258,4 +545,109 @@ mod tests {
             assert_eq!(parsed.to_string(), id);
         }
     }
+
+    fn raw_image(url: &str) -> bing::Image {
+        serde_json::from_value(serde_json::json!({
+            "startdate": "20260821",
+            "fullstartdate": "202608210700",
+            "enddate": "20260822",
+            "url": url,
+            "urlbase": "",
+            "copyright": "Winding road of Julier Pass, Switzerland (© Westend61/Getty Images)",
+            "copyrightlink": "/search?q=Julier+Pass",
+            "title": "The climb is calling",
+            "quiz": "/search?q=Bing+homepage+quiz",
+            "wp": true,
+            "hsh": "459542aadbfb9d8f326d1d697dd9b873",
+            "top": 1,
+            "bot": 0
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_parse_path_url() {
+        let image = Image::parse(raw_image(
+            "/az/hprichbg/rb/JulierPass_EN-US2643379571_1920x1080.jpg",
+        ))
+        .unwrap();
+
+        assert_eq!(image.id, "JulierPass_EN-US2643379571_1920x1080.jpg");
+
+        // Bing doesn't serve such images by ID, so they are downloaded from where they are.
+        let url = "https://www.bing.com/az/hprichbg/rb/JulierPass_EN-US2643379571_1920x1080.jpg";
+        assert!(!image.has_thumbnails());
+        assert_eq!(image.download_url().unwrap(), url);
+        assert_eq!(image.resized_url(1280, 720).unwrap(), url);
+
+        assert_eq!(
+            image.safe_area,
+            Some(SafeArea {
+                top: true,
+                bottom: false
+            })
+        );
+
+        let id = image.id_parsed.expect("failed to parse id");
+        assert_eq!(id.prefix, "");
+        assert_eq!(id.market, Some(Market::EN_US));
+    }
+
+    #[test]
+    fn test_parse_video() {
+        let mut raw = raw_image("/th?id=OHR.JulierPass_EN-US2643379571_UHD.jpg");
+        raw.video = serde_json::from_value(serde_json::json!({
+            "sources": [
+                ["video/mp4", "//az29176.vo.msecnd.net/videocontent/JulierPass_1080_HD_EN-US.mp4"],
+                ["video/webm", "//az29176.vo.msecnd.net/videocontent/JulierPass.webm", "vp8"],
+                ["video/mp4"]
+            ],
+            "loop": true,
+            "image": "//az29176.vo.msecnd.net/videocontent/JulierPass_poster.jpg"
+        }))
+        .unwrap();
+
+        let image = Image::parse(raw).unwrap();
+        assert!(image.has_thumbnails());
+        assert_eq!(
+            image.resized_url(1280, 720).unwrap(),
+            "https://www.bing.com/th?id=OHR.JulierPass_EN-US2643379571_UHD.jpg&w=1280&h=720&p=0"
+        );
+
+        let video = image.video.expect("missing video");
+
+        assert_eq!(video.sources.len(), 2);
+        assert_eq!(
+            video.sources[0].url.as_str(),
+            "https://az29176.vo.msecnd.net/videocontent/JulierPass_1080_HD_EN-US.mp4"
+        );
+        assert_eq!(video.sources[1].codecs.as_deref(), Some("vp8"));
+        assert!(video.looping);
+        assert!(video.poster.is_some());
+
+        // Images without video keep the existing JSON shape.
+        let image = Image::parse(raw_image("/th?id=OHR.JulierPass_EN-US2643379571_UHD.jpg"));
+        let json = serde_json::to_value(image.unwrap()).unwrap();
+        assert!(json.get("video").is_none());
+    }
+
+    #[test]
+    fn test_merge() {
+        let parse = |id: &str, date: NaiveDate, title: &str| {
+            let mut image = Image::parse(raw_image(&format!("/th?id=OHR.{id}_UHD.jpg"))).unwrap();
+            image.start_date = date;
+            image.title = title.to_string();
+            image
+        };
+        let day = |d| NaiveDate::from_ymd_opt(2026, 8, d).unwrap();
+
+        let existing = [parse("A", day(1), "old"), parse("B", day(3), "B")];
+        let new = [parse("A", day(1), "new"), parse("C", day(2), "C")];
+
+        let titles = merge(&existing, &new)
+            .into_iter()
+            .map(|image| image.title)
+            .collect::<Vec<_>>();
+        assert_eq!(titles, ["B", "C", "new"]);
+    }
 }
@@ -1,13 +1,30 @@
 pub mod bing;
+pub mod blurhash;
+#[cfg(feature = "cache")]
+pub mod cache;
+#[cfg(feature = "daemon")]
+pub mod daemon;
 pub mod date;
+pub mod duplicates;
+#[cfg(feature = "effects")]
+pub mod effects;
+pub mod feed;
 
 mod client;
+mod copyright;
 mod image;
+pub mod palette;
+pub mod rotation;
+pub mod search;
+pub mod source;
+#[cfg(feature = "wallpaper")]
+pub mod wallpaper;
 
 use std::sync::LazyLock;
 
 pub use crate::client::{Client, ImagesRequestBuilder, ThumbnailRequestBuilder};
-pub use crate::image::{Copyright, Image, ID};
+pub use crate::copyright::Copyright;
+pub use crate::image::{Image, SafeArea, Video, VideoSource, ID, merge};
 
 static DEFAULT_CLIENT: LazyLock<Client> = LazyLock::new(Client::default);
 
@@ -1,23 +1,41 @@
-use crate::CLI::{Download, Export, List};
-use anyhow::anyhow;
-use clap::{Args, Parser};
+use crate::CLI::{Archive, Cache, Daemon, Download, Export, Feed, List, Search, Set};
+use anyhow::{anyhow, bail};
+use clap::{Args, Parser, Subcommand, ValueEnum};
 use comfy_table::modifiers::UTF8_ROUND_CORNERS;
 use comfy_table::presets::UTF8_BORDERS_ONLY;
 use comfy_table::{Attribute, Cell, Color, ContentArrangement, Table};
 use futures::StreamExt;
+use std::cmp::Reverse;
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use strum::IntoEnumIterator;
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::io::AsyncReadExt;
+use tracing_subscriber::EnvFilter;
+use url::Url;
+use xpic::bing::Market;
 use xpic::bing::QueryParams;
-use xpic::bing::{Market, UrlBuilder};
-use xpic::{fetch_image, list_images, Image, ImagesRequestBuilder};
+use xpic::blurhash;
+use xpic::cache;
+use xpic::daemon;
+use xpic::duplicates::{self, PerceptualHash};
+use xpic::effects::{Effect, Pipeline};
+use xpic::feed::{self, Format};
+use xpic::palette::{self, Palette};
+use xpic::rotation::{Policy, Rotation};
+use xpic::source::{BingSource, LocalSource, WallpaperSource};
+use xpic::wallpaper::{Fit, Monitor};
+use xpic::{Image, list_images, search, wallpaper};
+
+/// How many thumbnails are fetched at once to extract palettes and placeholders.
+const THUMBNAIL_CONCURRENCY: usize = 8;
 
 /// Bing wallpapers
 #[derive(Parser)]
 #[command(version, about, arg_required_else_help(true))]
+#[allow(clippy::upper_case_acronyms)]
 enum CLI {
-    /// List Bing wallpapers
+    /// List wallpapers
     List(QueryArgs),
 
     /// Download recent wallpapers to a directory
@@ -26,16 +44,238 @@ enum CLI {
         #[arg(short, long, value_name = "DIR")]
         output: PathBuf,
 
+        /// An effect to apply to each image, e.g. dim=0.3, blur, grayscale, vignette or caption.
+        /// Repeat to apply several in order. The images are then saved as JPEG
+        #[arg(long = "effect", value_name = "EFFECT")]
+        effects: Vec<Effect>,
+
         #[command(flatten)]
         args: QueryArgs,
     },
 
-    /// Export wallpaper metadata to JSON files
+    /// Set a wallpaper as the desktop background
+    Set(SetArgs),
+
+    /// Keep the desktop wallpaper up to date in the background
+    Daemon(DaemonArgs),
+
+    /// Export wallpaper metadata, color palettes and placeholders to JSON files
     Export {
         /// The output directory
         #[arg(short, long, value_name = "DIR")]
         output: PathBuf,
     },
+
+    /// Search exported wallpaper metadata
+    Search {
+        /// The directory containing exported JSON files
+        #[arg(short, long, value_name = "DIR", default_value = "data")]
+        data: PathBuf,
+
+        /// The maximum number of results
+        #[arg(short, long, default_value_t = 8)]
+        number: usize,
+
+        /// The query, e.g. market:ja-JP year:2025 "mountain lake"
+        #[arg(required = true)]
+        query: Vec<String>,
+    },
+
+    /// Render a feed of the wallpapers of a market from exported metadata
+    Feed {
+        /// The directory containing exported JSON files
+        #[arg(short, long, value_name = "DIR", default_value = "data")]
+        data: PathBuf,
+
+        /// Market code
+        #[arg(short, long, ignore_case = true)]
+        market: Market,
+
+        /// The feed format: rss, atom or jsonfeed
+        #[arg(short, long, default_value_t = Format::Rss)]
+        format: Format,
+
+        /// The maximum number of entries
+        #[arg(short, long, default_value_t = feed::DEFAULT_LIMIT)]
+        number: usize,
+
+        /// Link enclosures resized to this size, e.g. 1920x1080, instead of the full UHD images
+        #[arg(short, long, value_name = "WxH", value_parser = parse_resolution)]
+        resolution: Option<(u32, u32)>,
+
+        /// The feed title, "Bing Wallpapers (<market>)" by default
+        #[arg(long)]
+        title: Option<String>,
+
+        /// Where the feed will be published
+        #[arg(long)]
+        url: Option<Url>,
+
+        /// The output file, or standard output if not specified
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Inspect exported wallpaper metadata
+    Archive {
+        /// The directory containing exported JSON files
+        #[arg(short, long, value_name = "DIR", default_value = "data", global = true)]
+        data: PathBuf,
+
+        #[command(subcommand)]
+        command: ArchiveCommand,
+    },
+
+    /// Manage the image cache
+    Cache {
+        /// The cache directory
+        #[arg(long, value_name = "DIR", global = true)]
+        dir: Option<PathBuf>,
+
+        #[command(subcommand)]
+        command: CacheCommand,
+    },
+}
+
+#[derive(Args)]
+struct SetArgs {
+    /// Market code
+    #[arg(short, long, ignore_case = true)]
+    market: Option<Market>,
+
+    /// Index (0 = today)
+    #[arg(short, long, default_value_t = 0)]
+    index: usize,
+
+    /// Resize the image, e.g. 1920x1080, instead of using the full UHD image
+    #[arg(short, long, value_name = "WxH", value_parser = parse_resolution)]
+    resolution: Option<(u32, u32)>,
+
+    /// Also set the lock screen image
+    #[arg(long)]
+    lock_screen: bool,
+
+    /// Frame the image for each monitor: per-monitor or span
+    #[arg(long, conflicts_with = "resolution")]
+    fit: Option<Fit>,
+
+    /// A monitor to frame the image for, e.g. DP-1=2560x1440+1920+0, instead of those listed
+    /// by the desktop
+    #[arg(long = "monitor", value_name = "[ID=]WxH+X+Y", requires = "fit")]
+    monitors: Vec<Monitor>,
+
+    /// An effect to apply before setting the image, e.g. dim=0.3, blur, grayscale, vignette or
+    /// caption. Repeat to apply several in order
+    #[arg(long = "effect", value_name = "EFFECT")]
+    effects: Vec<Effect>,
+
+    /// Print what would be set without downloading or setting anything
+    #[arg(long)]
+    dry_run: bool,
+
+    /// The cache directory
+    #[arg(long, value_name = "DIR")]
+    cache_dir: Option<PathBuf>,
+}
+
+#[derive(Args)]
+struct DaemonArgs {
+    /// Market code
+    #[arg(short, long, ignore_case = true)]
+    market: Option<Market>,
+
+    /// Resize the image, e.g. 1920x1080, instead of using the full UHD image
+    #[arg(short, long, value_name = "WxH", value_parser = parse_resolution)]
+    resolution: Option<(u32, u32)>,
+
+    /// Also set the lock screen image
+    #[arg(long)]
+    lock_screen: bool,
+
+    /// Frame the image for each monitor: per-monitor or span
+    #[arg(long, conflicts_with = "resolution")]
+    fit: Option<Fit>,
+
+    /// A monitor to frame the image for, e.g. DP-1=2560x1440+1920+0, instead of those listed
+    /// by the desktop
+    #[arg(long = "monitor", value_name = "[ID=]WxH+X+Y", requires = "fit")]
+    monitors: Vec<Monitor>,
+
+    /// An effect to apply before setting the image, e.g. dim=0.3, blur, grayscale, vignette or
+    /// caption. Repeat to apply several in order
+    #[arg(long = "effect", value_name = "EFFECT")]
+    effects: Vec<Effect>,
+
+    /// The cache directory
+    #[arg(long, value_name = "DIR")]
+    cache_dir: Option<PathBuf>,
+
+    /// Rotate through older images instead of showing the newest one: sequential, shuffle,
+    /// favorites or on-this-day
+    #[arg(long, value_name = "POLICY")]
+    rotate: Option<Policy>,
+
+    /// How often to rotate, e.g. 30m, 6h or 1d
+    #[arg(long, value_parser = parse_interval, default_value = "1h", requires = "rotate")]
+    interval: chrono::Duration,
+
+    /// The directory containing exported JSON files to rotate through
+    #[arg(long, value_name = "DIR", requires = "rotate")]
+    archive: Option<PathBuf>,
+
+    /// A file listing favorite image IDs or names, one per line
+    #[arg(long, value_name = "FILE", requires = "rotate")]
+    favorites: Option<PathBuf>,
+
+    /// Rotate only through images published on or after this date, e.g. 2024-01-01
+    #[arg(long, value_name = "DATE", requires = "rotate")]
+    from: Option<chrono::NaiveDate>,
+
+    /// Rotate only through images published on or before this date, e.g. 2024-12-31
+    #[arg(long, value_name = "DATE", requires = "rotate")]
+    to: Option<chrono::NaiveDate>,
+
+    /// Print a systemd user unit that runs the daemon with these options, and exit
+    #[arg(long)]
+    systemd_unit: bool,
+}
+
+#[derive(Subcommand)]
+enum ArchiveCommand {
+    /// Report photos published more than once under different IDs
+    Dupes {
+        /// The maximum number of bits in which perceptual hashes of the same photo differ
+        #[arg(long, default_value_t = duplicates::DEFAULT_THRESHOLD)]
+        threshold: u32,
+
+        /// Mark reruns in the metadata files, so galleries can collapse them
+        #[arg(long)]
+        mark: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum CacheCommand {
+    /// Show cache usage
+    Stats {
+        /// The maximum cache size to report usage against, such as the limit set in the app
+        #[arg(long, value_parser = parse_size)]
+        max_size: Option<u64>,
+    },
+
+    /// Remove all cached files
+    Clear,
+
+    /// Remove expired, excess and stray files
+    Prune {
+        /// The maximum cache size, e.g. 512M or 2G
+        #[arg(long, value_parser = parse_size)]
+        max_size: Option<u64>,
+
+        /// Remove files fetched more than this many days ago
+        #[arg(long, value_name = "DAYS")]
+        max_age: Option<u32>,
+    },
 }
 
 #[derive(Args)]
@@ -55,15 +295,54 @@ struct QueryArgs {
     /// Ultra High Definition
     #[arg(short, long, default_value_t = true)]
     uhd: bool,
+
+    /// Where to get wallpapers from
+    #[arg(short, long, value_enum, default_value_t = SourceKind::Bing)]
+    source: SourceKind,
+
+    /// The folder of images to list with `--source local`
+    #[arg(long, value_name = "DIR", required_if_eq("source", "local"))]
+    dir: Option<PathBuf>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum SourceKind {
+    Bing,
+    Local,
 }
 
 impl QueryArgs {
-    fn into_builder(self) -> ImagesRequestBuilder<'static> {
-        list_images()
-            .number(self.number)
-            .index(self.index)
-            .market_option(self.market)
-            .uhd(self.uhd)
+    fn into_source(self) -> Box<dyn WallpaperSource> {
+        match self.source {
+            SourceKind::Bing => Box::new(
+                BingSource::new()
+                    .number(self.number)
+                    .index(self.index)
+                    .market_option(self.market)
+                    .uhd(self.uhd),
+            ),
+            SourceKind::Local => Box::new(LocalSource::new(self.dir.unwrap_or_default())),
+        }
+    }
+
+    /// Lists wallpapers from the selected source, at most `number` from `index`.
+    async fn list(self) -> anyhow::Result<(Arc<dyn WallpaperSource>, Vec<Image>)> {
+        let (index, number) = match self.source {
+            // Bing applies them in the query.
+            SourceKind::Bing => (0, self.number),
+            SourceKind::Local => (self.index, self.number),
+        };
+
+        let source = Arc::<dyn WallpaperSource>::from(self.into_source());
+        let images = source
+            .list()
+            .await?
+            .into_iter()
+            .skip(index)
+            .take(number)
+            .collect();
+
+        Ok((source, images))
     }
 }
 
@@ -73,24 +352,94 @@ async fn main() -> Result<(), anyhow::Error> {
 
     match cli {
         List(args) => {
-            let images = args
-                .into_builder()
-                .send()
+            let (_, images) = args
+                .list()
                 .await
                 .map_err(|err| anyhow!("failed to list wallpapers: {err}"))?;
 
             print_images_table(images)?;
         }
-        Download { output, args } => {
-            download_wallpapers(&output, args)
+        Download {
+            output,
+            effects,
+            args,
+        } => {
+            download_wallpapers(&output, args, Pipeline::from(effects))
                 .await
                 .map_err(|err| anyhow!("failed to save wallpapers: {err}"))?;
         }
+        Set(args) => {
+            set_wallpaper(args)
+                .await
+                .map_err(|err| anyhow!("failed to set wallpaper: {err}"))?;
+        }
+        Daemon(args) => {
+            run_daemon(args)
+                .await
+                .map_err(|err| anyhow!("failed to run daemon: {err}"))?;
+        }
         Export { output } => {
             export_metadata(&output)
                 .await
                 .map_err(|err| anyhow!("failed to export metadata: {err}"))?;
         }
+        Search {
+            data,
+            number,
+            query,
+        } => {
+            let images = load_metadata(&data)
+                .await
+                .map_err(|err| anyhow!("failed to load metadata: {err}"))?;
+
+            let mut images = search::search(&images, join_query(query));
+            images.truncate(number);
+
+            print_images_table(images)?;
+        }
+        Feed {
+            data,
+            market,
+            format,
+            number,
+            resolution,
+            title,
+            url,
+            output,
+        } => {
+            let images = read_metadata_file(&data.join(format!("{}.json", market.code())))
+                .await
+                .map_err(|err| anyhow!("failed to load metadata: {err}"))?;
+
+            let mut feed = feed::Feed::new(market, &images).limit(number);
+            if let Some((width, height)) = resolution {
+                feed = feed.resolution(width, height);
+            }
+            if let Some(title) = title {
+                feed = feed.title(title);
+            }
+            if let Some(url) = url {
+                feed = feed.url(url);
+            }
+            let feed = feed.render(format)?;
+
+            match output {
+                Some(path) => tokio::fs::write(&path, feed)
+                    .await
+                    .map_err(|err| anyhow!("failed to write {}: {err}", path.display()))?,
+                None => print!("{feed}"),
+            }
+        }
+        Archive { data, command } => {
+            run_archive_command(&data, command)
+                .await
+                .map_err(|err| anyhow!("failed to manage archive: {err}"))?;
+        }
+        Cache { dir, command } => {
+            run_cache_command(dir, command)
+                .await
+                .map_err(|err| anyhow!("failed to manage cache: {err}"))?;
+        }
     }
 
     Ok(())
@@ -110,10 +459,12 @@ fn print_images_table(images: Vec<Image>) -> anyhow::Result<()> {
         ]);
 
     for image in images {
+        let link = image.download_url()?;
+
         table.add_row(vec![
             Cell::new(image.start_date).fg(Color::DarkYellow),
             Cell::new(image.title).fg(Color::DarkGreen),
-            Cell::new(UrlBuilder::new(image.id).build()?).fg(Color::DarkCyan),
+            Cell::new(link).fg(Color::DarkCyan),
         ]);
     }
 
@@ -122,53 +473,222 @@ fn print_images_table(images: Vec<Image>) -> anyhow::Result<()> {
     Ok(())
 }
 
-async fn download_file(id: impl Into<String>, path: impl AsRef<Path>) -> Result<(), anyhow::Error> {
-    if path.as_ref().exists() {
-        return Ok(());
+async fn download_wallpapers(
+    dir: impl AsRef<Path>,
+    args: QueryArgs,
+    effects: Pipeline,
+) -> Result<(), anyhow::Error> {
+    let dir = dir.as_ref();
+    let effects = Arc::new(effects);
+
+    tokio::fs::create_dir_all(dir).await?;
+
+    let (source, images) = args.list().await?;
+
+    let tasks = images.into_iter().filter_map(|image| {
+        let mut path = dir.join(&image.id);
+        // Processed images are encoded as JPEG.
+        if !effects.is_empty() {
+            path.set_extension("jpg");
+        }
+        if path.exists() {
+            return None;
+        }
+
+        let source = source.clone();
+        let effects = effects.clone();
+
+        Some(tokio::spawn(async move {
+            let result = async {
+                let mut data = source.fetch(&image).await?;
+                if !effects.is_empty() {
+                    data = tokio::task::spawn_blocking(move || effects.apply_bytes(&data, &image))
+                        .await??;
+                }
+                tokio::fs::write(&path, data).await?;
+                anyhow::Ok(())
+            }
+            .await
+            .map_err(|err| anyhow!("download failed: {err}"));
+
+            if let Err(err) = result {
+                eprintln!("{err}");
+            }
+        }))
+    });
+
+    futures::future::join_all(tasks).await;
+    Ok(())
+}
+
+/// Sets a Bing wallpaper with the backend for the running desktop, reusing cached images.
+async fn set_wallpaper(args: SetArgs) -> Result<(), anyhow::Error> {
+    let images = BingSource::new()
+        .market_option(args.market)
+        .index(args.index)
+        .number(1)
+        .uhd(true)
+        .list()
+        .await?;
+    let Some(image) = images.into_iter().next() else {
+        bail!("no wallpaper at index {}", args.index);
+    };
+
+    let url = match args.resolution {
+        Some((width, height)) => image.resized_url(width, height)?,
+        None => image.download_url()?,
+    };
+
+    // A dry run reports a missing backend instead of failing.
+    let backend = wallpaper::detect();
+    if let Ok(backend) = &backend {
+        if args.lock_screen && !backend.supports_lock_screen() {
+            bail!("{} cannot set the lock screen", backend.name());
+        }
+
+        if args.fit.is_some() && !backend.supports_monitors() {
+            bail!("{} cannot set a wallpaper per monitor", backend.name());
+        }
     }
+    let monitors = match &backend {
+        Ok(backend) if args.fit.is_some() && args.monitors.is_empty() => {
+            if args.dry_run {
+                backend.monitors().unwrap_or_default()
+            } else {
+                backend.monitors()?
+            }
+        }
+        _ => args.monitors,
+    };
 
-    let resp = fetch_image(id).await?.error_for_status()?;
+    let cache = cache::Cache::open(args.cache_dir.unwrap_or_else(cache::Cache::default_dir))?;
+    let target = if args.lock_screen {
+        "wallpaper and lock screen"
+    } else {
+        "wallpaper"
+    };
+    let effects = Pipeline::from(args.effects);
+
+    if args.dry_run {
+        println!("Image:   {} ({})", image.title, image.start_date);
+        println!("URL:     {url}");
+        let path = cache.path(&url);
+        let cached = if path.exists() {
+            "cached"
+        } else {
+            "not cached"
+        };
+        println!("File:    {} ({cached})", path.display());
+        if let Some(fit) = args.fit {
+            for monitor in &monitors {
+                println!("Monitor: {monitor} ({fit})");
+            }
+        }
+        if !effects.is_empty() {
+            println!("Effects: {effects}");
+        }
+        match backend {
+            Ok(backend) => println!("Backend: {} would set the {target}", backend.name()),
+            Err(err) => println!("Backend: none ({err})"),
+        }
 
-    let mut file = tokio::fs::File::create(path).await?;
-    let mut stream = resp.bytes_stream();
+        return Ok(());
+    }
 
-    while let Some(chunk) = stream.next().await {
-        let chunk = chunk?;
+    let backend = backend?;
+    let path = cache.fetch_path(&url).await?;
 
-        file.write_all(&chunk).await?;
+    match args.fit {
+        Some(fit) => {
+            let mut wallpapers = wallpaper::render(&cache, &image, &monitors, fit).await?;
+            for (_, path) in &mut wallpapers {
+                *path = effects.process(&cache, &image, path).await?;
+            }
+            backend.set_monitor_wallpapers(&wallpapers)?;
+        }
+        None => backend.set_wallpaper(&effects.process(&cache, &image, &path).await?)?,
     }
+    if args.lock_screen {
+        backend.set_lock_screen(&effects.process(&cache, &image, &path).await?)?;
+    }
+
+    println!("Set the {target} to {} ({})", image.title, image.start_date);
 
     Ok(())
 }
 
-async fn download_wallpapers(dir: impl AsRef<Path>, args: QueryArgs) -> Result<(), anyhow::Error> {
-    let dir = dir.as_ref();
+/// Sets each new Bing wallpaper as it is published, until stopped.
+async fn run_daemon(args: DaemonArgs) -> Result<(), anyhow::Error> {
+    if args.systemd_unit {
+        let args = std::env::args()
+            .skip(1)
+            .filter(|arg| arg != "--systemd-unit")
+            .collect::<Vec<_>>();
+        let args = daemon::absolute_paths(&args, &["--archive", "--favorites", "--cache-dir"])?;
+        print!("{}", daemon::systemd_unit(&std::env::current_exe()?, &args));
 
-    tokio::fs::create_dir_all(dir).await?;
+        return Ok(());
+    }
 
-    let tasks = args
-        .into_builder()
-        .send()
-        .await?
-        .into_iter()
-        .filter_map(|image| {
-            let path = dir.join(&image.id);
-            if path.exists() {
-                return None;
-            }
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")),
+        )
+        .init();
 
-            Some(tokio::spawn(async move {
-                let result = download_file(image.id, path)
-                    .await
-                    .map_err(|err| anyhow!("download failed: {err}"));
+    let backend = wallpaper::detect()?;
+    if args.lock_screen && !backend.supports_lock_screen() {
+        bail!("{} cannot set the lock screen", backend.name());
+    }
 
-                if let Err(err) = result {
-                    eprintln!("{err}");
-                }
-            }))
-        });
+    let cache = Arc::new(cache::Cache::open(
+        args.cache_dir.unwrap_or_else(cache::Cache::default_dir),
+    )?);
+    let source = BingSource::new()
+        .market_option(args.market)
+        .number(1)
+        .uhd(true)
+        .cache(cache.clone());
+
+    if args.fit.is_some() && !backend.supports_monitors() {
+        bail!("{} cannot set a wallpaper per monitor", backend.name());
+    }
+
+    let mut daemon = daemon::Daemon::new(Arc::new(source), Arc::from(backend), cache)
+        .resolution(args.resolution)
+        .lock_screen(args.lock_screen)
+        .effects(Pipeline::from(args.effects));
+
+    if let Some(fit) = args.fit {
+        daemon = daemon.fit(fit, args.monitors);
+    }
+
+    if let Some(policy) = args.rotate {
+        let mut rotation = Rotation::new(policy);
+        if let Some(path) = &args.favorites {
+            let favorites = tokio::fs::read_to_string(path).await?;
+            rotation = rotation.favorites(
+                favorites
+                    .lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty() && !line.starts_with('#')),
+            );
+        }
+        if let Some(date) = args.from {
+            rotation = rotation.since(date);
+        }
+        if let Some(date) = args.to {
+            rotation = rotation.until(date);
+        }
+
+        daemon = daemon.rotate(rotation, args.interval);
+        if let Some(dir) = &args.archive {
+            daemon = daemon.archive(load_metadata(dir).await?);
+        }
+    }
+
+    daemon.run().await;
 
-    futures::future::join_all(tasks).await;
     Ok(())
 }
 
@@ -189,17 +709,84 @@ async fn update_metadata_file(
     }
 
     images.sort_by(|a, b| a.hash.cmp(&b.hash));
-    images.dedup_by(|a, b| a.hash == b.hash);
+    images.dedup_by(|a, b| {
+        // Keep what an earlier export extracted from the thumbnail, and marked reruns.
+        if a.hash == b.hash {
+            b.palette = b.palette.take().or(a.palette.take());
+            b.blurhash = b.blurhash.take().or(a.blurhash.take());
+            b.perceptual_hash = b.perceptual_hash.or(a.perceptual_hash);
+            b.rerun_of = b.rerun_of.take().or(a.rerun_of.take());
+        }
+        a.hash == b.hash
+    });
+
+    add_thumbnail_data(&mut images).await;
 
-    images.sort_by(|a, b| b.start_date.cmp(&a.start_date));
+    images.sort_by_key(|image| Reverse(image.start_date));
 
-    let mut data = serde_json::to_vec_pretty(&images)?;
+    write_metadata_file(path, &images).await
+}
+
+async fn write_metadata_file(path: &Path, images: &[Image]) -> Result<(), anyhow::Error> {
+    let mut data = serde_json::to_vec_pretty(images)?;
     data.push(b'\n');
     tokio::fs::write(path, data).await?;
 
     Ok(())
 }
 
+/// Extracts the palette, BlurHash and perceptual hash of each image that lacks them from a
+/// thumbnail, a few images at a time, and returns how many images got them.
+async fn add_thumbnail_data(images: &mut [Image]) -> usize {
+    let results = futures::stream::iter(images.iter().enumerate())
+        .filter(|(_, image)| {
+            futures::future::ready(
+                image.palette.is_none()
+                    || image.blurhash.is_none()
+                    || image.perceptual_hash.is_none(),
+            )
+        })
+        .map(|(i, image)| async move {
+            let result = async {
+                let data = image
+                    .fetch_thumbnail(palette::THUMBNAIL_WIDTH, palette::THUMBNAIL_HEIGHT)
+                    .await?;
+
+                tokio::task::spawn_blocking(move || {
+                    let thumbnail = image::load_from_memory(&data)?;
+                    let palette = Palette::extract(&thumbnail)
+                        .ok_or_else(|| anyhow!("thumbnail is empty"))?;
+
+                    let blurhash = blurhash::encode(&thumbnail, blurhash::COMPONENTS)?;
+
+                    anyhow::Ok((palette, blurhash, PerceptualHash::of_image(&thumbnail)))
+                })
+                .await?
+            };
+
+            (i, result.await)
+        })
+        .buffer_unordered(THUMBNAIL_CONCURRENCY)
+        .collect::<Vec<_>>()
+        .await;
+
+    let mut added = 0;
+    for (i, result) in results {
+        match result {
+            Ok((palette, blurhash, perceptual_hash)) => {
+                let image = &mut images[i];
+                image.palette.get_or_insert(palette);
+                image.blurhash.get_or_insert(blurhash);
+                image.perceptual_hash.get_or_insert(perceptual_hash);
+                added += 1;
+            }
+            Err(err) => eprintln!("failed to process thumbnail of {}: {err}", images[i].id),
+        }
+    }
+
+    added
+}
+
 async fn export_metadata(dir: impl AsRef<Path>) -> Result<(), anyhow::Error> {
     let dir = dir.as_ref();
 
@@ -229,3 +816,259 @@ async fn export_metadata(dir: impl AsRef<Path>) -> Result<(), anyhow::Error> {
 
     Ok(())
 }
+
+/// Loads all JSON metadata files in a directory.
+async fn load_metadata(dir: impl AsRef<Path>) -> Result<Vec<Image>, anyhow::Error> {
+    let mut images = Vec::new();
+
+    for path in metadata_files(dir).await? {
+        images.extend(read_metadata_file(&path).await?);
+    }
+
+    images.sort_by_key(|image| Reverse(image.start_date));
+
+    Ok(images)
+}
+
+/// Lists the JSON metadata files in a directory, in order of their names.
+async fn metadata_files(dir: impl AsRef<Path>) -> Result<Vec<PathBuf>, anyhow::Error> {
+    let mut paths = Vec::new();
+    let mut entries = tokio::fs::read_dir(dir).await?;
+
+    while let Some(entry) = entries.next_entry().await? {
+        let path = entry.path();
+        if path.extension().is_some_and(|ext| ext == "json") {
+            paths.push(path);
+        }
+    }
+
+    paths.sort();
+
+    Ok(paths)
+}
+
+async fn read_metadata_file(path: &Path) -> Result<Vec<Image>, anyhow::Error> {
+    let data = tokio::fs::read(path).await?;
+
+    serde_json::from_slice(&data).map_err(|err| anyhow!("{}: {err}", path.display()))
+}
+
+async fn run_archive_command(dir: &Path, command: ArchiveCommand) -> Result<(), anyhow::Error> {
+    match command {
+        ArchiveCommand::Dupes { threshold, mark } => {
+            for path in metadata_files(dir).await? {
+                let mut images = read_metadata_file(&path).await?;
+                // Metadata exported by older versions has no perceptual hashes. They are saved,
+                // so the thumbnails are only fetched once.
+                let added = add_thumbnail_data(&mut images).await;
+
+                let groups = duplicates::group(&images, threshold);
+                let name = path.file_stem().unwrap_or_default().to_string_lossy();
+                if !groups.is_empty() {
+                    println!("{name}: {} photos published more than once", groups.len());
+                    print_duplicates_table(&groups);
+                }
+
+                if mark {
+                    let reruns = duplicates::mark_reruns(&mut images, threshold);
+                    write_metadata_file(&path, &images).await?;
+                    println!("{name}: marked {reruns} reruns");
+                } else if added > 0 {
+                    write_metadata_file(&path, &images).await?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Prints each group of duplicates with the distance of each image from the first one.
+fn print_duplicates_table(groups: &[Vec<&Image>]) {
+    let mut table = Table::new();
+
+    table
+        .load_preset(UTF8_BORDERS_ONLY)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_content_arrangement(ContentArrangement::Dynamic)
+        .set_header(vec![
+            Cell::new("#").add_attribute(Attribute::Bold),
+            Cell::new("Date").add_attribute(Attribute::Bold),
+            Cell::new("Title").add_attribute(Attribute::Bold),
+            Cell::new("ID").add_attribute(Attribute::Bold),
+            Cell::new("Distance").add_attribute(Attribute::Bold),
+        ]);
+
+    for (n, group) in groups.iter().enumerate() {
+        let first = group[0].perceptual_hash;
+
+        for (i, image) in group.iter().enumerate() {
+            let distance = first
+                .zip(image.perceptual_hash)
+                .map(|(first, hash)| first.distance(hash).to_string())
+                .unwrap_or_default();
+
+            table.add_row(vec![
+                Cell::new(if i == 0 {
+                    (n + 1).to_string()
+                } else {
+                    String::new()
+                }),
+                Cell::new(image.start_date).fg(Color::DarkYellow),
+                Cell::new(&image.title).fg(Color::DarkGreen),
+                Cell::new(&image.id).fg(Color::DarkCyan),
+                Cell::new(distance),
+            ]);
+        }
+    }
+
+    println!("{table}");
+}
+
+/// Joins query arguments, quoting those that the shell received as a single quoted argument.
+fn join_query(args: Vec<String>) -> String {
+    args.into_iter()
+        .map(|arg| {
+            if arg.contains(char::is_whitespace) && !arg.contains(['"', ':']) {
+                format!("\"{arg}\"")
+            } else {
+                arg
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+async fn run_cache_command(
+    dir: Option<PathBuf>,
+    command: CacheCommand,
+) -> Result<(), anyhow::Error> {
+    let cache = cache::Cache::open(dir.unwrap_or_else(cache::Cache::default_dir))?;
+
+    match command {
+        CacheCommand::Stats { max_size } => {
+            let cache = cache.max_size(max_size.or(Some(cache::Cache::DEFAULT_MAX_SIZE)));
+            let stats = cache.stats();
+
+            println!("Directory: {}", cache.dir().display());
+            println!("Entries:   {}", stats.entries);
+            match stats.max_size {
+                Some(max_size) => println!(
+                    "Size:      {} / {}",
+                    format_size(stats.size),
+                    format_size(max_size)
+                ),
+                None => println!("Size:      {}", format_size(stats.size)),
+            }
+            if let (Some(oldest), Some(newest)) = (stats.oldest, stats.newest) {
+                println!(
+                    "Fetched:   {} to {}",
+                    oldest.date_naive(),
+                    newest.date_naive()
+                );
+            }
+        }
+        CacheCommand::Clear => {
+            let removed = cache.clear().await?;
+            println!(
+                "Removed {} files ({})",
+                removed.files,
+                format_size(removed.bytes)
+            );
+        }
+        CacheCommand::Prune { max_size, max_age } => {
+            let cache = cache.max_size(max_size.or(Some(cache::Cache::DEFAULT_MAX_SIZE)));
+            let removed = cache
+                .prune(max_age.map(|days| chrono::Duration::days(days.into())))
+                .await?;
+            println!(
+                "Removed {} files ({})",
+                removed.files,
+                format_size(removed.bytes)
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Parses a size in bytes with an optional binary suffix, e.g. `512M` or `2GiB`.
+fn parse_size(s: &str) -> Result<u64, String> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (number, suffix) = s.split_at(split);
+
+    let number = number
+        .parse::<u64>()
+        .map_err(|err| format!("invalid size: {err}"))?;
+
+    let shift = match suffix.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 0,
+        "K" | "KB" | "KIB" => 10,
+        "M" | "MB" | "MIB" => 20,
+        "G" | "GB" | "GIB" => 30,
+        _ => return Err(format!("invalid size suffix: {suffix}")),
+    };
+
+    number
+        .checked_mul(1 << shift)
+        .ok_or_else(|| "size is too large".to_owned())
+}
+
+/// Parses a resolution such as `1920x1080`.
+fn parse_resolution(s: &str) -> Result<(u32, u32), String> {
+    let (width, height) = s
+        .trim()
+        .split_once(['x', 'X', '*'])
+        .ok_or_else(|| format!("invalid resolution: {s}, expected WxH"))?;
+
+    let parse = |n: &str| match n.trim().parse::<u32>() {
+        Ok(0) | Err(_) => Err(format!("invalid resolution: {s}")),
+        Ok(n) => Ok(n),
+    };
+
+    Ok((parse(width)?, parse(height)?))
+}
+
+/// Parses an interval such as `30m`, `6h` or `1d`. A number without a unit is in minutes.
+fn parse_interval(s: &str) -> Result<chrono::Duration, String> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+
+    let number = number
+        .parse::<i64>()
+        .map_err(|_| format!("invalid interval: {s}"))?;
+    let interval = match unit.trim().to_ascii_lowercase().as_str() {
+        "s" => chrono::Duration::try_seconds(number),
+        "" | "m" => chrono::Duration::try_minutes(number),
+        "h" => chrono::Duration::try_hours(number),
+        "d" => chrono::Duration::try_days(number),
+        unit => return Err(format!("invalid interval unit: {unit}")),
+    }
+    .ok_or_else(|| format!("interval is too long: {s}"))?;
+
+    if interval < chrono::Duration::minutes(1) {
+        return Err("interval must be at least a minute".to_owned());
+    }
+
+    Ok(interval)
+}
+
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+
+    let mut size = bytes as f64;
+    let mut unit = 0;
+
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{size:.1} {}", UNITS[unit])
+    }
+}
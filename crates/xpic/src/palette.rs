@@ -0,0 +1,356 @@
+//! Colors of wallpapers.
+//!
+//! A [`Palette`] is extracted from a small thumbnail when metadata is exported, so apps can show
+//! colored placeholders before the image loads and pick accent colors that match it.
+
+use crate::Image;
+use anyhow::anyhow;
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+use serde_with::{DeserializeFromStr, SerializeDisplay};
+use std::cmp::Reverse;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+/// The number of colors in a palette.
+pub const SIZE: usize = 5;
+
+/// Size of the thumbnail fetched to extract a palette.
+pub const THUMBNAIL_WIDTH: u32 = 192;
+pub const THUMBNAIL_HEIGHT: u32 = 108;
+
+/// Images are scaled to fit this size before their colors are counted.
+const SAMPLE_SIZE: u32 = 64;
+
+/// Rounds of k-means refining the colors found by median cut.
+const ITERATIONS: usize = 4;
+
+/// Colors with less chroma are too gray to be used as an accent.
+const MIN_ACCENT_CHROMA: u8 = 48;
+
+/// An sRGB color, written as `#rrggbb`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, SerializeDisplay, DeserializeFromStr)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl Color {
+    pub const fn new(r: u8, g: u8, b: u8) -> Self {
+        Self { r, g, b }
+    }
+
+    /// Relative luminance from 0 for black to 1 for white, as defined by WCAG.
+    pub fn luminance(self) -> f32 {
+        let linear = |c: u8| {
+            let c = c as f32 / 255.0;
+            if c <= 0.04045 {
+                c / 12.92
+            } else {
+                ((c + 0.055) / 1.055).powf(2.4)
+            }
+        };
+
+        0.2126 * linear(self.r) + 0.7152 * linear(self.g) + 0.0722 * linear(self.b)
+    }
+
+    /// Whether white text is easier to read on this color than black text.
+    pub fn is_dark(self) -> bool {
+        // Where the contrast ratios with black and white are equal.
+        self.luminance() < 0.179
+    }
+
+    /// How far the color is from gray, from 0 to 255.
+    pub fn chroma(self) -> u8 {
+        let max = self.r.max(self.g).max(self.b);
+        let min = self.r.min(self.g).min(self.b);
+
+        max - min
+    }
+
+    fn distance(self, other: Color) -> u32 {
+        let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2) as u32;
+
+        d(self.r, other.r) + d(self.g, other.g) + d(self.b, other.b)
+    }
+}
+
+impl From<[u8; 3]> for Color {
+    fn from([r, g, b]: [u8; 3]) -> Self {
+        Self::new(r, g, b)
+    }
+}
+
+/// Packs the color as `0xRRGGBB`.
+impl From<Color> for u32 {
+    fn from(color: Color) -> Self {
+        u32::from_be_bytes([0, color.r, color.g, color.b])
+    }
+}
+
+impl Display for Color {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.r, self.g, self.b)
+    }
+}
+
+/// Parses `#rrggbb`, with or without the `#`.
+impl FromStr for Color {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let hex = s.trim().trim_start_matches('#');
+        if hex.len() != 6 || !hex.is_ascii() {
+            return Err(format!("invalid color: {s}, expected #rrggbb"));
+        }
+
+        let channel = |i: usize| {
+            u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| format!("invalid color: {s}"))
+        };
+
+        Ok(Self::new(channel(0)?, channel(2)?, channel(4)?))
+    }
+}
+
+/// The main colors of an image.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Palette {
+    /// The color covering most of the image.
+    pub dominant: Color,
+    /// Up to [`SIZE`] colors, from the most to the least common, starting with the dominant one.
+    pub colors: Vec<Color>,
+}
+
+impl Palette {
+    /// Extracts the main colors of `image`, or returns `None` if it has no pixels.
+    pub fn extract(image: &DynamicImage) -> Option<Self> {
+        if image.width() == 0 || image.height() == 0 {
+            return None;
+        }
+
+        let pixels = image
+            .thumbnail(SAMPLE_SIZE, SAMPLE_SIZE)
+            .to_rgb8()
+            .pixels()
+            .map(|pixel| Color::from(pixel.0))
+            .collect::<Vec<_>>();
+
+        let mut centers = median_cut(pixels.clone(), SIZE);
+        let mut counts = vec![0u64; centers.len()];
+
+        for _ in 0..ITERATIONS {
+            let mut sums = vec![[0u64; 3]; centers.len()];
+            counts.fill(0);
+
+            for &pixel in &pixels {
+                let i = nearest(&centers, pixel);
+                counts[i] += 1;
+                sums[i][0] += pixel.r as u64;
+                sums[i][1] += pixel.g as u64;
+                sums[i][2] += pixel.b as u64;
+            }
+
+            for ((center, sum), &count) in centers.iter_mut().zip(&sums).zip(&counts) {
+                if count > 0 {
+                    *center = Color::from(sum.map(|sum| (sum / count) as u8));
+                }
+            }
+        }
+
+        let mut clusters = centers
+            .into_iter()
+            .zip(counts)
+            .filter(|&(_, count)| count > 0)
+            .collect::<Vec<_>>();
+        clusters.sort_by_key(|&(_, count)| Reverse(count));
+
+        let mut colors = Vec::with_capacity(clusters.len());
+        for (color, _) in clusters {
+            if !colors.contains(&color) {
+                colors.push(color);
+            }
+        }
+
+        Some(Self {
+            dominant: colors[0],
+            colors,
+        })
+    }
+
+    /// Decodes an image and extracts its main colors.
+    pub fn from_bytes(data: &[u8]) -> anyhow::Result<Self> {
+        Self::extract(&image::load_from_memory(data)?).ok_or_else(|| anyhow!("image is empty"))
+    }
+
+    /// Returns the most common color that is colorful enough to use as an accent, or the
+    /// dominant color if all are gray.
+    pub fn accent(&self) -> Color {
+        self.colors
+            .iter()
+            .copied()
+            .find(|color| color.chroma() >= MIN_ACCENT_CHROMA)
+            .unwrap_or(self.dominant)
+    }
+}
+
+/// Fetches a small thumbnail of `image` and extracts its palette. Local images are read whole.
+pub async fn fetch(image: &Image) -> anyhow::Result<Palette> {
+    let data = image
+        .fetch_thumbnail(THUMBNAIL_WIDTH, THUMBNAIL_HEIGHT)
+        .await?;
+
+    tokio::task::spawn_blocking(move || Palette::from_bytes(&data)).await?
+}
+
+/// Splits `pixels` into up to `n` boxes of equal population along their widest channel, and
+/// returns the average color of each.
+fn median_cut(pixels: Vec<Color>, n: usize) -> Vec<Color> {
+    let mut boxes = vec![pixels];
+
+    while boxes.len() < n {
+        let widest = boxes
+            .iter()
+            .enumerate()
+            .map(|(i, pixels)| {
+                let (channel, range) = widest_channel(pixels);
+                (i, channel, range)
+            })
+            .filter(|&(_, _, range)| range > 0)
+            .max_by_key(|&(_, _, range)| range);
+        let Some((i, channel, _)) = widest else {
+            break;
+        };
+
+        let mut pixels = boxes.swap_remove(i);
+        pixels.sort_unstable_by_key(|pixel| channel_of(*pixel, channel));
+        let upper = pixels.split_off(pixels.len() / 2);
+
+        boxes.push(pixels);
+        boxes.push(upper);
+    }
+
+    boxes.iter().map(|pixels| average(pixels)).collect()
+}
+
+fn widest_channel(pixels: &[Color]) -> (usize, u8) {
+    (0..3)
+        .map(|channel| {
+            let values = pixels.iter().map(|pixel| channel_of(*pixel, channel));
+            let min = values.clone().min().unwrap_or(0);
+            let max = values.max().unwrap_or(0);
+            (channel, max - min)
+        })
+        .max_by_key(|&(_, range)| range)
+        .unwrap_or((0, 0))
+}
+
+fn channel_of(color: Color, channel: usize) -> u8 {
+    match channel {
+        0 => color.r,
+        1 => color.g,
+        _ => color.b,
+    }
+}
+
+fn average(pixels: &[Color]) -> Color {
+    let n = pixels.len().max(1) as u64;
+    let sum = pixels.iter().fold([0u64; 3], |[r, g, b], pixel| {
+        [r + pixel.r as u64, g + pixel.g as u64, b + pixel.b as u64]
+    });
+
+    Color::from(sum.map(|sum| (sum / n) as u8))
+}
+
+fn nearest(centers: &[Color], color: Color) -> usize {
+    centers
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, center)| center.distance(color))
+        .map(|(i, _)| i)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgb, RgbImage};
+
+    #[test]
+    fn test_color() {
+        let color = "#1E90ff".parse::<Color>().unwrap();
+        assert_eq!(color, Color::new(0x1e, 0x90, 0xff));
+        assert_eq!(color.to_string(), "#1e90ff");
+        assert_eq!("1e90ff".parse::<Color>().unwrap(), color);
+        assert_eq!(u32::from(color), 0x1e90ff);
+
+        for s in ["", "#fff", "#1e90fg", "#1e90ff00", "#1e90é"] {
+            assert!(s.parse::<Color>().is_err(), "{s}");
+        }
+
+        assert!(Color::new(0, 0, 0).is_dark());
+        assert!(Color::new(20, 40, 90).is_dark());
+        assert!(!Color::new(255, 255, 255).is_dark());
+        assert!(!Color::new(240, 200, 80).is_dark());
+        assert_eq!(Color::new(40, 200, 100).chroma(), 160);
+    }
+
+    #[test]
+    fn test_extract() {
+        // Three quarters blue, one quarter red.
+        let image = DynamicImage::ImageRgb8(RgbImage::from_fn(200, 100, |x, _| {
+            if x < 50 {
+                Rgb([220, 30, 30])
+            } else {
+                Rgb([20, 60, 200])
+            }
+        }));
+
+        let palette = Palette::extract(&image).unwrap();
+        assert!(palette.dominant.distance(Color::new(20, 60, 200)) < 100);
+        assert_eq!(palette.colors[0], palette.dominant);
+        assert!(palette.colors.len() <= SIZE);
+        assert!(
+            palette
+                .colors
+                .iter()
+                .any(|color| color.distance(Color::new(220, 30, 30)) < 100)
+        );
+
+        let gray = DynamicImage::ImageRgb8(RgbImage::from_pixel(10, 10, Rgb([128, 128, 128])));
+        let palette = Palette::extract(&gray).unwrap();
+        assert_eq!(palette.colors, [Color::new(128, 128, 128)]);
+        assert_eq!(palette.accent(), palette.dominant);
+
+        assert!(Palette::extract(&DynamicImage::new_rgb8(0, 0)).is_none());
+    }
+
+    #[test]
+    fn test_accent() {
+        let palette = Palette {
+            dominant: Color::new(200, 200, 210),
+            colors: vec![
+                Color::new(200, 200, 210),
+                Color::new(30, 30, 30),
+                Color::new(230, 120, 20),
+            ],
+        };
+
+        assert_eq!(palette.accent(), Color::new(230, 120, 20));
+    }
+
+    #[test]
+    fn test_serde() {
+        let palette = Palette {
+            dominant: Color::new(0x1e, 0x90, 0xff),
+            colors: vec![Color::new(0x1e, 0x90, 0xff), Color::new(0, 0, 0)],
+        };
+
+        let json = serde_json::to_string(&palette).unwrap();
+        assert_eq!(
+            json,
+            r##"{"dominant":"#1e90ff","colors":["#1e90ff","#000000"]}"##
+        );
+        assert_eq!(serde_json::from_str::<Palette>(&json).unwrap(), palette);
+    }
+}
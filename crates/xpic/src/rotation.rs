@@ -0,0 +1,366 @@
+//! Choosing which wallpaper to show next from an archive of images.
+//!
+//! A [`Rotation`] cycles through the images it is given according to a [`Policy`], remembering
+//! what it has shown so that it does not repeat itself. It works on any list of images, such as
+//! the Bing archive merged with [`merge`](crate::merge).
+
+use crate::{ID, Image};
+use chrono::{Datelike, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::borrow::Borrow;
+use std::collections::HashSet;
+use strum::{Display, EnumString};
+
+/// How many times more likely favorites are to be chosen by [`Policy::Favorites`].
+pub const DEFAULT_FAVORITE_WEIGHT: u32 = 4;
+
+/// How the next wallpaper is chosen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Display, EnumString)]
+#[serde(rename_all = "kebab-case")]
+#[strum(serialize_all = "kebab-case", ascii_case_insensitive)]
+pub enum Policy {
+    /// Goes back in time one image after another, starting over at the newest.
+    Sequential,
+    /// Shows every image once in random order before starting over.
+    Shuffle,
+    /// Picks at random, favoring favorites.
+    Favorites,
+    /// Shows images from the same day in previous years, or the newest image if there are none.
+    OnThisDay,
+}
+
+/// Chooses wallpapers according to a [`Policy`].
+#[derive(Debug, Clone)]
+pub struct Rotation {
+    policy: Policy,
+    favorites: HashSet<String>,
+    favorite_weight: u32,
+    /// First and last dates of the images to choose from.
+    since: Option<NaiveDate>,
+    until: Option<NaiveDate>,
+    rng: fastrand::Rng,
+    /// ID of the image chosen last.
+    last: Option<String>,
+    /// IDs of the images shown in the current shuffle.
+    seen: HashSet<String>,
+}
+
+impl Rotation {
+    pub fn new(policy: Policy) -> Self {
+        Self {
+            policy,
+            favorites: HashSet::new(),
+            favorite_weight: DEFAULT_FAVORITE_WEIGHT,
+            since: None,
+            until: None,
+            rng: fastrand::Rng::new(),
+            last: None,
+            seen: HashSet::new(),
+        }
+    }
+
+    pub fn policy(&self) -> Policy {
+        self.policy
+    }
+
+    /// Sets the favorite images, by ID (`OHR.Name_EN-US123_UHD.jpg`) or by name (`Name`), which
+    /// matches the image in every market and resolution.
+    pub fn favorites(mut self, favorites: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.favorites = favorites.into_iter().map(Into::into).collect();
+
+        self
+    }
+
+    pub fn favorite_weight(mut self, weight: u32) -> Self {
+        self.favorite_weight = weight.max(1);
+
+        self
+    }
+
+    /// Chooses only from images published on or after `date`.
+    pub fn since(mut self, date: NaiveDate) -> Self {
+        self.since = Some(date);
+
+        self
+    }
+
+    /// Chooses only from images published on or before `date`.
+    pub fn until(mut self, date: NaiveDate) -> Self {
+        self.until = Some(date);
+
+        self
+    }
+
+    /// Makes random choices repeatable.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.rng = fastrand::Rng::with_seed(seed);
+
+        self
+    }
+
+    pub fn is_favorite(&self, image: &Image) -> bool {
+        self.favorites.contains(&image.id)
+            || ID::parse(&image.id).is_some_and(|id| self.favorites.contains(&id.name))
+    }
+
+    /// Returns whether `image` was published within the dates to choose from.
+    pub fn contains(&self, image: &Image) -> bool {
+        self.since.is_none_or(|since| image.start_date >= since)
+            && self.until.is_none_or(|until| image.start_date <= until)
+    }
+
+    /// Returns the next image to show on `today`, from `images` sorted newest first.
+    pub fn next<'a, T: Borrow<Image>>(
+        &mut self,
+        images: &'a [T],
+        today: NaiveDate,
+    ) -> Option<&'a T> {
+        let images = images
+            .iter()
+            .filter(|image| self.contains(as_image(*image)))
+            .collect::<Vec<_>>();
+
+        let image = match self.policy {
+            Policy::Sequential => self.sequential(images),
+            Policy::Shuffle => self.shuffle(&images),
+            Policy::Favorites => self.favorite(&images),
+            Policy::OnThisDay => {
+                let on_this_day = images
+                    .iter()
+                    .copied()
+                    .filter(|image| {
+                        let date = as_image(*image).start_date;
+                        date.month() == today.month()
+                            && date.day() == today.day()
+                            && date.year() < today.year()
+                    })
+                    .collect::<Vec<_>>();
+
+                if on_this_day.is_empty() {
+                    images.first().copied()
+                } else {
+                    self.sequential(on_this_day)
+                }
+            }
+        }?;
+
+        self.last = Some(as_image(image).id.clone());
+
+        Some(image)
+    }
+
+    /// Returns the image after the last one, or the first.
+    fn sequential<'a, T: Borrow<Image>>(&self, images: Vec<&'a T>) -> Option<&'a T> {
+        let next = self
+            .position(&images)
+            .map_or(0, |position| (position + 1) % images.len());
+
+        images.get(next).copied()
+    }
+
+    fn shuffle<'a, T: Borrow<Image>>(&mut self, images: &[&'a T]) -> Option<&'a T> {
+        let mut unseen = self.unseen(images);
+
+        if unseen.is_empty() {
+            self.seen.clear();
+            unseen = self.unseen(images);
+        }
+        if unseen.is_empty() {
+            return None;
+        }
+
+        let image = unseen[self.rng.usize(..unseen.len())];
+        self.seen.insert(as_image(image).id.clone());
+
+        Some(image)
+    }
+
+    /// Returns the images not shown in the current shuffle, except the last one unless it is the
+    /// only image.
+    fn unseen<'a, T: Borrow<Image>>(&self, images: &[&'a T]) -> Vec<&'a T> {
+        let candidates = images
+            .iter()
+            .copied()
+            .filter(|image| !self.seen.contains(&as_image(*image).id))
+            .collect::<Vec<_>>();
+
+        self.not_last(candidates)
+    }
+
+    fn favorite<'a, T: Borrow<Image>>(&mut self, images: &[&'a T]) -> Option<&'a T> {
+        let candidates = self.not_last(images.to_vec());
+        let weights = candidates
+            .iter()
+            .map(|image| {
+                if self.is_favorite(as_image(*image)) {
+                    self.favorite_weight
+                } else {
+                    1
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let total = weights.iter().sum::<u32>();
+        if total == 0 {
+            return None;
+        }
+
+        let mut n = self.rng.u32(..total);
+        for (image, weight) in candidates.into_iter().zip(weights) {
+            if n < weight {
+                return Some(image);
+            }
+            n -= weight;
+        }
+
+        None
+    }
+
+    fn not_last<'a, T: Borrow<Image>>(&self, mut images: Vec<&'a T>) -> Vec<&'a T> {
+        if images.len() > 1
+            && let Some(position) = self.position(&images)
+        {
+            images.remove(position);
+        }
+
+        images
+    }
+
+    fn position<T: Borrow<Image>>(&self, images: &[&T]) -> Option<usize> {
+        let last = self.last.as_deref()?;
+
+        images.iter().position(|image| as_image(*image).id == last)
+    }
+}
+
+fn as_image<T: Borrow<Image>>(image: &T) -> &Image {
+    image.borrow()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::ImageBuilder;
+    use chrono::NaiveTime;
+    use std::collections::HashMap;
+
+    fn image(name: &str, date: &str) -> Image {
+        let start = date.parse::<NaiveDate>().unwrap();
+
+        ImageBuilder::new(format!("OHR.{name}_EN-US123_UHD.jpg"))
+            .start(start.and_time(NaiveTime::MIN).and_utc())
+            .title(name)
+            .build()
+    }
+
+    fn images() -> Vec<Image> {
+        vec![
+            image("D", "2026-03-04"),
+            image("C", "2025-03-04"),
+            image("B", "2025-01-01"),
+            image("A", "2024-03-04"),
+        ]
+    }
+
+    fn titles(rotation: &mut Rotation, images: &[Image], today: &str, n: usize) -> Vec<String> {
+        let today = today.parse().unwrap();
+
+        (0..n)
+            .map(|_| rotation.next(images, today).unwrap().title.clone())
+            .collect()
+    }
+
+    #[test]
+    fn test_policy() {
+        assert_eq!("on-this-day".parse::<Policy>().unwrap(), Policy::OnThisDay);
+        assert_eq!("Shuffle".parse::<Policy>().unwrap(), Policy::Shuffle);
+        assert_eq!(Policy::OnThisDay.to_string(), "on-this-day");
+    }
+
+    #[test]
+    fn test_sequential() {
+        let mut rotation = Rotation::new(Policy::Sequential);
+
+        assert_eq!(
+            titles(&mut rotation, &images(), "2026-03-04", 6),
+            ["D", "C", "B", "A", "D", "C"]
+        );
+    }
+
+    #[test]
+    fn test_shuffle_without_repeats() {
+        let images = images();
+        let mut rotation = Rotation::new(Policy::Shuffle).seed(7);
+
+        for _ in 0..10 {
+            let mut cycle = titles(&mut rotation, &images, "2026-03-04", 4);
+            cycle.sort();
+            assert_eq!(cycle, ["A", "B", "C", "D"]);
+        }
+
+        let titles = titles(&mut rotation, &images, "2026-03-04", 100);
+        assert!(titles.windows(2).all(|pair| pair[0] != pair[1]));
+    }
+
+    #[test]
+    fn test_favorites() {
+        let images = images();
+        let mut rotation = Rotation::new(Policy::Favorites)
+            .favorites(["B", "OHR.C_EN-US123_UHD.jpg"])
+            .seed(1);
+
+        assert!(rotation.is_favorite(&images[2]));
+        assert!(!rotation.is_favorite(&images[0]));
+
+        let mut counts = HashMap::new();
+        let titles = titles(&mut rotation, &images, "2026-03-04", 1000);
+        for title in &titles {
+            *counts.entry(title.as_str()).or_insert(0) += 1;
+        }
+
+        assert!(counts["B"] > counts["A"] * 2);
+        assert!(counts["C"] > counts["D"] * 2);
+        assert!(titles.windows(2).all(|pair| pair[0] != pair[1]));
+    }
+
+    #[test]
+    fn test_on_this_day() {
+        let images = images();
+        let mut rotation = Rotation::new(Policy::OnThisDay);
+
+        assert_eq!(
+            titles(&mut rotation, &images, "2026-03-04", 3),
+            ["C", "A", "C"]
+        );
+        // Nothing from this day, so the newest image.
+        assert_eq!(titles(&mut rotation, &images, "2026-06-01", 1), ["D"]);
+    }
+
+    #[test]
+    fn test_since_until() {
+        let mut rotation = Rotation::new(Policy::Sequential)
+            .since("2025-01-01".parse().unwrap())
+            .until("2025-12-31".parse().unwrap());
+
+        assert!(!rotation.contains(&images()[0]));
+        assert_eq!(
+            titles(&mut rotation, &images(), "2026-03-04", 3),
+            ["C", "B", "C"]
+        );
+    }
+
+    #[test]
+    fn test_empty() {
+        let images: Vec<Image> = Vec::new();
+
+        for policy in [
+            Policy::Sequential,
+            Policy::Shuffle,
+            Policy::Favorites,
+            Policy::OnThisDay,
+        ] {
+            let mut rotation = Rotation::new(policy);
+            assert!(rotation.next(&images, NaiveDate::MIN).is_none());
+        }
+    }
+}
@@ -0,0 +1,592 @@
+//! Full-text search over image metadata.
+//!
+//! Queries are free text with optional filters and quoted phrases, e.g.
+//! `market:ja-JP year:2025 "mountain lake"`. Matching is case-insensitive, ignores diacritics
+//! and splits CJK text into bigrams so that queries work without word boundaries.
+
+use crate::bing::Market;
+use crate::{Copyright, ID, Image};
+use chrono::Datelike;
+use std::borrow::Borrow;
+use std::ops::RangeInclusive;
+use unicode_normalization::UnicodeNormalization;
+
+/// Relative weight of each searchable field.
+const TITLE_WEIGHT: f32 = 3.0;
+const DESCRIPTION_WEIGHT: f32 = 2.0;
+const CREDIT_WEIGHT: f32 = 1.0;
+
+/// Score multiplier for a word that only matches as a prefix.
+const PREFIX_MATCH: f32 = 0.6;
+/// Score multiplier for a quoted phrase, which is more specific than a single word.
+const PHRASE_MATCH: f32 = 2.0;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Term {
+    /// A single word, or a bigram of CJK text.
+    Word(String),
+    /// A quoted phrase that must appear as-is, with whitespace collapsed.
+    Phrase(String),
+}
+
+/// Parsed search query.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Query {
+    /// Folded terms that must all match.
+    pub terms: Vec<Term>,
+    /// Restricts results to these markets. Empty means any market.
+    pub markets: Vec<Market>,
+    /// Restricts results to a range of years by start date.
+    pub years: Option<RangeInclusive<i32>>,
+}
+
+impl Query {
+    /// Parses a query string.
+    ///
+    /// Supported filters are `market:<code>` (repeatable) and `year:<year>` or
+    /// `year:<from>..<to>`. Filters with invalid values are searched as plain text.
+    pub fn parse(s: impl AsRef<str>) -> Self {
+        let mut query = Query::default();
+
+        for (part, quoted) in split_query(s.as_ref()) {
+            if quoted {
+                let phrase = collapse_whitespace(&fold(&part));
+                if !tokenize(&phrase).is_empty() {
+                    query.terms.push(Term::Phrase(phrase));
+                }
+                continue;
+            }
+
+            if let Some((key, value)) = part.split_once(':') {
+                match key.to_ascii_lowercase().as_str() {
+                    "market" | "mkt" => {
+                        if let Ok(market) = value.parse::<Market>() {
+                            query.markets.push(market);
+                            continue;
+                        }
+                    }
+                    "year" => {
+                        if let Some(years) = parse_years(value) {
+                            query.years = Some(years);
+                            continue;
+                        }
+                    }
+                    _ => {}
+                }
+            }
+
+            query
+                .terms
+                .extend(tokenize(&fold(&part)).into_iter().map(Term::Word));
+        }
+
+        query
+    }
+
+    /// Returns `true` if the query neither searches nor filters anything.
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty() && self.markets.is_empty() && self.years.is_none()
+    }
+}
+
+fn parse_years(s: &str) -> Option<RangeInclusive<i32>> {
+    if let Some((from, to)) = s.split_once("..") {
+        Some(from.parse().ok()?..=to.parse().ok()?)
+    } else {
+        let year = s.parse().ok()?;
+        Some(year..=year)
+    }
+}
+
+/// Splits a query on whitespace, keeping double-quoted parts together.
+///
+/// Returns each part along with whether it was quoted.
+fn split_query(s: &str) -> Vec<(String, bool)> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+
+    for c in s.chars() {
+        match c {
+            '"' | '“' | '”' | '「' | '」' => {
+                if !current.trim().is_empty() {
+                    parts.push((current.trim().to_owned(), quoted));
+                }
+                current.clear();
+                quoted = !quoted;
+            }
+            c if c.is_whitespace() && !quoted => {
+                if !current.is_empty() {
+                    parts.push((std::mem::take(&mut current), false));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+
+    if !current.trim().is_empty() {
+        parts.push((current.trim().to_owned(), quoted));
+    }
+
+    parts
+}
+
+/// Lowercases `s` and strips Latin diacritics, e.g. `Grömitz` becomes `gromitz`.
+///
+/// Full-width forms are normalized to their ASCII equivalents. Other combining marks, such as
+/// Japanese voicing marks, are preserved.
+pub fn fold(s: &str) -> String {
+    let mut folded = String::with_capacity(s.len());
+
+    for c in s
+        .nfkd()
+        .filter(|c| !matches!(c, '\u{0300}'..='\u{036F}'))
+        .nfc()
+        .flat_map(char::to_lowercase)
+    {
+        match c {
+            'ß' => folded.push_str("ss"),
+            'æ' => folded.push_str("ae"),
+            'œ' => folded.push_str("oe"),
+            'ø' => folded.push('o'),
+            'ł' => folded.push('l'),
+            'đ' => folded.push('d'),
+            c => folded.push(c),
+        }
+    }
+
+    folded
+}
+
+fn collapse_whitespace(s: &str) -> String {
+    s.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Returns `true` for Chinese, Japanese and Korean characters, which are not separated by spaces.
+fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{3040}'..='\u{30FF}'
+        | '\u{3400}'..='\u{4DBF}'
+        | '\u{4E00}'..='\u{9FFF}'
+        | '\u{AC00}'..='\u{D7AF}'
+        | '\u{F900}'..='\u{FAFF}'
+        | '\u{20000}'..='\u{2FFFF}'
+    )
+}
+
+/// Splits folded text into tokens.
+///
+/// Alphanumeric runs become words, and runs of CJK characters become overlapping bigrams, or a
+/// single character if the run is one character long.
+pub fn tokenize(s: &str) -> Vec<String> {
+    fn flush_cjk(run: &mut Vec<char>, tokens: &mut Vec<String>) {
+        match run.len() {
+            0 => {}
+            1 => tokens.push(run[0].to_string()),
+            _ => tokens.extend(run.windows(2).map(|pair| pair.iter().collect())),
+        }
+        run.clear();
+    }
+
+    let mut tokens = Vec::new();
+    let mut word = String::new();
+    let mut cjk = Vec::new();
+
+    for c in s.chars() {
+        if is_cjk(c) {
+            if !word.is_empty() {
+                tokens.push(std::mem::take(&mut word));
+            }
+            cjk.push(c);
+        } else if c.is_alphanumeric() {
+            flush_cjk(&mut cjk, &mut tokens);
+            word.push(c);
+        } else {
+            flush_cjk(&mut cjk, &mut tokens);
+            if !word.is_empty() {
+                tokens.push(std::mem::take(&mut word));
+            }
+        }
+    }
+
+    flush_cjk(&mut cjk, &mut tokens);
+    if !word.is_empty() {
+        tokens.push(word);
+    }
+
+    tokens
+}
+
+struct Field {
+    weight: f32,
+    /// Folded text with whitespace collapsed, for phrase matching.
+    text: String,
+    tokens: Vec<String>,
+}
+
+impl Field {
+    fn new(weight: f32, text: &str) -> Self {
+        let text = collapse_whitespace(&fold(text));
+
+        Self {
+            weight,
+            tokens: tokenize(&text),
+            text,
+        }
+    }
+
+    /// Returns how well `term` matches this field, from `0.0` for no match to `1.0`.
+    fn score(&self, term: &Term) -> f32 {
+        match term {
+            Term::Phrase(phrase) => {
+                if self.text.contains(phrase.as_str()) {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            Term::Word(word) if word.chars().count() == 1 && word.chars().all(is_cjk) => {
+                if self
+                    .tokens
+                    .iter()
+                    .any(|token| token.contains(word.as_str()))
+                {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            Term::Word(word) => self
+                .tokens
+                .iter()
+                .map(|token| {
+                    if token == word {
+                        1.0
+                    } else if token.starts_with(word.as_str()) {
+                        PREFIX_MATCH
+                    } else {
+                        0.0
+                    }
+                })
+                .fold(0.0, f32::max),
+        }
+    }
+}
+
+struct Document {
+    market: Option<Market>,
+    year: i32,
+    fields: [Field; 3],
+}
+
+impl Document {
+    fn new(image: &Image) -> Self {
+        let copyright = image
+            .copyright_parsed
+            .clone()
+            .or_else(|| Copyright::parse(&image.copyright))
+            .unwrap_or_default();
+
+        Self {
+            market: image
+                .id_parsed
+                .clone()
+                .or_else(|| ID::parse(&image.id))
+                .and_then(|id| id.market),
+            year: image.start_date.year(),
+            fields: [
+                Field::new(TITLE_WEIGHT, &image.title),
+                Field::new(DESCRIPTION_WEIGHT, &copyright.description),
+                Field::new(CREDIT_WEIGHT, &copyright.copyright),
+            ],
+        }
+    }
+
+    fn score(&self, query: &Query) -> Option<f32> {
+        if !query.markets.is_empty()
+            && !self
+                .market
+                .is_some_and(|market| query.markets.contains(&market))
+        {
+            return None;
+        }
+
+        if let Some(years) = &query.years
+            && !years.contains(&self.year)
+        {
+            return None;
+        }
+
+        let mut total = 0.0;
+
+        for term in &query.terms {
+            let multiplier = match term {
+                Term::Phrase(_) => PHRASE_MATCH,
+                Term::Word(_) => 1.0,
+            };
+
+            let best = self
+                .fields
+                .iter()
+                .map(|field| field.weight * field.score(term))
+                .fold(0.0, f32::max);
+
+            if best == 0.0 {
+                return None;
+            }
+
+            total += best * multiplier;
+        }
+
+        Some(total)
+    }
+}
+
+/// A search result.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Hit {
+    /// Position of the image in the indexed list.
+    pub index: usize,
+    pub score: f32,
+}
+
+/// Pre-processed metadata for a list of images, for repeated searches.
+pub struct Index {
+    documents: Vec<Document>,
+}
+
+impl Index {
+    pub fn new<T: Borrow<Image>>(images: &[T]) -> Self {
+        Self {
+            documents: images
+                .iter()
+                .map(|image| Document::new(image.borrow()))
+                .collect(),
+        }
+    }
+
+    /// Returns matching images, best first.
+    ///
+    /// Results with equal scores keep their order in the indexed list.
+    pub fn search(&self, query: &Query) -> Vec<Hit> {
+        let mut hits = self
+            .documents
+            .iter()
+            .enumerate()
+            .filter_map(|(index, document)| {
+                Some(Hit {
+                    index,
+                    score: document.score(query)?,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        hits.sort_by(|a, b| b.score.total_cmp(&a.score));
+        hits
+    }
+}
+
+/// Searches `images` for `query`, returning matches best first.
+///
+/// An empty query returns all images in their original order.
+pub fn search<T: Borrow<Image> + Clone>(images: &[T], query: impl AsRef<str>) -> Vec<T> {
+    let query = Query::parse(query);
+    if query.is_empty() {
+        return images.to_vec();
+    }
+
+    Index::new(images)
+        .search(&query)
+        .into_iter()
+        .map(|hit| images[hit.index].clone())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::Path;
+
+    fn image(id: &str, title: &str, copyright: &str, start_date: &str) -> Image {
+        serde_json::from_value(serde_json::json!({
+            "url": format!("https://www.bing.com/th?id={id}"),
+            "start_date": start_date,
+            "full_start_date": format!("{start_date}0700"),
+            "end_date": start_date,
+            "id": id,
+            "copyright": copyright,
+            "copyright_link": "https://www.bing.com/",
+            "title": title,
+            "quiz_link": "https://www.bing.com/",
+            "wallpaper": true,
+            "hash": id,
+        }))
+        .unwrap()
+    }
+
+    fn images() -> Vec<Image> {
+        vec![
+            image(
+                "OHR.JulierPass_DE-DE2643379571_UHD.jpg",
+                "Serpentinen im Engadin",
+                "Serpentinenstraße am Julierpass, Schweiz (© Westend61/Getty Images)",
+                "20250821",
+            ),
+            image(
+                "OHR.Groemitz_DE-DE1234567890_UHD.jpg",
+                "Strandkörbe",
+                "Strandkörbe am Ostseestrand von Grömitz (© Sabine Lubenow/Alamy)",
+                "20240701",
+            ),
+            image(
+                "OHR.MountFuji_JA-JP1234567890_UHD.jpg",
+                "富士山と桜",
+                "富士山と桜, 山梨県 (© Mountain Light/Getty Images)",
+                "20250401",
+            ),
+            image(
+                "OHR.Huangshan_ZH-CN1234567890_UHD.jpg",
+                "黄山云海",
+                "黄山的云海，安徽省，中国 (© Mountain Gallery/Getty Images)",
+                "20250302",
+            ),
+        ]
+    }
+
+    fn titles(images: &[Image]) -> Vec<&str> {
+        images.iter().map(|image| image.title.as_str()).collect()
+    }
+
+    #[test]
+    fn test_fold() {
+        assert_eq!(fold("Grömitz"), "gromitz");
+        assert_eq!(fold("Cathédrales"), "cathedrales");
+        assert_eq!(fold("Serpentinenstraße"), "serpentinenstrasse");
+        assert_eq!(fold("ＡＢＣ"), "abc");
+        // Japanese voicing marks are not diacritics.
+        assert_eq!(fold("ガ"), "ガ");
+    }
+
+    #[test]
+    fn test_tokenize() {
+        assert_eq!(tokenize("julier pass, 1938"), ["julier", "pass", "1938"]);
+        assert_eq!(tokenize("黄山云海"), ["黄山", "山云", "云海"]);
+        assert_eq!(
+            tokenize("富士山と桜 mt"),
+            ["富士", "士山", "山と", "と桜", "mt"]
+        );
+        assert_eq!(tokenize("桜"), ["桜"]);
+    }
+
+    #[test]
+    fn test_parse_query() {
+        let query = Query::parse(r#"market:ja-JP year:2025 "Mountain  Light" fuji"#);
+
+        assert_eq!(query.markets, [Market::JA_JP]);
+        assert_eq!(query.years, Some(2025..=2025));
+        assert_eq!(
+            query.terms,
+            [
+                Term::Phrase("mountain light".to_string()),
+                Term::Word("fuji".to_string())
+            ]
+        );
+
+        let query = Query::parse("year:2020..2024 market:nowhere");
+        assert_eq!(query.years, Some(2020..=2024));
+        assert_eq!(
+            query.terms,
+            [
+                Term::Word("market".to_string()),
+                Term::Word("nowhere".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_search() {
+        let images = images();
+
+        assert_eq!(titles(&search(&images, "")), titles(&images));
+        assert_eq!(titles(&search(&images, "gromitz")), ["Strandkörbe"]);
+        assert_eq!(
+            titles(&search(&images, "JULIERPASS")),
+            ["Serpentinen im Engadin"]
+        );
+        assert_eq!(titles(&search(&images, "云海")), ["黄山云海"]);
+        assert_eq!(titles(&search(&images, "桜")), ["富士山と桜"]);
+        assert_eq!(titles(&search(&images, "market:zh-CN")), ["黄山云海"]);
+        assert_eq!(
+            titles(&search(&images, "year:2025 getty")),
+            ["Serpentinen im Engadin", "富士山と桜", "黄山云海"]
+        );
+        assert_eq!(
+            titles(&search(&images, r#""mountain light""#)),
+            ["富士山と桜"]
+        );
+        assert!(search(&images, "julier alamy").is_empty());
+    }
+
+    #[test]
+    fn test_ranking() {
+        let images = vec![
+            image(
+                "OHR.A_EN-US1_UHD.jpg",
+                "Lake view",
+                "A lake (© Mountain Light/Getty Images)",
+                "20250101",
+            ),
+            image(
+                "OHR.B_EN-US2_UHD.jpg",
+                "Lake view",
+                "Mountain lake, Canada (© Jane Doe/Alamy)",
+                "20250102",
+            ),
+            image(
+                "OHR.C_EN-US3_UHD.jpg",
+                "Mountain lake",
+                "A lake (© Jane Doe/Alamy)",
+                "20250103",
+            ),
+        ];
+
+        // Title matches rank above description matches, which rank above credits.
+        let hits = search(&images, "mountain");
+        assert_eq!(
+            hits.iter()
+                .map(|image| image.id.as_str())
+                .collect::<Vec<_>>(),
+            [
+                "OHR.C_EN-US3_UHD.jpg",
+                "OHR.B_EN-US2_UHD.jpg",
+                "OHR.A_EN-US1_UHD.jpg"
+            ]
+        );
+
+        // Exact words rank above prefixes.
+        let images = vec![
+            image("OHR.A_EN-US1_UHD.jpg", "Mountains", "", "20250101"),
+            image("OHR.B_EN-US2_UHD.jpg", "Mountain", "", "20250102"),
+        ];
+        assert_eq!(
+            titles(&search(&images, "mountain")),
+            ["Mountain", "Mountains"]
+        );
+    }
+
+    #[test]
+    fn test_search_corpus() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../data");
+
+        let mut images = Vec::new();
+        for entry in fs::read_dir(dir).unwrap() {
+            let data = fs::read(entry.unwrap().path()).unwrap();
+            images.extend(serde_json::from_slice::<Vec<Image>>(&data).unwrap());
+        }
+
+        let hits = search(&images, "market:de-DE gromitz");
+        assert!(!hits.is_empty());
+        assert!(hits.iter().all(|image| image.id.contains("_DE-DE")));
+    }
+}
@@ -0,0 +1,97 @@
+use super::WallpaperSource;
+use crate::bing::{Query, QueryParams};
+#[cfg(feature = "cache")]
+use crate::cache::Cache;
+use crate::{Client, Image};
+use futures::future::BoxFuture;
+use std::cmp::Reverse;
+use std::sync::Arc;
+
+/// Bing wallpapers of the day.
+pub struct BingSource {
+    client: Arc<Client>,
+    #[cfg(feature = "cache")]
+    cache: Option<Arc<Cache>>,
+    query: Query,
+}
+
+impl Default for BingSource {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BingSource {
+    pub fn new() -> Self {
+        Self {
+            client: Arc::new(Client::default()),
+            #[cfg(feature = "cache")]
+            cache: None,
+            query: Query::new(),
+        }
+    }
+
+    pub fn client(mut self, client: Arc<Client>) -> Self {
+        self.client = client;
+
+        self
+    }
+
+    /// Fetches images through `cache`.
+    #[cfg(feature = "cache")]
+    pub fn cache(mut self, cache: Arc<Cache>) -> Self {
+        self.cache = Some(cache);
+
+        self
+    }
+}
+
+impl QueryParams for BingSource {
+    fn query(&self) -> &Query {
+        &self.query
+    }
+
+    fn query_mut(&mut self) -> &mut Query {
+        &mut self.query
+    }
+}
+
+impl WallpaperSource for BingSource {
+    fn name(&self) -> &'static str {
+        "bing"
+    }
+
+    fn list(&self) -> BoxFuture<'_, anyhow::Result<Vec<Image>>> {
+        Box::pin(async move {
+            let mut images = self
+                .client
+                .bing()
+                .hp_image_archive(&self.query)
+                .await?
+                .into_iter()
+                .filter_map(|raw| Image::parse(raw).ok())
+                .collect::<Vec<_>>();
+
+            images.sort_by_key(|image| Reverse(image.full_start_date));
+
+            Ok(images)
+        })
+    }
+
+    fn fetch<'a>(&'a self, image: &'a Image) -> BoxFuture<'a, anyhow::Result<Vec<u8>>> {
+        Box::pin(async move {
+            #[cfg(feature = "cache")]
+            if let Some(cache) = &self.cache {
+                return cache.fetch(image.download_url()?).await;
+            }
+
+            let resp = if image.has_thumbnails() {
+                self.client.fetch_image(&image.id).await?
+            } else {
+                reqwest::get(image.url.clone()).await?
+            };
+
+            Ok(resp.error_for_status()?.bytes().await?.to_vec())
+        })
+    }
+}
@@ -0,0 +1,505 @@
+use super::WallpaperSource;
+use crate::{Copyright, Image};
+use anyhow::anyhow;
+use chrono::{DateTime, Days, NaiveDate, NaiveDateTime, Utc};
+use exif::{Context, Exif, In, Tag, Value};
+use futures::future::BoxFuture;
+use regex::Regex;
+use sha2::{Digest, Sha256};
+use std::cmp::Reverse;
+use std::io::{Cursor, Read};
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+use std::{fs, io};
+use tracing::warn;
+use url::Url;
+
+/// File extensions of the images listed by [`LocalSource`].
+const EXTENSIONS: &[&str] = &[
+    "jpg", "jpeg", "png", "webp", "avif", "tif", "tiff", "bmp", "gif",
+];
+
+/// Bytes read from the start of an image for its metadata, which file formats keep in the header.
+const HEADER_SIZE: u64 = 256 * 1024;
+
+/// `XPTitle`, the title Windows Explorer shows and edits.
+const XP_TITLE: Tag = Tag(Context::Tiff, 0x9c9b);
+
+/// Images in a local folder, such as a shared folder of curated wallpapers.
+///
+/// Titles, descriptions and credits are read from embedded XMP or EXIF metadata, with XMP taking
+/// precedence. Images without a title are named after their file, and images without a date are
+/// dated by when they were last modified. Every image is assumed to be a wallpaper.
+#[derive(Debug, Clone)]
+pub struct LocalSource {
+    dir: PathBuf,
+}
+
+impl LocalSource {
+    /// Lists the images directly in `dir`.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Lists the images in the directory, skipping those that can't be read.
+    fn list_blocking(&self) -> anyhow::Result<Vec<Image>> {
+        let mut images = Vec::new();
+
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if !path.is_file() || !is_image(&path) {
+                continue;
+            }
+
+            match to_image(&path) {
+                Ok(image) => images.push(image),
+                Err(err) => warn!(path = %path.display(), "skipping image: {err}"),
+            }
+        }
+
+        images.sort_by_key(|image| Reverse(image.full_start_date));
+
+        Ok(images)
+    }
+}
+
+impl WallpaperSource for LocalSource {
+    fn name(&self) -> &'static str {
+        "local"
+    }
+
+    fn list(&self) -> BoxFuture<'_, anyhow::Result<Vec<Image>>> {
+        let this = self.clone();
+
+        Box::pin(async move { tokio::task::spawn_blocking(move || this.list_blocking()).await? })
+    }
+
+    fn fetch<'a>(&'a self, image: &'a Image) -> BoxFuture<'a, anyhow::Result<Vec<u8>>> {
+        Box::pin(async move {
+            let path = image
+                .url
+                .to_file_path()
+                .map_err(|_| anyhow!("not a local image: {}", image.url))?;
+
+            Ok(tokio::fs::read(path).await?)
+        })
+    }
+}
+
+fn is_image(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+}
+
+/// Describes the image at `path` as an [`Image`].
+fn to_image(path: &Path) -> io::Result<Image> {
+    let path = path.canonicalize()?;
+    let mut file = fs::File::open(&path)?;
+
+    let mut data = Vec::new();
+    file.by_ref().take(HEADER_SIZE).read_to_end(&mut data)?;
+
+    // The rest of the file is only hashed, not kept in memory.
+    let mut hasher = Sha256::new();
+    hasher.update(&data);
+    let mut buf = vec![0; 64 * 1024];
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+
+    let url = Url::from_file_path(&path)
+        .map_err(|_| io::Error::other(format!("invalid path: {}", path.display())))?;
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    let mut metadata = Metadata::from_xmp(&data);
+    if let Ok(exif) = exif::Reader::new().read_from_container(&mut Cursor::new(&data)) {
+        metadata = metadata.or(Metadata::from_exif(&exif));
+    }
+
+    let title = metadata.title.unwrap_or_else(|| {
+        path.file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    });
+
+    let credit = metadata
+        .copyright
+        .or(metadata.artist.map(|artist| format!("© {artist}")));
+    let copyright = match (metadata.description, credit) {
+        (Some(description), Some(credit)) => format!("{description} ({credit})"),
+        (description, credit) => description.or(credit).unwrap_or_default(),
+    };
+
+    let date = match metadata.date {
+        Some(date) => date,
+        None => fs::metadata(&path)?.modified()?.into(),
+    };
+
+    Ok(Image {
+        start_date: date.date_naive(),
+        full_start_date: date,
+        end_date: date
+            .date_naive()
+            .checked_add_days(Days::new(1))
+            .unwrap_or(date.date_naive()),
+        id: name,
+        id_parsed: None,
+        copyright_parsed: Copyright::parse(&copyright),
+        copyright,
+        copyright_link: url.clone(),
+        title,
+        quiz_link: url.clone(),
+        url,
+        wallpaper: true,
+        hash: hex::encode(hasher.finalize()),
+        video: None,
+        safe_area: None,
+        palette: None,
+        blurhash: None,
+        perceptual_hash: None,
+        rerun_of: None,
+    })
+}
+
+/// Metadata embedded in an image file.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Metadata {
+    title: Option<String>,
+    description: Option<String>,
+    copyright: Option<String>,
+    artist: Option<String>,
+    date: Option<DateTime<Utc>>,
+}
+
+static XMP_ELEMENT: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?s)<(dc:title|dc:description|dc:rights|dc:creator|photoshop:DateCreated|xmp:CreateDate)\b[^>]*>(.*?)</(?:dc:title|dc:description|dc:rights|dc:creator|photoshop:DateCreated|xmp:CreateDate)>",
+    )
+    .unwrap()
+});
+
+static XMP_ATTRIBUTE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"\s(photoshop:DateCreated|xmp:CreateDate)\s*=\s*"([^"]*)""#).unwrap()
+});
+
+static XMP_ITEM: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?s)<rdf:li\b[^>]*>(.*?)</rdf:li>").unwrap());
+
+impl Metadata {
+    /// Reads the first XMP packet in `data`, wherever the file format keeps it.
+    fn from_xmp(data: &[u8]) -> Self {
+        const START: &[u8] = b"<x:xmpmeta";
+        const END: &[u8] = b"</x:xmpmeta>";
+
+        let mut metadata = Self::default();
+
+        let Some(packet) = find(data, START)
+            .map(|start| &data[start..])
+            .and_then(|rest| find(rest, END).map(|end| &rest[..end + END.len()]))
+        else {
+            return metadata;
+        };
+        let packet = String::from_utf8_lossy(packet);
+
+        let elements = XMP_ELEMENT.captures_iter(&packet).map(|captures| {
+            // Language alternatives and lists use their first item.
+            let value = captures.get(2).unwrap().as_str();
+            let value = XMP_ITEM
+                .captures(value)
+                .map_or(value, |item| item.get(1).unwrap().as_str());
+
+            (captures.get(1).unwrap().as_str(), value)
+        });
+        let attributes = XMP_ATTRIBUTE.captures_iter(&packet).map(|captures| {
+            (
+                captures.get(1).unwrap().as_str(),
+                captures.get(2).unwrap().as_str(),
+            )
+        });
+
+        for (name, value) in elements.chain(attributes) {
+            let value = unescape(value.trim());
+            if value.is_empty() {
+                continue;
+            }
+
+            let field = match name {
+                "dc:title" => &mut metadata.title,
+                "dc:description" => &mut metadata.description,
+                "dc:rights" => &mut metadata.copyright,
+                "dc:creator" => &mut metadata.artist,
+                _ => {
+                    metadata.date = metadata.date.or(parse_xmp_date(&value));
+                    continue;
+                }
+            };
+            field.get_or_insert(value);
+        }
+
+        metadata
+    }
+
+    fn from_exif(exif: &Exif) -> Self {
+        let text = |tag| {
+            exif.get_field(tag, In::PRIMARY)
+                .and_then(|field| ascii(&field.value))
+        };
+
+        Self {
+            title: exif
+                .get_field(XP_TITLE, In::PRIMARY)
+                .and_then(|field| utf16(&field.value)),
+            description: text(Tag::ImageDescription),
+            copyright: text(Tag::Copyright),
+            artist: text(Tag::Artist),
+            date: text(Tag::DateTimeOriginal)
+                .or_else(|| text(Tag::DateTime))
+                .and_then(|date| NaiveDateTime::parse_from_str(&date, "%Y:%m:%d %H:%M:%S").ok())
+                .map(|date| date.and_utc()),
+        }
+    }
+
+    /// Fills the fields missing from `self` with those of `other`.
+    fn or(self, other: Self) -> Self {
+        Self {
+            title: self.title.or(other.title),
+            description: self.description.or(other.description),
+            copyright: self.copyright.or(other.copyright),
+            artist: self.artist.or(other.artist),
+            date: self.date.or(other.date),
+        }
+    }
+}
+
+/// Returns the position of the first occurrence of `needle` in `haystack`.
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+/// Returns the first non-empty string of an EXIF ASCII value.
+fn ascii(value: &Value) -> Option<String> {
+    let Value::Ascii(strings) = value else {
+        return None;
+    };
+
+    strings
+        .iter()
+        .map(|bytes| String::from_utf8_lossy(bytes).trim().to_string())
+        .find(|string| !string.is_empty())
+}
+
+/// Decodes the UTF-16LE strings Windows writes to the `XP*` tags.
+fn utf16(value: &Value) -> Option<String> {
+    let Value::Byte(bytes) = value else {
+        return None;
+    };
+
+    let units = bytes
+        .chunks_exact(2)
+        .map(|unit| u16::from_le_bytes([unit[0], unit[1]]))
+        .take_while(|&unit| unit != 0)
+        .collect::<Vec<_>>();
+    let string = String::from_utf16_lossy(&units).trim().to_string();
+
+    (!string.is_empty()).then_some(string)
+}
+
+fn parse_xmp_date(date: &str) -> Option<DateTime<Utc>> {
+    if let Ok(date) = DateTime::parse_from_rfc3339(date) {
+        return Some(date.to_utc());
+    }
+
+    ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%dT%H:%M"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(date, format).ok())
+        .or_else(|| {
+            NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                .ok()
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+        })
+        .map(|date| date.and_utc())
+}
+
+/// Decodes the XML character references and predefined entities in `text`.
+fn unescape(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find('&') {
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let decoded = rest.find(';').and_then(|end| {
+            let c = match &rest[1..end] {
+                "amp" => '&',
+                "lt" => '<',
+                "gt" => '>',
+                "quot" => '"',
+                "apos" => '\'',
+                entity => {
+                    let code = match entity.strip_prefix("#x").or(entity.strip_prefix("#X")) {
+                        Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                        None => entity.strip_prefix('#')?.parse().ok()?,
+                    };
+                    char::from_u32(code)?
+                }
+            };
+            Some((c, end))
+        });
+
+        match decoded {
+            Some((c, end)) => {
+                result.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                result.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    result.push_str(rest);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    const XMP: &str = r#"<?xpacket begin="" id="W5M0MpCehiHzreSzNTczkc9d"?>
+<x:xmpmeta xmlns:x="adobe:ns:meta/">
+  <rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+    <rdf:Description rdf:about="" photoshop:DateCreated="2024-05-01T08:30:00+02:00">
+      <dc:title><rdf:Alt><rdf:li xml:lang="x-default">Lavender fields</rdf:li></rdf:Alt></dc:title>
+      <dc:description><rdf:Alt><rdf:li xml:lang="x-default">Valensole, Provence &amp; more</rdf:li></rdf:Alt></dc:description>
+      <dc:rights><rdf:Alt><rdf:li xml:lang="x-default">&#169; Jane Doe</rdf:li></rdf:Alt></dc:rights>
+      <dc:creator><rdf:Seq><rdf:li>Jane Doe</rdf:li></rdf:Seq></dc:creator>
+    </rdf:Description>
+  </rdf:RDF>
+</x:xmpmeta>
+<?xpacket end="w"?>"#;
+
+    /// Builds a JPEG APP1 segment with the ASCII `tags` in IFD0.
+    fn exif_segment(tags: &[(u16, &str)]) -> Vec<u8> {
+        let mut tiff = b"II*\0\x08\0\0\0".to_vec();
+        let mut data = Vec::<u8>::new();
+        let data_offset = 8 + 2 + tags.len() * 12 + 4;
+
+        tiff.extend((tags.len() as u16).to_le_bytes());
+        for (tag, value) in tags {
+            let value = format!("{value}\0");
+            tiff.extend(tag.to_le_bytes());
+            tiff.extend(2u16.to_le_bytes());
+            tiff.extend((value.len() as u32).to_le_bytes());
+            tiff.extend(((data_offset + data.len()) as u32).to_le_bytes());
+            data.extend(value.as_bytes());
+        }
+        tiff.extend(0u32.to_le_bytes());
+        tiff.extend(data);
+
+        let mut segment = vec![0xff, 0xe1];
+        segment.extend(((2 + 6 + tiff.len()) as u16).to_be_bytes());
+        segment.extend(b"Exif\0\0");
+        segment.extend(tiff);
+        segment
+    }
+
+    fn jpeg() -> Vec<u8> {
+        let mut data = Vec::new();
+        image::RgbImage::new(16, 9)
+            .write_to(&mut Cursor::new(&mut data), image::ImageFormat::Jpeg)
+            .unwrap();
+        data
+    }
+
+    #[test]
+    fn test_from_xmp() {
+        let metadata = Metadata::from_xmp(format!("\0\0{XMP}\0").as_bytes());
+
+        assert_eq!(metadata.title.as_deref(), Some("Lavender fields"));
+        assert_eq!(
+            metadata.description.as_deref(),
+            Some("Valensole, Provence & more")
+        );
+        assert_eq!(metadata.copyright.as_deref(), Some("© Jane Doe"));
+        assert_eq!(metadata.artist.as_deref(), Some("Jane Doe"));
+        assert_eq!(
+            metadata.date,
+            "2024-05-01T06:30:00Z".parse::<DateTime<Utc>>().ok()
+        );
+
+        assert_eq!(Metadata::from_xmp(b"no metadata"), Metadata::default());
+        assert_eq!(
+            Metadata::from_xmp(b"<x:xmpmeta><dc:title>Unterminated</dc:title>"),
+            Metadata::default()
+        );
+    }
+
+    #[test]
+    fn test_unescape() {
+        assert_eq!(unescape("a &amp; b &lt;c&gt;"), "a & b <c>");
+        assert_eq!(unescape("&#169; &#xA9;"), "© ©");
+        assert_eq!(unescape("AT&T; &bogus;"), "AT&T; &bogus;");
+    }
+
+    #[tokio::test]
+    async fn test_local_source() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let mut data = jpeg();
+        data.splice(
+            2..2,
+            exif_segment(&[
+                (0x010e, "Misty forest"),
+                (0x013b, "John Roe"),
+                (0x0132, "2023:10:05 07:00:00"),
+            ]),
+        );
+        fs::write(dir.path().join("forest.jpg"), &data).unwrap();
+
+        let mut file = fs::File::create(dir.path().join("lavender.jpg")).unwrap();
+        file.write_all(&jpeg()).unwrap();
+        file.write_all(XMP.as_bytes()).unwrap();
+
+        fs::write(dir.path().join("notes.txt"), "not an image").unwrap();
+
+        let source = LocalSource::new(dir.path());
+        let images = source.list().await.unwrap();
+
+        assert_eq!(images.len(), 2);
+
+        assert_eq!(images[0].id, "lavender.jpg");
+        assert_eq!(images[0].title, "Lavender fields");
+        assert_eq!(
+            images[0].copyright,
+            "Valensole, Provence & more (© Jane Doe)"
+        );
+        assert_eq!(images[0].start_date.to_string(), "2024-05-01");
+
+        assert_eq!(images[1].id, "forest.jpg");
+        assert_eq!(images[1].title, "forest");
+        assert_eq!(images[1].copyright, "Misty forest (© John Roe)");
+        assert_eq!(images[1].start_date.to_string(), "2023-10-05");
+        assert_eq!(images[1].url.scheme(), "file");
+        assert_eq!(images[1].hash, hex::encode(Sha256::digest(&data)));
+
+        assert_eq!(source.fetch(&images[1]).await.unwrap(), data);
+        assert!(source.metadata("forest.jpg").await.unwrap().is_some());
+    }
+}
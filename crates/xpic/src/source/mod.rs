@@ -0,0 +1,33 @@
+//! Wallpaper sources behind one API.
+//!
+//! Every source lists its wallpapers as [`Image`]s, so they can be browsed, searched and
+//! downloaded the same way whether they come from Bing, a local folder or another crate, such as
+//! the Windows Spotlight source of the `spotlight` crate.
+
+mod bing;
+#[cfg(feature = "local")]
+mod local;
+
+pub use self::bing::BingSource;
+#[cfg(feature = "local")]
+pub use self::local::LocalSource;
+
+use crate::Image;
+use futures::future::BoxFuture;
+
+/// Somewhere to get wallpapers from.
+pub trait WallpaperSource: Send + Sync {
+    /// Short name of the source, e.g. `bing`.
+    fn name(&self) -> &'static str;
+
+    /// Lists the available wallpapers, newest first.
+    fn list(&self) -> BoxFuture<'_, anyhow::Result<Vec<Image>>>;
+
+    /// Fetches the image data of `image`, which must have been listed by this source.
+    fn fetch<'a>(&'a self, image: &'a Image) -> BoxFuture<'a, anyhow::Result<Vec<u8>>>;
+
+    /// Returns the metadata of the wallpaper with `id`, if it is still available.
+    fn metadata<'a>(&'a self, id: &'a str) -> BoxFuture<'a, anyhow::Result<Option<Image>>> {
+        Box::pin(async move { Ok(self.list().await?.into_iter().find(|image| image.id == id)) })
+    }
+}
@@ -0,0 +1,130 @@
+use anyhow::{Context, bail};
+use std::process::{Command, Stdio};
+
+/// Runs the external programs the desktop backends are driven by.
+///
+/// Backends never spawn processes themselves, so they can be tested with a fake runner.
+pub trait CommandRunner: Send + Sync {
+    /// Runs `program` to completion and returns its standard output.
+    fn run(&self, program: &str, args: &[&str]) -> anyhow::Result<String>;
+
+    /// Starts `program` and leaves it running in the background.
+    fn spawn(&self, program: &str, args: &[&str]) -> anyhow::Result<()>;
+
+    /// Whether `program` is on `PATH`.
+    fn exists(&self, program: &str) -> bool;
+}
+
+/// Runs programs on the system.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemRunner;
+
+impl CommandRunner for SystemRunner {
+    fn run(&self, program: &str, args: &[&str]) -> anyhow::Result<String> {
+        let output = Command::new(program)
+            .args(args)
+            .stdin(Stdio::null())
+            .output()
+            .with_context(|| format!("failed to run {program}"))?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            bail!("{program} failed with {}: {}", output.status, stderr.trim());
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+
+    fn spawn(&self, program: &str, args: &[&str]) -> anyhow::Result<()> {
+        Command::new(program)
+            .args(args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .with_context(|| format!("failed to start {program}"))?;
+
+        Ok(())
+    }
+
+    fn exists(&self, program: &str) -> bool {
+        std::env::var_os("PATH").is_some_and(|paths| {
+            std::env::split_paths(&paths).any(|dir| dir.join(program).is_file())
+        })
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod testing {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    /// Records the commands it is asked to run instead of running them.
+    #[derive(Debug, Default)]
+    pub(crate) struct FakeRunner {
+        programs: Vec<String>,
+        outputs: HashMap<String, String>,
+        failing: Vec<String>,
+        calls: Mutex<Vec<String>>,
+    }
+
+    impl FakeRunner {
+        /// Pretends that `programs` are installed.
+        pub(crate) fn new(programs: &[&str]) -> Self {
+            Self {
+                programs: programs.iter().map(|program| program.to_string()).collect(),
+                ..Self::default()
+            }
+        }
+
+        /// Answers commands starting with `command` with `output`.
+        pub(crate) fn output(mut self, command: &str, output: &str) -> Self {
+            self.outputs.insert(command.to_string(), output.to_string());
+            self
+        }
+
+        /// Fails commands starting with `command`.
+        pub(crate) fn fail(mut self, command: &str) -> Self {
+            self.failing.push(command.to_string());
+            self
+        }
+
+        /// The commands run so far, with their arguments separated by spaces. Spawned commands
+        /// are prefixed with `&`.
+        pub(crate) fn calls(&self) -> Vec<String> {
+            self.calls.lock().unwrap().clone()
+        }
+
+        fn record(&self, call: String) -> anyhow::Result<String> {
+            self.calls.lock().unwrap().push(call.clone());
+
+            let call = call.trim_start_matches('&');
+            if self.failing.iter().any(|command| call.starts_with(command)) {
+                bail!("{call} failed");
+            }
+
+            Ok(self
+                .outputs
+                .iter()
+                .find(|(command, _)| call.starts_with(command.as_str()))
+                .map(|(_, output)| output.clone())
+                .unwrap_or_default())
+        }
+    }
+
+    impl CommandRunner for FakeRunner {
+        fn run(&self, program: &str, args: &[&str]) -> anyhow::Result<String> {
+            self.record([&[program], args].concat().join(" "))
+        }
+
+        fn spawn(&self, program: &str, args: &[&str]) -> anyhow::Result<()> {
+            self.record(format!("&{}", [&[program], args].concat().join(" ")))
+                .map(drop)
+        }
+
+        fn exists(&self, program: &str) -> bool {
+            self.programs.iter().any(|p| p == program)
+        }
+    }
+}
@@ -0,0 +1,76 @@
+use super::{CommandRunner, WallpaperBackend, file_url};
+use std::path::Path;
+use std::sync::Arc;
+
+const BACKGROUND: &str = "org.gnome.desktop.background";
+const SCREENSAVER: &str = "org.gnome.desktop.screensaver";
+
+/// GNOME and other desktops built on its settings, through `gsettings`.
+pub struct Gnome {
+    runner: Arc<dyn CommandRunner>,
+}
+
+impl Gnome {
+    pub fn new(runner: Arc<dyn CommandRunner>) -> Self {
+        Self { runner }
+    }
+
+    fn set(&self, schema: &str, key: &str, value: &str) -> anyhow::Result<()> {
+        self.runner
+            .run("gsettings", &["set", schema, key, value])
+            .map(drop)
+    }
+}
+
+impl WallpaperBackend for Gnome {
+    fn name(&self) -> &'static str {
+        "gnome"
+    }
+
+    fn set_wallpaper(&self, path: &Path) -> anyhow::Result<()> {
+        let url = file_url(path)?;
+
+        self.set(BACKGROUND, "picture-uri", &url)?;
+        // GNOME 42 and later show this one with the dark style, older versions lack the key.
+        let _ = self.set(BACKGROUND, "picture-uri-dark", &url);
+        self.set(BACKGROUND, "picture-options", "zoom")
+    }
+
+    fn supports_lock_screen(&self) -> bool {
+        true
+    }
+
+    fn set_lock_screen(&self, path: &Path) -> anyhow::Result<()> {
+        self.set(SCREENSAVER, "picture-uri", &file_url(path)?)?;
+        self.set(SCREENSAVER, "picture-options", "zoom")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wallpaper::command::testing::FakeRunner;
+
+    #[test]
+    fn test_set_wallpaper() {
+        let runner = Arc::new(
+            FakeRunner::new(&["gsettings"])
+                .fail("gsettings set org.gnome.desktop.background picture-uri-dark"),
+        );
+        let gnome = Gnome::new(runner.clone());
+
+        gnome.set_wallpaper(Path::new("/tmp/a b.jpg")).unwrap();
+        gnome.set_lock_screen(Path::new("/tmp/a b.jpg")).unwrap();
+
+        assert_eq!(
+            runner.calls(),
+            [
+                "gsettings set org.gnome.desktop.background picture-uri file:///tmp/a%20b.jpg",
+                "gsettings set org.gnome.desktop.background picture-uri-dark file:///tmp/a%20b.jpg",
+                "gsettings set org.gnome.desktop.background picture-options zoom",
+                "gsettings set org.gnome.desktop.screensaver picture-uri file:///tmp/a%20b.jpg",
+                "gsettings set org.gnome.desktop.screensaver picture-options zoom",
+            ]
+        );
+    }
+}
@@ -0,0 +1,109 @@
+use super::{CommandRunner, WallpaperBackend, file_url};
+use anyhow::bail;
+use std::path::Path;
+use std::sync::Arc;
+
+/// KDE Plasma, through a Plasma shell script sent over D-Bus.
+pub struct Kde {
+    runner: Arc<dyn CommandRunner>,
+}
+
+impl Kde {
+    pub fn new(runner: Arc<dyn CommandRunner>) -> Self {
+        Self { runner }
+    }
+
+    /// Returns the script that shows `url` on every desktop.
+    fn script(url: &str) -> String {
+        let url = serde_json::to_string(url).expect("string should serialize");
+
+        format!(
+            r#"for (const desktop of desktops()) {{
+    desktop.wallpaperPlugin = "org.kde.image";
+    desktop.currentConfigGroup = ["Wallpaper", "org.kde.image", "General"];
+    desktop.writeConfig("Image", {url});
+}}"#
+        )
+    }
+}
+
+impl WallpaperBackend for Kde {
+    fn name(&self) -> &'static str {
+        "kde"
+    }
+
+    fn set_wallpaper(&self, path: &Path) -> anyhow::Result<()> {
+        let script = format!("string:{}", Self::script(&file_url(path)?));
+
+        self.runner
+            .run(
+                "dbus-send",
+                &[
+                    "--session",
+                    "--dest=org.kde.plasmashell",
+                    "--type=method_call",
+                    "--print-reply",
+                    "/PlasmaShell",
+                    "org.kde.PlasmaShell.evaluateScript",
+                    &script,
+                ],
+            )
+            .map(drop)
+    }
+
+    fn supports_lock_screen(&self) -> bool {
+        true
+    }
+
+    fn set_lock_screen(&self, path: &Path) -> anyhow::Result<()> {
+        // Plasma 6 renamed the tool.
+        let Some(program) = ["kwriteconfig6", "kwriteconfig5"]
+            .into_iter()
+            .find(|program| self.runner.exists(program))
+        else {
+            bail!("kwriteconfig is not installed");
+        };
+
+        #[rustfmt::skip]
+        let args = [
+            "--file", "kscreenlockerrc",
+            "--group", "Greeter",
+            "--group", "Wallpaper",
+            "--group", "org.kde.image",
+            "--group", "General",
+            "--key", "Image",
+        ];
+        let url = file_url(path)?;
+
+        self.runner
+            .run(program, &[&args[..], &[url.as_str()]].concat())
+            .map(drop)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wallpaper::command::testing::FakeRunner;
+
+    #[test]
+    fn test_set_wallpaper() {
+        let runner = Arc::new(FakeRunner::new(&["dbus-send", "kwriteconfig6"]));
+        let kde = Kde::new(runner.clone());
+
+        kde.set_wallpaper(Path::new("/tmp/\"a\".jpg")).unwrap();
+        kde.set_lock_screen(Path::new("/tmp/a.jpg")).unwrap();
+
+        let calls = runner.calls();
+        assert!(calls[0].starts_with(
+            "dbus-send --session --dest=org.kde.plasmashell --type=method_call --print-reply \
+             /PlasmaShell org.kde.PlasmaShell.evaluateScript string:for"
+        ));
+        assert!(calls[0].contains(r#"desktop.writeConfig("Image", "file:///tmp/%22a%22.jpg");"#));
+        assert_eq!(
+            calls[1],
+            "kwriteconfig6 --file kscreenlockerrc --group Greeter --group Wallpaper \
+             --group org.kde.image --group General --key Image file:///tmp/a.jpg"
+        );
+    }
+}
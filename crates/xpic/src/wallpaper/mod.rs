@@ -0,0 +1,265 @@
+//! Setting the desktop wallpaper and lock screen.
+//!
+//! [`detect`] picks the backend for the running desktop. Windows is supported natively; on Linux
+//! and other Unix desktops the backends drive the desktop's own tools through a
+//! [`CommandRunner`]:
+//!
+//! | Desktop              | Backend             | Lock screen | Per monitor |
+//! |----------------------|---------------------|-------------|-------------|
+//! | GNOME, Unity, Budgie | `gsettings`         | yes         | no          |
+//! | KDE Plasma           | D-Bus script        | yes         | no          |
+//! | Xfce                 | `xfconf-query`      | no          | yes         |
+//! | sway                 | `swaymsg`           | no          | yes         |
+//! | other Wayland        | `swaybg`            | no          | yes         |
+//! | other X11            | `feh` or `nitrogen` | no          | no          |
+//!
+//! Backends that set a wallpaper per monitor can show images [rendered](render()) for each
+//! monitor's size and orientation, or cut from one canvas spanning the desktop.
+
+mod command;
+mod gnome;
+mod kde;
+mod monitor;
+mod render;
+#[cfg(windows)]
+mod windows;
+mod wlroots;
+mod x11;
+mod xfce;
+
+pub use self::command::{CommandRunner, SystemRunner};
+pub use self::gnome::Gnome;
+pub use self::kde::Kde;
+pub use self::monitor::Monitor;
+pub use self::render::{Fit, frame, render, span};
+#[cfg(windows)]
+pub use self::windows::Windows;
+pub use self::wlroots::{Sway, Swaybg};
+pub use self::x11::{Feh, Nitrogen};
+pub use self::xfce::Xfce;
+
+use anyhow::{anyhow, bail};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use url::Url;
+
+/// Something that can show an image as the wallpaper.
+pub trait WallpaperBackend: Send + Sync {
+    /// Short name of the backend, e.g. `gnome`.
+    fn name(&self) -> &'static str;
+
+    /// Shows the image at `path` as the desktop wallpaper.
+    fn set_wallpaper(&self, path: &Path) -> anyhow::Result<()>;
+
+    /// Whether [`set_lock_screen`](Self::set_lock_screen) is supported.
+    fn supports_lock_screen(&self) -> bool {
+        false
+    }
+
+    /// Shows the image at `path` on the lock screen.
+    fn set_lock_screen(&self, path: &Path) -> anyhow::Result<()> {
+        let _ = path;
+        bail!("{} cannot set the lock screen", self.name())
+    }
+
+    /// Whether [`set_monitor_wallpapers`](Self::set_monitor_wallpapers) is supported.
+    fn supports_monitors(&self) -> bool {
+        false
+    }
+
+    /// Lists the connected monitors, with the IDs [`set_monitor_wallpapers`] expects.
+    ///
+    /// [`set_monitor_wallpapers`]: Self::set_monitor_wallpapers
+    fn monitors(&self) -> anyhow::Result<Vec<Monitor>> {
+        bail!("{} cannot list monitors", self.name())
+    }
+
+    /// Shows a separate image on each monitor. Monitors are told apart by their ID.
+    fn set_monitor_wallpapers(&self, wallpapers: &[(Monitor, PathBuf)]) -> anyhow::Result<()> {
+        let _ = wallpapers;
+        bail!("{} cannot set a wallpaper per monitor", self.name())
+    }
+}
+
+/// Desktops with a dedicated backend.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Desktop {
+    Gnome,
+    Kde,
+    Xfce,
+    Sway,
+}
+
+/// What [`detect_with`] knows about the session, read from environment variables.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Environment {
+    /// Lowercase desktop names from `XDG_CURRENT_DESKTOP` and `DESKTOP_SESSION`.
+    pub desktops: Vec<String>,
+    /// Whether `SWAYSOCK` is set.
+    pub sway: bool,
+    /// Whether `WAYLAND_DISPLAY` is set.
+    pub wayland: bool,
+}
+
+impl Environment {
+    pub fn from_env() -> Self {
+        Self::from_vars(|name| std::env::var(name).ok())
+    }
+
+    /// Reads the session from the variables returned by `var`.
+    pub fn from_vars(var: impl Fn(&str) -> Option<String>) -> Self {
+        let desktops = ["XDG_CURRENT_DESKTOP", "DESKTOP_SESSION"]
+            .into_iter()
+            .filter_map(&var)
+            .flat_map(|value| {
+                value
+                    .split(':')
+                    .map(|name| name.trim().to_lowercase())
+                    .collect::<Vec<_>>()
+            })
+            .filter(|name| !name.is_empty())
+            .collect();
+        let set = |name| var(name).is_some_and(|value| !value.is_empty());
+
+        Self {
+            desktops,
+            sway: set("SWAYSOCK"),
+            wayland: set("WAYLAND_DISPLAY"),
+        }
+    }
+
+    pub fn desktop(&self) -> Option<Desktop> {
+        if self.sway {
+            return Some(Desktop::Sway);
+        }
+
+        self.desktops.iter().find_map(|name| match name.as_str() {
+            "gnome" | "gnome-classic" | "ubuntu" | "unity" | "budgie" | "budgie-desktop" => {
+                Some(Desktop::Gnome)
+            }
+            "kde" | "plasma" | "plasmawayland" => Some(Desktop::Kde),
+            "xfce" | "xfce4" | "xubuntu" => Some(Desktop::Xfce),
+            "sway" => Some(Desktop::Sway),
+            _ => None,
+        })
+    }
+}
+
+/// Returns the backend for the running desktop.
+pub fn detect() -> anyhow::Result<Box<dyn WallpaperBackend>> {
+    #[cfg(windows)]
+    return Ok(Box::new(Windows));
+
+    #[cfg(not(windows))]
+    detect_with(&Environment::from_env(), Arc::new(SystemRunner))
+}
+
+/// Returns the backend for the desktop described by `env`, which runs commands with `runner`.
+///
+/// Without a known desktop, the first installed of `swaybg` (on Wayland), `feh` and `nitrogen`
+/// is used.
+pub fn detect_with(
+    env: &Environment,
+    runner: Arc<dyn CommandRunner>,
+) -> anyhow::Result<Box<dyn WallpaperBackend>> {
+    Ok(match env.desktop() {
+        Some(Desktop::Gnome) => Box::new(Gnome::new(runner)),
+        Some(Desktop::Kde) => Box::new(Kde::new(runner)),
+        Some(Desktop::Xfce) => Box::new(Xfce::new(runner)),
+        Some(Desktop::Sway) => Box::new(Sway::new(runner)),
+        None if env.wayland && runner.exists("swaybg") => Box::new(Swaybg::new(runner)),
+        None if runner.exists("feh") => Box::new(Feh::new(runner)),
+        None if runner.exists("nitrogen") => Box::new(Nitrogen::new(runner)),
+        None => bail!("no supported desktop or wallpaper tool found"),
+    })
+}
+
+/// Returns the ID of `monitor`, which backends need to tell monitors apart.
+fn monitor_id(monitor: &Monitor) -> anyhow::Result<&str> {
+    monitor
+        .id
+        .as_deref()
+        .ok_or_else(|| anyhow!("monitor {monitor} has no ID"))
+}
+
+fn path_str(path: &Path) -> anyhow::Result<&str> {
+    path.to_str()
+        .ok_or_else(|| anyhow!("path is not valid UTF-8: {}", path.display()))
+}
+
+/// Returns the `file:` URL of `path`, made absolute.
+fn file_url(path: &Path) -> anyhow::Result<String> {
+    let path = std::path::absolute(path)?;
+
+    Url::from_file_path(&path)
+        .map(String::from)
+        .map_err(|_| anyhow!("invalid path: {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wallpaper::command::testing::FakeRunner;
+    use std::collections::HashMap;
+
+    fn env(vars: &[(&str, &str)]) -> Environment {
+        let vars = vars.iter().copied().collect::<HashMap<_, _>>();
+        Environment::from_vars(|name| vars.get(name).map(|value| value.to_string()))
+    }
+
+    fn detect(vars: &[(&str, &str)], programs: &[&str]) -> Option<&'static str> {
+        detect_with(&env(vars), Arc::new(FakeRunner::new(programs)))
+            .ok()
+            .map(|backend| backend.name())
+    }
+
+    #[test]
+    fn test_environment() {
+        let env = env(&[
+            ("XDG_CURRENT_DESKTOP", "ubuntu:GNOME"),
+            ("DESKTOP_SESSION", "ubuntu"),
+            ("WAYLAND_DISPLAY", "wayland-0"),
+            ("SWAYSOCK", ""),
+        ]);
+
+        assert_eq!(env.desktops, ["ubuntu", "gnome", "ubuntu"]);
+        assert!(env.wayland);
+        assert!(!env.sway);
+        assert_eq!(env.desktop(), Some(Desktop::Gnome));
+    }
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(
+            detect(&[("XDG_CURRENT_DESKTOP", "GNOME")], &[]),
+            Some("gnome")
+        );
+        assert_eq!(detect(&[("XDG_CURRENT_DESKTOP", "KDE")], &[]), Some("kde"));
+        assert_eq!(
+            detect(&[("XDG_CURRENT_DESKTOP", "XFCE")], &[]),
+            Some("xfce")
+        );
+        assert_eq!(detect(&[("SWAYSOCK", "/run/sway.sock")], &[]), Some("sway"));
+        assert_eq!(detect(&[("DESKTOP_SESSION", "plasma")], &[]), Some("kde"));
+
+        let wayland = [
+            ("XDG_CURRENT_DESKTOP", "Hyprland"),
+            ("WAYLAND_DISPLAY", "wayland-1"),
+        ];
+        assert_eq!(detect(&wayland, &["swaybg", "feh"]), Some("swaybg"));
+        assert_eq!(
+            detect(&[("DESKTOP_SESSION", "i3")], &["swaybg", "feh"]),
+            Some("feh")
+        );
+        assert_eq!(detect(&[], &["nitrogen"]), Some("nitrogen"));
+        assert_eq!(detect(&[], &[]), None);
+    }
+
+    #[test]
+    fn test_default_lock_screen() {
+        let feh = Feh::new(Arc::new(FakeRunner::new(&["feh"])));
+
+        assert!(!feh.supports_lock_screen());
+        assert!(feh.set_lock_screen(Path::new("/tmp/a.jpg")).is_err());
+    }
+}
@@ -0,0 +1,154 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+/// A display and where it sits on the desktop.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Monitor {
+    /// What the backend calls the monitor, e.g. `DP-1` or a Windows device path.
+    pub id: Option<String>,
+    pub x: i32,
+    pub y: i32,
+    /// Width as shown, so a rotated monitor is taller than it is wide.
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Monitor {
+    pub fn new(x: i32, y: i32, width: u32, height: u32) -> Self {
+        Self {
+            id: None,
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    pub fn id(mut self, id: impl Into<String>) -> Self {
+        self.id = Some(id.into());
+
+        self
+    }
+
+    pub fn is_portrait(&self) -> bool {
+        self.height > self.width
+    }
+
+    /// Returns the smallest area that covers all `monitors`, or `None` if there are none.
+    pub fn bounds(monitors: &[Monitor]) -> Option<Monitor> {
+        let left = monitors.iter().map(|monitor| monitor.x).min()?;
+        let top = monitors.iter().map(|monitor| monitor.y).min()?;
+        let right = monitors.iter().map(Monitor::right).max()?;
+        let bottom = monitors.iter().map(Monitor::bottom).max()?;
+
+        Some(Monitor::new(
+            left,
+            top,
+            (right - left) as u32,
+            (bottom - top) as u32,
+        ))
+    }
+
+    fn right(&self) -> i32 {
+        self.x + self.width as i32
+    }
+
+    fn bottom(&self) -> i32 {
+        self.y + self.height as i32
+    }
+}
+
+/// Formats as `[ID=]WxH+X+Y`, like X11 geometry.
+impl Display for Monitor {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if let Some(id) = &self.id {
+            write!(f, "{id}=")?;
+        }
+
+        write!(f, "{}x{}{:+}{:+}", self.width, self.height, self.x, self.y)
+    }
+}
+
+/// Parses `[ID=]WxH[+X+Y]`, e.g. `DP-1=2560x1440+1920+0`.
+impl FromStr for Monitor {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid monitor: {s}, expected [ID=]WxH+X+Y");
+
+        let (id, geometry) = match s.rsplit_once('=') {
+            Some((id, geometry)) => (Some(id.trim()), geometry.trim()),
+            None => (None, s.trim()),
+        };
+
+        let (size, offset) = match geometry.find(['+', '-']) {
+            Some(i) => geometry.split_at(i),
+            None => (geometry, "+0+0"),
+        };
+        let (width, height) = size.split_once(['x', 'X']).ok_or_else(invalid)?;
+
+        let i = offset[1..].find(['+', '-']).ok_or_else(invalid)? + 1;
+        let (x, y) = offset.split_at(i);
+
+        let monitor = Monitor::new(
+            x.parse().map_err(|_| invalid())?,
+            y.parse().map_err(|_| invalid())?,
+            width.parse().map_err(|_| invalid())?,
+            height.parse().map_err(|_| invalid())?,
+        );
+        if monitor.width == 0 || monitor.height == 0 {
+            return Err(invalid());
+        }
+
+        Ok(match id {
+            Some(id) if !id.is_empty() => monitor.id(id),
+            _ => monitor,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            "DP-1=2560x1440+1920+0".parse::<Monitor>().unwrap(),
+            Monitor::new(1920, 0, 2560, 1440).id("DP-1")
+        );
+        assert_eq!(
+            "1080x1920-1080-420".parse::<Monitor>().unwrap(),
+            Monitor::new(-1080, -420, 1080, 1920)
+        );
+        assert_eq!(
+            "1920x1080".parse::<Monitor>().unwrap(),
+            Monitor::new(0, 0, 1920, 1080)
+        );
+
+        for s in ["", "1920", "0x1080", "1920x1080+0", "a=bxc+0+0"] {
+            assert!(s.parse::<Monitor>().is_err(), "{s}");
+        }
+
+        let monitor = Monitor::new(-1080, 0, 1080, 1920).id("HDMI-1");
+        assert_eq!(monitor.to_string(), "HDMI-1=1080x1920-1080+0");
+        assert_eq!(monitor.to_string().parse::<Monitor>().unwrap(), monitor);
+        assert!(monitor.is_portrait());
+    }
+
+    #[test]
+    fn test_bounds() {
+        let monitors = [
+            Monitor::new(-1080, -420, 1080, 1920),
+            Monitor::new(0, 0, 2560, 1440),
+            Monitor::new(2560, 180, 1920, 1080),
+        ];
+
+        assert_eq!(
+            Monitor::bounds(&monitors),
+            Some(Monitor::new(-1080, -420, 5560, 1920))
+        );
+        assert_eq!(Monitor::bounds(&[]), None);
+    }
+}
@@ -0,0 +1,253 @@
+use super::Monitor;
+use crate::Image;
+use crate::bing::{CropMode, ThumbnailParams, UrlBuilder};
+use crate::cache::{self, Cache};
+use anyhow::bail;
+use image::DynamicImage;
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use strum::{Display, EnumString};
+
+/// JPEG quality of locally cropped images.
+const QUALITY: u8 = 90;
+
+/// How one image is shown on several monitors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Display, EnumString)]
+#[serde(rename_all = "kebab-case")]
+#[strum(serialize_all = "kebab-case", ascii_case_insensitive)]
+pub enum Fit {
+    /// The whole image on every monitor, cropped to the shape of each one.
+    PerMonitor,
+    /// One image across the whole desktop, each monitor showing its part.
+    Span,
+}
+
+/// Returns a wallpaper for each of `monitors`, framed according to `fit` and stored in `cache`.
+///
+/// Bing crops its images per monitor around their subject ([`CropMode::SmartRatio`]), and also
+/// renders the canvas of a spanned Bing image, which is then cut up locally. Other images are
+/// cropped locally from the center.
+pub async fn render(
+    cache: &Cache,
+    image: &Image,
+    monitors: &[Monitor],
+    fit: Fit,
+) -> anyhow::Result<Vec<(Monitor, PathBuf)>> {
+    let Some(bounds) = Monitor::bounds(monitors) else {
+        bail!("no monitors to render the wallpaper for");
+    };
+    let bing = image.has_thumbnails();
+
+    if bing && fit == Fit::PerMonitor {
+        let mut wallpapers = Vec::with_capacity(monitors.len());
+        for monitor in monitors {
+            let url = smart_crop_url(image, monitor.width, monitor.height)?;
+            wallpapers.push((monitor.clone(), cache.fetch_path(url).await?));
+        }
+
+        return Ok(wallpapers);
+    }
+
+    let source = if bing {
+        smart_crop_url(image, bounds.width, bounds.height)?
+    } else {
+        image.url.to_string()
+    };
+    let keys = monitors
+        .iter()
+        .map(|monitor| cache::derived_key(&source, image, format!("{fit}/{monitor}")))
+        .collect::<Vec<_>>();
+
+    // Files may have been deleted behind the cache's back.
+    if keys
+        .iter()
+        .all(|key| cache.entry(key).is_some() && cache.path(key).is_file())
+    {
+        return Ok(monitors
+            .iter()
+            .cloned()
+            .zip(keys.iter().map(|key| cache.path(key)))
+            .collect());
+    }
+
+    let data = cache.fetch(&source).await?;
+    let tiles = {
+        let monitors = monitors.to_vec();
+
+        tokio::task::spawn_blocking(move || {
+            let image = image::load_from_memory(&data)?;
+
+            frame(&image, &monitors, fit)
+                .iter()
+                .map(encode)
+                .collect::<anyhow::Result<Vec<_>>>()
+        })
+        .await??
+    };
+
+    let mut wallpapers = Vec::with_capacity(monitors.len());
+    for ((monitor, key), tile) in monitors.iter().zip(&keys).zip(tiles) {
+        wallpapers.push((monitor.clone(), cache.put(key, &tile).await?));
+    }
+
+    Ok(wallpapers)
+}
+
+/// Crops `image` for each of `monitors`, either to the shape of each one or as its part of the
+/// [`span`] canvas.
+pub fn frame(image: &DynamicImage, monitors: &[Monitor], fit: Fit) -> Vec<DynamicImage> {
+    match fit {
+        Fit::PerMonitor => monitors
+            .iter()
+            .map(|monitor| {
+                image.resize_to_fill(monitor.width, monitor.height, FilterType::Lanczos3)
+            })
+            .collect(),
+        Fit::Span => {
+            let Some((bounds, canvas)) = Monitor::bounds(monitors).zip(span(image, monitors))
+            else {
+                return Vec::new();
+            };
+
+            monitors
+                .iter()
+                .map(|monitor| {
+                    canvas.crop_imm(
+                        (monitor.x - bounds.x) as u32,
+                        (monitor.y - bounds.y) as u32,
+                        monitor.width,
+                        monitor.height,
+                    )
+                })
+                .collect()
+        }
+    }
+}
+
+/// Scales and crops `image` to cover the area of all `monitors`, or returns `None` if there are
+/// none.
+pub fn span(image: &DynamicImage, monitors: &[Monitor]) -> Option<DynamicImage> {
+    let bounds = Monitor::bounds(monitors)?;
+
+    Some(image.resize_to_fill(bounds.width, bounds.height, FilterType::Lanczos3))
+}
+
+fn smart_crop_url(image: &Image, width: u32, height: u32) -> anyhow::Result<String> {
+    UrlBuilder::new(&image.id)
+        .width(width)
+        .height(height)
+        .crop(CropMode::SmartRatio)
+        .build()
+}
+
+fn encode(image: &DynamicImage) -> anyhow::Result<Vec<u8>> {
+    let mut data = Vec::new();
+    JpegEncoder::new_with_quality(&mut data, QUALITY).encode_image(&image.to_rgb8())?;
+
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::ImageBuilder;
+    use image::{Rgb, RgbImage};
+    use url::Url;
+
+    /// A 400x200 image, red on the left half and blue on the right.
+    fn halves() -> DynamicImage {
+        DynamicImage::ImageRgb8(RgbImage::from_fn(400, 200, |x, _| {
+            if x < 200 {
+                Rgb([255, 0, 0])
+            } else {
+                Rgb([0, 0, 255])
+            }
+        }))
+    }
+
+    fn is_red(image: &DynamicImage) -> bool {
+        let pixel = image
+            .to_rgb8()
+            .get_pixel(image.width() / 2, image.height() / 2)
+            .0;
+        pixel[0] > 200 && pixel[2] < 50
+    }
+
+    fn monitors() -> [Monitor; 2] {
+        [
+            Monitor::new(0, 0, 108, 192).id("left"),
+            Monitor::new(108, 42, 192, 108).id("right"),
+        ]
+    }
+
+    #[test]
+    fn test_frame_per_monitor() {
+        let frames = frame(&halves(), &monitors(), Fit::PerMonitor);
+
+        assert_eq!(frames[0].width(), 108);
+        assert_eq!(frames[0].height(), 192);
+        assert_eq!(frames[1].width(), 192);
+        assert_eq!(frames[1].height(), 108);
+    }
+
+    #[test]
+    fn test_frame_span() {
+        let monitors = monitors();
+        let canvas = span(&halves(), &monitors).unwrap();
+        assert_eq!((canvas.width(), canvas.height()), (300, 192));
+
+        let frames = frame(&halves(), &monitors, Fit::Span);
+        assert_eq!((frames[0].width(), frames[0].height()), (108, 192));
+        assert_eq!((frames[1].width(), frames[1].height()), (192, 108));
+        // Each monitor shows its own side of the image.
+        assert!(is_red(&frames[0]));
+        assert!(!is_red(&frames[1]));
+
+        assert!(frame(&halves(), &[], Fit::Span).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_render_local() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("halves.png");
+        halves().save(&path).unwrap();
+
+        let cache = Cache::open(dir.path().join("cache")).unwrap();
+        let image = ImageBuilder::new("halves.png")
+            .url(Url::from_file_path(&path).unwrap())
+            .build();
+
+        let wallpapers = render(&cache, &image, &monitors(), Fit::Span)
+            .await
+            .unwrap();
+        assert_eq!(wallpapers.len(), 2);
+        assert_eq!(wallpapers[1].0.id.as_deref(), Some("right"));
+
+        let right = image::ImageReader::open(&wallpapers[1].1)
+            .and_then(|reader| reader.with_guessed_format())
+            .unwrap()
+            .decode()
+            .unwrap();
+        assert_eq!((right.width(), right.height()), (192, 108));
+
+        // Rendered once.
+        std::fs::remove_file(&path).unwrap();
+        let again = render(&cache, &image, &monitors(), Fit::Span)
+            .await
+            .unwrap();
+        assert_eq!(again, wallpapers);
+
+        // Rendered again if a file is gone.
+        halves().save(&path).unwrap();
+        std::fs::remove_file(&wallpapers[1].1).unwrap();
+        let again = render(&cache, &image, &monitors(), Fit::Span)
+            .await
+            .unwrap();
+        assert_eq!(again, wallpapers);
+        assert!(wallpapers[1].1.is_file());
+
+        assert!(render(&cache, &image, &[], Fit::Span).await.is_err());
+    }
+}
@@ -0,0 +1,115 @@
+use super::{Monitor, WallpaperBackend, monitor_id};
+use ::windows::{
+    Storage::StorageFile,
+    System::UserProfile::LockScreen,
+    Win32::{
+        System::Com::{
+            CLSCTX_ALL, COINIT_MULTITHREADED, CoCreateInstance, CoInitializeEx, CoTaskMemFree,
+            CoUninitialize,
+        },
+        UI::Shell::{DesktopWallpaper, IDesktopWallpaper},
+    },
+    core::{HSTRING, PCWSTR},
+};
+use std::path::{Path, PathBuf};
+
+/// Windows, through `IDesktopWallpaper` and the WinRT `LockScreen` API.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Windows;
+
+impl WallpaperBackend for Windows {
+    fn name(&self) -> &'static str {
+        "windows"
+    }
+
+    /// Sets the desktop wallpaper on all monitors.
+    fn set_wallpaper(&self, path: &Path) -> anyhow::Result<()> {
+        let path = HSTRING::from(path.as_os_str());
+
+        with_desktop_wallpaper(|wallpaper| unsafe {
+            Ok(wallpaper.SetWallpaper(PCWSTR::null(), &path)?)
+        })
+    }
+
+    fn supports_lock_screen(&self) -> bool {
+        true
+    }
+
+    fn set_lock_screen(&self, path: &Path) -> anyhow::Result<()> {
+        let path = HSTRING::from(path.as_os_str());
+
+        futures::executor::block_on(async {
+            let file = StorageFile::GetFileFromPathAsync(&path)?.await?;
+            LockScreen::SetImageFileAsync(&file)?.await?;
+
+            Ok::<_, anyhow::Error>(())
+        })
+    }
+
+    fn supports_monitors(&self) -> bool {
+        true
+    }
+
+    /// Lists the attached monitors, identified by their device paths.
+    fn monitors(&self) -> anyhow::Result<Vec<Monitor>> {
+        with_desktop_wallpaper(|wallpaper| unsafe {
+            let mut monitors = Vec::new();
+
+            for index in 0..wallpaper.GetMonitorDevicePathCount()? {
+                let id = wallpaper.GetMonitorDevicePathAt(index)?;
+                let name = id.to_string();
+                CoTaskMemFree(Some(id.0 as *const _));
+                let name = name?;
+
+                // Monitors that are connected but not part of the desktop have no rectangle.
+                let Ok(rect) = wallpaper.GetMonitorRECT(&HSTRING::from(&name)) else {
+                    continue;
+                };
+
+                monitors.push(
+                    Monitor::new(
+                        rect.left,
+                        rect.top,
+                        (rect.right - rect.left) as u32,
+                        (rect.bottom - rect.top) as u32,
+                    )
+                    .id(name),
+                );
+            }
+
+            Ok(monitors)
+        })
+    }
+
+    fn set_monitor_wallpapers(&self, wallpapers: &[(Monitor, PathBuf)]) -> anyhow::Result<()> {
+        with_desktop_wallpaper(|wallpaper| {
+            for (monitor, path) in wallpapers {
+                let id = HSTRING::from(monitor_id(monitor)?);
+                let path = HSTRING::from(path.as_os_str());
+
+                unsafe { wallpaper.SetWallpaper(&id, &path)? };
+            }
+
+            Ok(())
+        })
+    }
+}
+
+/// Runs `f` with the shell's `IDesktopWallpaper`, initializing COM around it.
+fn with_desktop_wallpaper<T>(
+    f: impl FnOnce(&IDesktopWallpaper) -> anyhow::Result<T>,
+) -> anyhow::Result<T> {
+    unsafe {
+        CoInitializeEx(None, COINIT_MULTITHREADED).ok()?;
+    }
+
+    let result = unsafe { CoCreateInstance(&DesktopWallpaper, None, CLSCTX_ALL) }
+        .map_err(anyhow::Error::from)
+        .and_then(|wallpaper: IDesktopWallpaper| f(&wallpaper));
+
+    unsafe {
+        CoUninitialize();
+    }
+
+    result
+}
@@ -0,0 +1,228 @@
+use super::{CommandRunner, Monitor, WallpaperBackend, monitor_id, path_str};
+use anyhow::Context;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// An output as listed by `swaymsg -t get_outputs`.
+#[derive(Deserialize)]
+struct Output {
+    name: String,
+    active: bool,
+    rect: Rect,
+}
+
+#[derive(Deserialize)]
+struct Rect {
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+}
+
+/// sway, through `swaymsg`.
+pub struct Sway {
+    runner: Arc<dyn CommandRunner>,
+}
+
+impl Sway {
+    pub fn new(runner: Arc<dyn CommandRunner>) -> Self {
+        Self { runner }
+    }
+
+    fn set(&self, output: &str, path: &Path) -> anyhow::Result<()> {
+        // swaymsg joins its arguments into one command, so the path is quoted.
+        let path = format!(
+            "\"{}\"",
+            path_str(path)?.replace('\\', "\\\\").replace('"', "\\\"")
+        );
+
+        self.runner
+            .run("swaymsg", &["output", output, "bg", &path, "fill"])
+            .map(drop)
+    }
+}
+
+impl WallpaperBackend for Sway {
+    fn name(&self) -> &'static str {
+        "sway"
+    }
+
+    fn set_wallpaper(&self, path: &Path) -> anyhow::Result<()> {
+        self.set("*", path)
+    }
+
+    fn supports_monitors(&self) -> bool {
+        true
+    }
+
+    /// Lists the active outputs, in logical pixels.
+    fn monitors(&self) -> anyhow::Result<Vec<Monitor>> {
+        let outputs = self.runner.run("swaymsg", &["-t", "get_outputs", "-r"])?;
+        let outputs = serde_json::from_str::<Vec<Output>>(&outputs)
+            .context("failed to parse sway outputs")?;
+
+        Ok(outputs
+            .into_iter()
+            .filter(|output| output.active)
+            .map(|Output { name, rect, .. }| {
+                Monitor::new(rect.x, rect.y, rect.width, rect.height).id(name)
+            })
+            .collect())
+    }
+
+    fn set_monitor_wallpapers(&self, wallpapers: &[(Monitor, PathBuf)]) -> anyhow::Result<()> {
+        for (monitor, path) in wallpapers {
+            self.set(monitor_id(monitor)?, path)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Other wlroots compositors, through `swaybg`.
+///
+/// `swaybg` draws the background for as long as it runs, so any running instance is replaced.
+pub struct Swaybg {
+    runner: Arc<dyn CommandRunner>,
+}
+
+impl Swaybg {
+    pub fn new(runner: Arc<dyn CommandRunner>) -> Self {
+        Self { runner }
+    }
+
+    fn restart(&self, args: &[&str]) -> anyhow::Result<()> {
+        // Fails when no instance is running.
+        let _ = self.runner.run("pkill", &["-x", "swaybg"]);
+
+        self.runner.spawn("swaybg", args)
+    }
+}
+
+impl WallpaperBackend for Swaybg {
+    fn name(&self) -> &'static str {
+        "swaybg"
+    }
+
+    fn set_wallpaper(&self, path: &Path) -> anyhow::Result<()> {
+        self.restart(&["-i", path_str(path)?, "-m", "fill"])
+    }
+
+    fn supports_monitors(&self) -> bool {
+        true
+    }
+
+    /// Runs one `swaybg` for all outputs, so the monitors need the IDs of the outputs, e.g.
+    /// from `wlr-randr`.
+    fn set_monitor_wallpapers(&self, wallpapers: &[(Monitor, PathBuf)]) -> anyhow::Result<()> {
+        let mut args = Vec::new();
+        for (monitor, path) in wallpapers {
+            args.extend([
+                "-o",
+                monitor_id(monitor)?,
+                "-i",
+                path_str(path)?,
+                "-m",
+                "fill",
+            ]);
+        }
+
+        self.restart(&args)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wallpaper::command::testing::FakeRunner;
+
+    #[test]
+    fn test_sway() {
+        let runner = Arc::new(FakeRunner::new(&["swaymsg"]));
+        Sway::new(runner.clone())
+            .set_wallpaper(Path::new("/tmp/my \"wall\".jpg"))
+            .unwrap();
+
+        assert_eq!(
+            runner.calls(),
+            [r#"swaymsg output * bg "/tmp/my \"wall\".jpg" fill"#]
+        );
+    }
+
+    #[test]
+    fn test_sway_monitors() {
+        let runner = Arc::new(FakeRunner::new(&["swaymsg"]).output(
+            "swaymsg -t get_outputs",
+            r#"[
+                {"name": "eDP-1", "active": true, "scale": 2.0,
+                 "rect": {"x": 0, "y": 0, "width": 1280, "height": 800}},
+                {"name": "DP-1", "active": true,
+                 "rect": {"x": 1280, "y": -200, "width": 1440, "height": 2560}},
+                {"name": "HDMI-A-1", "active": false,
+                 "rect": {"x": 0, "y": 0, "width": 0, "height": 0}}
+            ]"#,
+        ));
+        let sway = Sway::new(runner.clone());
+
+        let monitors = sway.monitors().unwrap();
+        assert_eq!(
+            monitors,
+            [
+                Monitor::new(0, 0, 1280, 800).id("eDP-1"),
+                Monitor::new(1280, -200, 1440, 2560).id("DP-1"),
+            ]
+        );
+
+        sway.set_monitor_wallpapers(&[
+            (monitors[0].clone(), PathBuf::from("/tmp/a.jpg")),
+            (monitors[1].clone(), PathBuf::from("/tmp/b.jpg")),
+        ])
+        .unwrap();
+        assert_eq!(
+            runner.calls()[1..],
+            [
+                r#"swaymsg output eDP-1 bg "/tmp/a.jpg" fill"#,
+                r#"swaymsg output DP-1 bg "/tmp/b.jpg" fill"#,
+            ]
+        );
+
+        let unnamed = (Monitor::new(0, 0, 1, 1), PathBuf::from("/tmp/a.jpg"));
+        assert!(sway.set_monitor_wallpapers(&[unnamed]).is_err());
+    }
+
+    #[test]
+    fn test_swaybg() {
+        let runner = Arc::new(FakeRunner::new(&["swaybg"]).fail("pkill"));
+        Swaybg::new(runner.clone())
+            .set_wallpaper(Path::new("/tmp/a.jpg"))
+            .unwrap();
+
+        assert_eq!(
+            runner.calls(),
+            ["pkill -x swaybg", "&swaybg -i /tmp/a.jpg -m fill"]
+        );
+    }
+
+    #[test]
+    fn test_swaybg_monitors() {
+        let runner = Arc::new(FakeRunner::new(&["swaybg"]));
+        Swaybg::new(runner.clone())
+            .set_monitor_wallpapers(&[
+                (
+                    Monitor::new(0, 0, 1, 1).id("DP-1"),
+                    PathBuf::from("/tmp/a.jpg"),
+                ),
+                (
+                    Monitor::new(1, 0, 1, 1).id("DP-2"),
+                    PathBuf::from("/tmp/b.jpg"),
+                ),
+            ])
+            .unwrap();
+
+        assert_eq!(
+            runner.calls()[1],
+            "&swaybg -o DP-1 -i /tmp/a.jpg -m fill -o DP-2 -i /tmp/b.jpg -m fill"
+        );
+    }
+}
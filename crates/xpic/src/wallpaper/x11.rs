@@ -0,0 +1,167 @@
+use super::{CommandRunner, Monitor, WallpaperBackend, monitor_id, path_str};
+use anyhow::anyhow;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Plain X11 window managers, through `feh`.
+///
+/// `feh` also writes `~/.fehbg`, which window managers commonly run at startup. Per-monitor
+/// images are given to `feh` in the order `xrandr` lists the monitors.
+pub struct Feh {
+    runner: Arc<dyn CommandRunner>,
+}
+
+impl Feh {
+    pub fn new(runner: Arc<dyn CommandRunner>) -> Self {
+        Self { runner }
+    }
+}
+
+impl WallpaperBackend for Feh {
+    fn name(&self) -> &'static str {
+        "feh"
+    }
+
+    fn set_wallpaper(&self, path: &Path) -> anyhow::Result<()> {
+        self.runner
+            .run("feh", &["--bg-fill", path_str(path)?])
+            .map(drop)
+    }
+
+    fn supports_monitors(&self) -> bool {
+        true
+    }
+
+    fn monitors(&self) -> anyhow::Result<Vec<Monitor>> {
+        xrandr_monitors(self.runner.as_ref())
+    }
+
+    fn set_monitor_wallpapers(&self, wallpapers: &[(Monitor, PathBuf)]) -> anyhow::Result<()> {
+        let mut args = vec!["--bg-fill"];
+        for monitor in self.monitors()? {
+            let id = monitor_id(&monitor)?;
+            let (_, path) = wallpapers
+                .iter()
+                .find(|(monitor, _)| monitor.id.as_deref() == Some(id))
+                .ok_or_else(|| anyhow!("no wallpaper for monitor {id}"))?;
+
+            args.push(path_str(path)?);
+        }
+
+        self.runner.run("feh", &args).map(drop)
+    }
+}
+
+/// Plain X11 window managers, through `nitrogen`.
+pub struct Nitrogen {
+    runner: Arc<dyn CommandRunner>,
+}
+
+impl Nitrogen {
+    pub fn new(runner: Arc<dyn CommandRunner>) -> Self {
+        Self { runner }
+    }
+}
+
+impl WallpaperBackend for Nitrogen {
+    fn name(&self) -> &'static str {
+        "nitrogen"
+    }
+
+    fn set_wallpaper(&self, path: &Path) -> anyhow::Result<()> {
+        self.runner
+            .run("nitrogen", &["--set-zoom-fill", "--save", path_str(path)?])
+            .map(drop)
+    }
+}
+
+/// Lists the monitors with `xrandr --listmonitors`.
+pub(super) fn xrandr_monitors(runner: &dyn CommandRunner) -> anyhow::Result<Vec<Monitor>> {
+    let output = runner.run("xrandr", &["--listmonitors"])?;
+
+    // Monitors: 2
+    //  0: +*eDP-1 1920/344x1080/194+0+0  eDP-1
+    output
+        .lines()
+        .skip(1)
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let fields = line.split_whitespace().collect::<Vec<_>>();
+            let invalid = || anyhow!("unexpected xrandr output: {line}");
+
+            let (Some(geometry), Some(name)) = (fields.get(2), fields.last()) else {
+                return Err(invalid());
+            };
+            // Drop the physical size in millimeters.
+            let (width, rest) = geometry.split_once('x').ok_or_else(invalid)?;
+            let width = width.split('/').next().unwrap_or_default();
+            let (height, offset) = rest.split_at(rest.find(['+', '-']).ok_or_else(invalid)?);
+            let height = height.split('/').next().unwrap_or_default();
+
+            format!("{name}={width}x{height}{offset}")
+                .parse::<Monitor>()
+                .map_err(|_| invalid())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wallpaper::command::testing::FakeRunner;
+
+    #[test]
+    fn test_set_wallpaper() {
+        let runner = Arc::new(FakeRunner::new(&["feh", "nitrogen"]));
+
+        Feh::new(runner.clone())
+            .set_wallpaper(Path::new("/tmp/a.jpg"))
+            .unwrap();
+        Nitrogen::new(runner.clone())
+            .set_wallpaper(Path::new("/tmp/a.jpg"))
+            .unwrap();
+
+        assert_eq!(
+            runner.calls(),
+            [
+                "feh --bg-fill /tmp/a.jpg",
+                "nitrogen --set-zoom-fill --save /tmp/a.jpg"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_feh_monitors() {
+        let runner = Arc::new(FakeRunner::new(&["feh", "xrandr"]).output(
+            "xrandr --listmonitors",
+            "Monitors: 2\n \
+             0: +*HDMI-1 2560/597x1440/336+1080+240  HDMI-1\n \
+             1: +DP-1 1080/300x1920/530+0+0  DP-1\n",
+        ));
+        let feh = Feh::new(runner.clone());
+
+        let monitors = feh.monitors().unwrap();
+        assert_eq!(
+            monitors,
+            [
+                Monitor::new(1080, 240, 2560, 1440).id("HDMI-1"),
+                Monitor::new(0, 0, 1080, 1920).id("DP-1"),
+            ]
+        );
+
+        feh.set_monitor_wallpapers(&[
+            (monitors[1].clone(), PathBuf::from("/tmp/portrait.jpg")),
+            (monitors[0].clone(), PathBuf::from("/tmp/wide.jpg")),
+        ])
+        .unwrap();
+        assert_eq!(
+            runner.calls().last().unwrap(),
+            "feh --bg-fill /tmp/wide.jpg /tmp/portrait.jpg"
+        );
+
+        assert!(
+            feh.set_monitor_wallpapers(&[(monitors[0].clone(), PathBuf::from("/tmp/a.jpg"))])
+                .is_err()
+        );
+    }
+}
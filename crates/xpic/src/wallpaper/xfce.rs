@@ -0,0 +1,188 @@
+use super::x11::xrandr_monitors;
+use super::{CommandRunner, Monitor, WallpaperBackend, monitor_id, path_str};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+const CHANNEL: &str = "xfce4-desktop";
+
+/// Property used when no monitor has had a background set yet.
+const DEFAULT_PROPERTY: &str = "/backdrop/screen0/monitor0/workspace0/last-image";
+
+/// `image-style` value that scales the image to fill the screen.
+const ZOOMED: &str = "5";
+
+/// Xfce, through `xfconf-query`.
+///
+/// Xfce keeps a background per monitor and workspace. A wallpaper is set on every workspace of
+/// every monitor, or of the monitors it is meant for.
+pub struct Xfce {
+    runner: Arc<dyn CommandRunner>,
+}
+
+impl Xfce {
+    pub fn new(runner: Arc<dyn CommandRunner>) -> Self {
+        Self { runner }
+    }
+
+    /// Returns the `last-image` properties that have been set.
+    fn properties(&self) -> anyhow::Result<Vec<String>> {
+        let properties = self.runner.run("xfconf-query", &["-c", CHANNEL, "-l"])?;
+
+        Ok(properties
+            .lines()
+            .map(str::trim)
+            .filter(|property| property.ends_with("/last-image"))
+            .map(str::to_owned)
+            .collect())
+    }
+
+    /// Shows the image at `path` on the backgrounds of `properties`.
+    fn set_image(&self, properties: &[String], path: &Path) -> anyhow::Result<()> {
+        let path = path_str(path)?;
+
+        for property in properties {
+            self.set(property, "string", path)?;
+
+            let style = property.replace("/last-image", "/image-style");
+            self.set(&style, "int", ZOOMED)?;
+        }
+
+        Ok(())
+    }
+
+    fn set(&self, property: &str, kind: &str, value: &str) -> anyhow::Result<()> {
+        self.runner
+            .run(
+                "xfconf-query",
+                &["-c", CHANNEL, "-p", property, "-n", "-t", kind, "-s", value],
+            )
+            .map(drop)
+    }
+}
+
+impl WallpaperBackend for Xfce {
+    fn name(&self) -> &'static str {
+        "xfce"
+    }
+
+    fn set_wallpaper(&self, path: &Path) -> anyhow::Result<()> {
+        let mut properties = self.properties()?;
+        if properties.is_empty() {
+            properties.push(DEFAULT_PROPERTY.to_string());
+        }
+
+        self.set_image(&properties, path)
+    }
+
+    fn supports_monitors(&self) -> bool {
+        true
+    }
+
+    fn monitors(&self) -> anyhow::Result<Vec<Monitor>> {
+        xrandr_monitors(self.runner.as_ref())
+    }
+
+    fn set_monitor_wallpapers(&self, wallpapers: &[(Monitor, PathBuf)]) -> anyhow::Result<()> {
+        let properties = self.properties()?;
+
+        for (monitor, path) in wallpapers {
+            let prefix = format!("/backdrop/screen0/monitor{}/", monitor_id(monitor)?);
+            let mut monitor_properties = properties
+                .iter()
+                .filter(|property| property.starts_with(&prefix))
+                .cloned()
+                .collect::<Vec<_>>();
+            if monitor_properties.is_empty() {
+                monitor_properties.push(format!("{prefix}workspace0/last-image"));
+            }
+
+            self.set_image(&monitor_properties, path)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wallpaper::command::testing::FakeRunner;
+
+    #[test]
+    fn test_set_wallpaper() {
+        let runner = Arc::new(FakeRunner::new(&["xfconf-query"]).output(
+            "xfconf-query -c xfce4-desktop -l",
+            "/backdrop/screen0/monitorHDMI-1/workspace0/image-style\n\
+             /backdrop/screen0/monitorHDMI-1/workspace0/last-image\n\
+             /backdrop/screen0/monitoreDP-1/workspace0/last-image\n\
+             /desktop-icons/style\n",
+        ));
+        let xfce = Xfce::new(runner.clone());
+
+        xfce.set_wallpaper(Path::new("/tmp/a.jpg")).unwrap();
+        assert!(!xfce.supports_lock_screen());
+
+        let prefix = "xfconf-query -c xfce4-desktop -p /backdrop/screen0";
+        assert_eq!(
+            runner.calls(),
+            [
+                "xfconf-query -c xfce4-desktop -l".to_string(),
+                format!("{prefix}/monitorHDMI-1/workspace0/last-image -n -t string -s /tmp/a.jpg"),
+                format!("{prefix}/monitorHDMI-1/workspace0/image-style -n -t int -s 5"),
+                format!("{prefix}/monitoreDP-1/workspace0/last-image -n -t string -s /tmp/a.jpg"),
+                format!("{prefix}/monitoreDP-1/workspace0/image-style -n -t int -s 5"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_set_wallpaper_without_properties() {
+        let runner = Arc::new(FakeRunner::new(&["xfconf-query"]));
+        Xfce::new(runner.clone())
+            .set_wallpaper(Path::new("/tmp/a.jpg"))
+            .unwrap();
+
+        assert_eq!(
+            runner.calls()[1],
+            format!(
+                "xfconf-query -c xfce4-desktop -p {DEFAULT_PROPERTY} -n -t string -s /tmp/a.jpg"
+            )
+        );
+    }
+
+    #[test]
+    fn test_set_monitor_wallpapers() {
+        let runner = Arc::new(FakeRunner::new(&["xfconf-query"]).output(
+            "xfconf-query -c xfce4-desktop -l",
+            "/backdrop/screen0/monitorHDMI-1/workspace0/last-image\n\
+             /backdrop/screen0/monitorHDMI-1/workspace1/last-image\n",
+        ));
+        Xfce::new(runner.clone())
+            .set_monitor_wallpapers(&[
+                (
+                    Monitor::new(0, 0, 1, 1).id("HDMI-1"),
+                    PathBuf::from("/tmp/a.jpg"),
+                ),
+                (
+                    Monitor::new(1, 0, 1, 1).id("DP-1"),
+                    PathBuf::from("/tmp/b.jpg"),
+                ),
+            ])
+            .unwrap();
+
+        let prefix = "xfconf-query -c xfce4-desktop -p /backdrop/screen0";
+        let images = runner
+            .calls()
+            .into_iter()
+            .filter(|call| call.contains("-t string"))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            images,
+            [
+                format!("{prefix}/monitorHDMI-1/workspace0/last-image -n -t string -s /tmp/a.jpg"),
+                format!("{prefix}/monitorHDMI-1/workspace1/last-image -n -t string -s /tmp/a.jpg"),
+                format!("{prefix}/monitorDP-1/workspace0/last-image -n -t string -s /tmp/b.jpg"),
+            ]
+        );
+    }
+}